@@ -0,0 +1,33 @@
+create table schedule
+(
+    schedule_id                 uuid,
+    schedule_cron               varchar(256),
+    schedule_interval_seconds   bigint,
+    schedule_run_spec           jsonb       not null,
+    schedule_next_fire_datetime timestamptz not null,
+    schedule_insertion_datetime timestamp   not null default localtimestamp,
+    primary key (schedule_id),
+    constraint ck_trigger
+        check ((schedule_cron is null) <> (schedule_interval_seconds is null))
+);
+
+create index ix_schedule_next_fire_datetime on schedule (schedule_next_fire_datetime);
+
+create table schedule_firing
+(
+    schedule_id     uuid        not null,
+    firing_datetime timestamptz not null,
+    run_id          uuid,
+    constraint fk_schedule
+        foreign key (schedule_id)
+            references schedule (schedule_id)
+            on delete cascade,
+    constraint fk_run
+        foreign key (run_id)
+            references run (run_id)
+);
+
+create index ix_schedule_firing_schedule_id on schedule_firing (schedule_id);
+
+grant select, insert, update, delete on schedule to faulty_server_poller_service;
+grant select, insert on schedule_firing to faulty_server_poller_service;
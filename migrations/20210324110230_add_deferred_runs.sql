@@ -0,0 +1,6 @@
+alter table run
+    add column run_start_datetime timestamptz;
+
+insert into run_status (status_id, status_name)
+values (3, 'SCHEDULED'),
+       (4, 'CANCELLED');
@@ -0,0 +1,3 @@
+alter table run
+    add column run_requested_seconds bigint,
+    add column run_effective_seconds bigint;
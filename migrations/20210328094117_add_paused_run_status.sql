@@ -0,0 +1,2 @@
+insert into run_status (status_id, status_name)
+values (5, 'PAUSED');
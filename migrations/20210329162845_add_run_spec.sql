@@ -0,0 +1,3 @@
+alter table run
+    add column run_spec     jsonb,
+    add column run_rerun_of uuid references run (run_id);
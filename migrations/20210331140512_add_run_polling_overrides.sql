@@ -0,0 +1,3 @@
+alter table run
+    add column run_concurrent_requests integer,
+    add column run_polling_address     varchar(2048);
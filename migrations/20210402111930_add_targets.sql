@@ -0,0 +1,16 @@
+create table target
+(
+    target_id                 uuid,
+    target_url                varchar(2048) not null,
+    target_method             varchar(16)   not null,
+    target_headers            jsonb         not null,
+    target_timeout_ms         bigint,
+    target_response_schema    jsonb         not null,
+    target_insertion_datetime timestamp     not null default localtimestamp,
+    primary key (target_id)
+);
+
+alter table run
+    add column run_target_id uuid;
+
+grant select, insert, update, delete on target to faulty_server_poller_service;
@@ -0,0 +1,3 @@
+alter table run
+    alter column run_value_sum type numeric(40) using run_value_sum::numeric(40),
+    add column run_sum_overflowed boolean not null default false;
@@ -0,0 +1,13 @@
+create table run_stats
+(
+    run_id              uuid references run (run_id),
+    run_stats_count     bigint not null,
+    run_stats_min       bigint,
+    run_stats_max       bigint,
+    run_stats_mean      double precision,
+    run_stats_variance  double precision,
+    run_stats_histogram jsonb  not null,
+    primary key (run_id)
+);
+
+grant select, insert on run_stats to faulty_server_poller_service;
@@ -0,0 +1,2 @@
+alter table run
+    add column run_aggregates jsonb;
@@ -0,0 +1,12 @@
+create table run_sample
+(
+    run_id                uuid references run (run_id),
+    run_sample_datetime   timestamptz not null,
+    run_sample_attempts   bigint      not null,
+    run_sample_successes  bigint      not null,
+    run_sample_errors     bigint      not null,
+    run_sample_value_sum  numeric(40) not null,
+    primary key (run_id, run_sample_datetime)
+);
+
+grant select, insert on run_sample to faulty_server_poller_service;
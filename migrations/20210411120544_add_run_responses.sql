@@ -0,0 +1,17 @@
+create table run_response
+(
+    run_response_id         bigserial,
+    run_id                  uuid references run (run_id),
+    run_response_datetime   timestamptz not null,
+    run_response_latency_us bigint      not null,
+    run_response_slot       integer     not null,
+    run_response_status     smallint,
+    run_response_body       jsonb,
+    run_response_error      text,
+    primary key (run_response_id)
+);
+
+create index run_response_run_id_idx on run_response (run_id);
+
+grant select, insert on run_response to faulty_server_poller_service;
+grant usage on sequence run_response_run_response_id_seq to faulty_server_poller_service;
@@ -0,0 +1,45 @@
+alter table run
+    add column run_callback_url varchar(2048);
+
+create table webhook_delivery_status
+(
+    status_id   smallint,
+    status_name varchar(256) not null,
+    primary key (status_id)
+);
+
+insert into webhook_delivery_status (status_id, status_name)
+values (0, 'PENDING'),
+       (1, 'DELIVERED'),
+       (2, 'FAILED');
+
+create table webhook_delivery
+(
+    webhook_delivery_id                    bigserial,
+    run_id                                 uuid          not null references run (run_id),
+    webhook_delivery_url                   varchar(2048) not null,
+    status_id                              smallint      not null default 0
+        references webhook_delivery_status (status_id),
+    webhook_delivery_attempts              integer       not null default 0,
+    webhook_delivery_next_attempt_datetime timestamptz,
+    primary key (webhook_delivery_id),
+    unique (run_id)
+);
+
+create index webhook_delivery_next_attempt_idx
+    on webhook_delivery (webhook_delivery_next_attempt_datetime)
+    where webhook_delivery_next_attempt_datetime is not null;
+
+create table webhook_delivery_attempt
+(
+    webhook_delivery_id               bigint references webhook_delivery (webhook_delivery_id),
+    webhook_delivery_attempt_datetime timestamptz not null,
+    webhook_delivery_attempt_status   smallint,
+    webhook_delivery_attempt_error    text,
+    primary key (webhook_delivery_id, webhook_delivery_attempt_datetime)
+);
+
+grant select on webhook_delivery_status to faulty_server_poller_service;
+grant select, insert, update on webhook_delivery to faulty_server_poller_service;
+grant usage on sequence webhook_delivery_webhook_delivery_id_seq to faulty_server_poller_service;
+grant select, insert on webhook_delivery_attempt to faulty_server_poller_service;
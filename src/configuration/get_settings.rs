@@ -24,5 +24,8 @@ pub fn get_settings() -> Result<Settings> {
 
     conf.merge(config::Environment::with_prefix("app").separator("__"))?;
 
-    Ok(conf.try_into()?)
+    let settings: Settings = conf.try_into()?;
+    settings.validate()?;
+
+    Ok(settings)
 }
@@ -1,12 +1,30 @@
+use actix_web::http::header::AUTHORIZATION;
+use actix_web::HttpRequest;
+use serde::{Deserialize, Deserializer};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::PgConnectOptions;
+use std::fmt::Display;
 use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+
+use crate::polling::errors::{ServiceError, ServiceResult};
+
+pub const DEFAULT_HISTOGRAM_BUCKETS: [i64; 9] = [-1000, -100, -10, 0, 10, 50, 100, 1000, 10000];
+pub const DEFAULT_MAX_SCHEDULED_RUNS: usize = 1000;
 
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct Settings {
     pub application: ApplicationSettings,
     pub database: DatabaseSettings,
     pub polling: PollingSettings,
+    pub scheduling: SchedulingSettings,
+    pub webhooks: WebhookSettings,
+    #[serde(default)]
+    pub telemetry: TelemetrySettings,
+    #[serde(default)]
+    pub logging: LoggingSettings,
+    #[serde(default)]
+    pub admin: AdminSettings,
 }
 
 #[derive(serde::Deserialize, Clone, Debug)]
@@ -37,6 +55,120 @@ pub struct PollingSettings {
     pub max_pending_runs: usize,
     #[serde(deserialize_with = "deserialize_number_from_string")]
     pub concurrent_requests_per_run: usize,
+    #[serde(default)]
+    pub max_pending_runs_per_priority: PriorityLimits,
+    #[serde(default, deserialize_with = "deserialize_number_from_string")]
+    pub priority_aging_seconds: u64,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_queue_wait_seconds: Option<u64>,
+    /// Most runs with a deferred start kept at once, `DEFAULT_MAX_SCHEDULED_RUNS` if not set
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_scheduled_runs: Option<usize>,
+    /// Most concurrent requests a run may ask for, `concurrent_requests_per_run` if not set
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub max_concurrent_requests_per_run: Option<usize>,
+    /// Addresses a run may poll instead of `polling_address`
+    #[serde(default)]
+    pub allowed_polling_addresses: Vec<String>,
+    /// Inclusive upper bounds of value histogram buckets, `DEFAULT_HISTOGRAM_BUCKETS` if empty
+    #[serde(default)]
+    pub histogram_buckets: Vec<i64>,
+    /// Period of run counter samples, 1 second if not set, 0 disables sampling
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub sample_interval_seconds: Option<u64>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SchedulingSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub check_interval_seconds: u64,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct WebhookSettings {
+    /// Key of the HMAC-SHA256 signatures of callback payloads, has no default
+    #[serde(default)]
+    pub secret: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub check_interval_seconds: u64,
+    /// Attempts of a delivery before it is given up
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for every following one
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub retry_base_seconds: u64,
+    /// Origins (scheme, host and port) callback URLs may be on, none if empty
+    #[serde(default)]
+    pub allowed_callback_origins: Vec<String>,
+}
+
+/// Where spans of runs and upstream requests are exported
+#[derive(serde::Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(tag = "exporter", rename_all = "snake_case")]
+pub enum TelemetrySettings {
+    #[default]
+    None,
+    Stdout,
+    /// Appends spans to a local file, one per line
+    File {
+        path: String,
+    },
+    /// OTLP over gRPC, e.g. to an OpenTelemetry collector at `http://localhost:4317`
+    Otlp {
+        endpoint: String,
+    },
+}
+
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct LoggingSettings {
+    #[serde(default)]
+    pub format: LogFormat,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum LogFormat {
+    /// One JSON object per line, with the fields of the event and of its spans
+    #[default]
+    Json,
+    /// Human-readable lines, for local development
+    Pretty,
+}
+
+#[derive(serde::Deserialize, Clone, Default)]
+pub struct AdminSettings {
+    /// Bearer token of the `/admin` endpoints, which refuse every request while it is not set
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Limits of pending runs by the priority they were started with
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+pub struct PriorityLimits {
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub high: Option<usize>,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub normal: Option<usize>,
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub low: Option<usize>,
+}
+
+impl Settings {
+    /// Rejects settings the service must not start with
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.webhooks.secret.is_empty() {
+            anyhow::bail!("Webhook secret is not set. Set APP_WEBHOOKS__SECRET to a private key");
+        }
+        let buckets = &self.polling.histogram_buckets;
+        if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+            anyhow::bail!(
+                "Histogram buckets must be strictly increasing, got {:?}",
+                buckets
+            );
+        }
+
+        Ok(())
+    }
 }
 
 impl ApplicationSettings {
@@ -45,6 +177,103 @@ impl ApplicationSettings {
     }
 }
 
+impl PollingSettings {
+    pub fn histogram_buckets(&self) -> Vec<i64> {
+        if self.histogram_buckets.is_empty() {
+            DEFAULT_HISTOGRAM_BUCKETS.to_vec()
+        } else {
+            self.histogram_buckets.clone()
+        }
+    }
+
+    pub fn sample_interval(&self) -> Option<std::time::Duration> {
+        match self.sample_interval_seconds {
+            Some(0) => None,
+            Some(seconds) => Some(std::time::Duration::from_secs(seconds)),
+            None => Some(std::time::Duration::from_secs(1)),
+        }
+    }
+
+    pub fn scheduled_runs_limit(&self) -> usize {
+        self.max_scheduled_runs
+            .unwrap_or(DEFAULT_MAX_SCHEDULED_RUNS)
+    }
+
+    pub fn concurrent_requests_limit(&self) -> usize {
+        self.max_concurrent_requests_per_run
+            .unwrap_or(self.concurrent_requests_per_run)
+    }
+
+    pub fn is_polling_address_allowed(&self, address: &str) -> bool {
+        address == self.polling_address
+            || self
+                .allowed_polling_addresses
+                .iter()
+                .any(|allowed| allowed == address)
+    }
+
+    /// Targets may only be defined on the origins of the addresses runs may poll
+    pub fn is_target_url_allowed(&self, url: &reqwest::Url) -> bool {
+        std::iter::once(&self.polling_address)
+            .chain(&self.allowed_polling_addresses)
+            .filter_map(|address| reqwest::Url::parse(address).ok())
+            .any(|allowed| allowed.origin() == url.origin())
+    }
+}
+
+impl WebhookSettings {
+    pub fn is_callback_url_allowed(&self, url: &reqwest::Url) -> bool {
+        self.allowed_callback_origins
+            .iter()
+            .filter_map(|origin| reqwest::Url::parse(origin).ok())
+            .any(|allowed| allowed.origin() == url.origin())
+    }
+
+    /// Delay after the given number of failed attempts, at most an hour
+    pub fn retry_delay(&self, failed_attempts: u32) -> std::time::Duration {
+        let factor = 2u64.saturating_pow(failed_attempts.saturating_sub(1));
+        std::time::Duration::from_secs(self.retry_base_seconds.saturating_mul(factor).min(3600))
+    }
+}
+
+impl AdminSettings {
+    pub fn is_authorized(&self, token: &str) -> bool {
+        match self.token.as_deref() {
+            Some(expected) if !expected.is_empty() => {
+                // compared in constant time, so that the token cannot be guessed byte by byte
+                expected.len() == token.len()
+                    && expected
+                        .bytes()
+                        .zip(token.bytes())
+                        .fold(0, |diff, (a, b)| diff | (a ^ b))
+                        == 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Lets through requests carrying the admin token in `Authorization: Bearer <token>`
+    pub fn authorize(&self, request: &HttpRequest) -> ServiceResult<()> {
+        let token = request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match token {
+            Some(token) if self.is_authorized(token) => Ok(()),
+            _ => Err(ServiceError::Unauthorized),
+        }
+    }
+}
+
+impl std::fmt::Debug for AdminSettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AdminSettings")
+            .field("token", &self.token.as_ref().map(|_| "<hidden>"))
+            .finish()
+    }
+}
+
 impl DatabaseSettings {
     pub fn connection_options(&self) -> PgConnectOptions {
         PgConnectOptions::new()
@@ -55,3 +284,140 @@ impl DatabaseSettings {
             .database(&self.database)
     }
 }
+
+/// Unlike the `serde_aux` one, accepts owned strings, which is what environment variables are.
+fn deserialize_option_number_from_string<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Deserialize<'de>,
+    T::Err: Display,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString<T> {
+        Number(T),
+        String(String),
+    }
+
+    match Option::<NumberOrString<T>>::deserialize(deserializer)? {
+        Some(NumberOrString::Number(number)) => Ok(Some(number)),
+        Some(NumberOrString::String(s)) if s.is_empty() => Ok(None),
+        Some(NumberOrString::String(s)) => s.parse().map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    fn settings(webhook_secret: Option<&str>) -> Settings {
+        let mut conf = config::Config::default();
+        conf.merge(config::File::with_name("configuration/default"))
+            .unwrap();
+        if let Some(secret) = webhook_secret {
+            conf.set("webhooks.secret", secret).unwrap();
+        }
+        conf.try_into().unwrap()
+    }
+
+    /// Values of environment variables reach the settings as strings
+    fn settings_overridden_by_strings(overrides: &[(&str, &str)]) -> Settings {
+        let mut conf = config::Config::default();
+        conf.merge(config::File::with_name("configuration/default"))
+            .unwrap();
+        for (key, value) in overrides {
+            conf.set(key, *value).unwrap();
+        }
+        conf.try_into().unwrap()
+    }
+
+    #[test]
+    fn read_optional_numbers_from_strings() {
+        let settings = settings_overridden_by_strings(&[
+            ("polling.max_pending_runs_per_priority.high", "3"),
+            ("polling.max_pending_runs_per_priority.low", "1"),
+            ("polling.max_queue_wait_seconds", "30"),
+            ("polling.max_concurrent_requests_per_run", ""),
+        ]);
+
+        let limits = settings.polling.max_pending_runs_per_priority;
+        assert_eq!(
+            (Some(3), None, Some(1)),
+            (limits.high, limits.normal, limits.low)
+        );
+        assert_eq!(Some(30), settings.polling.max_queue_wait_seconds);
+        assert_eq!(None, settings.polling.max_concurrent_requests_per_run);
+    }
+
+    #[test]
+    fn allow_callbacks_to_allowed_origins_only() {
+        let polling_address = settings(Some("private key")).polling.polling_address;
+        let webhooks = WebhookSettings {
+            allowed_callback_origins: vec![
+                "https://callbacks.example.com".into(),
+                "http://localhost:9000".into(),
+            ],
+            ..settings(Some("private key")).webhooks
+        };
+        let allowed = |url: &str| webhooks.is_callback_url_allowed(&url.parse().unwrap());
+
+        assert!(allowed("https://callbacks.example.com/runs?finished=1"));
+        assert!(allowed("https://callbacks.example.com:443/runs"));
+        assert!(allowed("http://localhost:9000/callback"));
+        assert!(!allowed(&format!("{}/callback", polling_address)));
+        assert!(!allowed("http://callbacks.example.com/runs"));
+        assert!(!allowed("https://callbacks.example.com:8443/runs"));
+        assert!(!allowed("http://169.254.169.254/latest/meta-data"));
+    }
+
+    #[test]
+    fn authorize_admin_token() {
+        let admin = AdminSettings {
+            token: Some("admin token".into()),
+        };
+
+        assert!(admin.is_authorized("admin token"));
+        assert!(!admin.is_authorized("admin tokeN"));
+        assert!(!admin.is_authorized("admin"));
+        assert!(!admin.is_authorized(""));
+    }
+
+    #[test]
+    fn refuse_every_token_while_admin_token_is_not_set() {
+        for token in [None, Some(String::new())] {
+            let admin = AdminSettings { token };
+
+            assert!(!admin.is_authorized(""));
+            assert!(!admin.is_authorized("admin token"));
+        }
+    }
+
+    #[test]
+    fn accept_webhook_secret() {
+        assert!(settings(Some("private key")).validate().is_ok());
+    }
+
+    #[test]
+    fn reject_missing_webhook_secret() {
+        assert!(settings(None).validate().is_err());
+    }
+
+    #[test]
+    fn reject_empty_webhook_secret() {
+        assert!(settings(Some("")).validate().is_err());
+    }
+
+    #[test]
+    fn accept_only_strictly_increasing_histogram_buckets() {
+        let mut settings = settings(Some("secret"));
+        settings.polling.histogram_buckets = vec![0, 10, 100];
+        assert!(settings.validate().is_ok());
+
+        settings.polling.histogram_buckets = vec![0, 100, 100];
+        assert!(settings.validate().is_err());
+
+        settings.polling.histogram_buckets = vec![100, 0];
+        assert!(settings.validate().is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use actix_web::http::header::{CACHE_CONTROL, CONTENT_TYPE};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpResponse, Responder};
+use futures::stream;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::dto::RunEvent;
+use crate::events::event_bus::RunEventBus;
+
+/// Comments sent to idle streams, so that proxies do not close them
+const HEARTBEAT_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
+
+async fn stream_events(events: web::Data<RunEventBus>) -> impl Responder {
+    let receiver = events.subscribe();
+    let heartbeats = tokio::time::interval(HEARTBEAT_INTERVAL);
+
+    let messages = stream::unfold(
+        (receiver, heartbeats),
+        |(mut receiver, mut heartbeats)| async move {
+            let message = tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => server_sent_event(&event),
+                    // tells the client to catch up by reading the runs it follows
+                    Err(RecvError::Lagged(missed)) => format!("event: lagged\ndata: {}\n\n", missed),
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = heartbeats.tick() => ": heartbeat\n\n".to_string(),
+            };
+            Some((
+                Ok::<_, actix_web::Error>(Bytes::from(message)),
+                (receiver, heartbeats),
+            ))
+        },
+    );
+
+    HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "text/event-stream"))
+        .insert_header((CACHE_CONTROL, "no-cache"))
+        .streaming(Box::pin(messages))
+}
+
+fn server_sent_event(event: &RunEvent) -> String {
+    format!(
+        "event: {}\ndata: {}\n\n",
+        event.name(),
+        serde_json::to_string(event).expect("Failed to serialize run event")
+    )
+}
+
+pub fn configure(events: web::Data<RunEventBus>, cfg: &mut web::ServiceConfig) {
+    cfg.app_data(events);
+    cfg.route("/events", web::get().to(stream_events));
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::events::dto::RunEventKind;
+    use crate::polling::dto::RunId;
+    use actix_web::body::MessageBody;
+    use actix_web::{test, App};
+
+    async fn next_message<B: MessageBody + Unpin>(body: &mut B) -> Bytes {
+        futures::future::poll_fn(|cx| std::pin::Pin::new(&mut *body).poll_next(cx))
+            .await
+            .expect("Stream ended")
+            .map_err(|_| "Stream failed")
+            .unwrap()
+    }
+
+    #[actix_rt::test]
+    async fn stream_published_events() {
+        let events = RunEventBus::default();
+        let run_id = RunId::new_v4();
+
+        let app = {
+            let events = web::Data::new(events.clone());
+            test::init_service(App::new().configure(|cfg| configure(events, cfg))).await
+        };
+
+        let request = test::TestRequest::get().uri("/events").to_request();
+
+        let mut response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+        assert_eq!(
+            "text/event-stream",
+            response.headers().get(CONTENT_TYPE).unwrap()
+        );
+
+        let mut body = response.take_body();
+        assert_eq!(
+            Bytes::from(": heartbeat\n\n"),
+            next_message(&mut body).await
+        );
+
+        events.publish(run_id, RunEventKind::Started);
+
+        let message = next_message(&mut body).await;
+        let message = std::str::from_utf8(&message).unwrap();
+        let data = message
+            .strip_prefix("event: started\ndata: ")
+            .and_then(|message| message.strip_suffix("\n\n"))
+            .unwrap();
+        let event: RunEvent = serde_json::from_str(data).unwrap();
+        assert_eq!((run_id, RunEventKind::Started), (event.run_id, event.kind));
+    }
+}
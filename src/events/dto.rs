@@ -0,0 +1,102 @@
+use chrono::{DateTime, Utc};
+
+use crate::polling::dto::RunId;
+
+/// State transition of a run
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RunEvent {
+    pub run_id: RunId,
+    pub at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: RunEventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunEventKind {
+    Queued {
+        estimated_start_at: DateTime<Utc>,
+    },
+    Started,
+    /// Counters of the run since its previous progress
+    Progress {
+        attempts: u64,
+        successes: u64,
+        errors: u64,
+    },
+    Paused,
+    Resumed,
+    Finished {
+        successful_responses_count: u64,
+    },
+    Cancelled,
+    /// Dropped without being executed, or its results could not be saved
+    Failed {
+        reason: String,
+    },
+}
+
+impl RunEvent {
+    pub fn new(run_id: RunId, kind: RunEventKind) -> Self {
+        Self {
+            run_id,
+            at: Utc::now(),
+            kind,
+        }
+    }
+
+    /// Same as the `type` of the serialized event
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            RunEventKind::Queued { .. } => "queued",
+            RunEventKind::Started => "started",
+            RunEventKind::Progress { .. } => "progress",
+            RunEventKind::Paused => "paused",
+            RunEventKind::Resumed => "resumed",
+            RunEventKind::Finished { .. } => "finished",
+            RunEventKind::Cancelled => "cancelled",
+            RunEventKind::Failed { .. } => "failed",
+        }
+    }
+
+    /// Whether the run will not change after this event
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self.kind,
+            RunEventKind::Finished { .. } | RunEventKind::Cancelled | RunEventKind::Failed { .. }
+        )
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn serialize_kind_as_type_of_event() {
+        let event = RunEvent {
+            run_id: RunId::nil(),
+            at: Utc.ymd(2021, 4, 1).and_hms(12, 30, 0),
+            kind: RunEventKind::Progress {
+                attempts: 3,
+                successes: 2,
+                errors: 1,
+            },
+        };
+
+        assert_eq!(
+            json!({
+                "run_id": "00000000-0000-0000-0000-000000000000",
+                "at": "2021-04-01T12:30:00Z",
+                "type": event.name(),
+                "attempts": 3,
+                "successes": 2,
+                "errors": 1,
+            }),
+            serde_json::to_value(&event).unwrap()
+        );
+        assert_eq!("progress", event.name());
+    }
+}
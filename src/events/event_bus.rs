@@ -0,0 +1,33 @@
+use tokio::sync::broadcast;
+
+use crate::events::dto::{RunEvent, RunEventKind};
+use crate::polling::dto::RunId;
+
+/// Events kept for the slowest subscriber, it misses the older ones
+const EVENT_BUFFER: usize = 4096;
+
+/// Hands run events published by the job runner and the polling service
+/// to every in-process subscriber, such as the event stream or finished run waiters.
+#[derive(Clone, Debug)]
+pub struct RunEventBus {
+    sender: broadcast::Sender<RunEvent>,
+}
+
+impl Default for RunEventBus {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(EVENT_BUFFER).0,
+        }
+    }
+}
+
+impl RunEventBus {
+    pub fn publish(&self, run_id: RunId, kind: RunEventKind) {
+        // nobody may be subscribed
+        let _ = self.sender.send(RunEvent::new(run_id, kind));
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RunEvent> {
+        self.sender.subscribe()
+    }
+}
@@ -0,0 +1,3 @@
+pub mod controller;
+pub mod dto;
+pub mod event_bus;
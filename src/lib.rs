@@ -1,3 +1,9 @@
 pub mod configuration;
+pub mod events;
 pub mod health_check;
+pub mod metrics;
 pub mod polling;
+pub mod scheduling;
+pub mod targets;
+pub mod telemetry;
+pub mod webhooks;
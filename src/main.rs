@@ -1,12 +1,30 @@
-use actix_web::middleware::Logger;
 use actix_web::{web, App, HttpServer};
 use faulty_server_poller::configuration::get_settings;
-use faulty_server_poller::configuration::settings::Settings;
+use faulty_server_poller::configuration::settings::{AdminSettings, Settings};
+use faulty_server_poller::events::event_bus::RunEventBus;
+use faulty_server_poller::metrics::middleware::HttpMetrics;
+use faulty_server_poller::metrics::registry::Metrics;
 use faulty_server_poller::polling::background_job_runner::TokioBackgroundJobRunner;
+use faulty_server_poller::polling::finished_run_notifier::FinishedRunNotifier;
 use faulty_server_poller::polling::polling_service::{PollingService, PollingServiceImpl};
-use faulty_server_poller::polling::request_sender::ReqwestRequestSender;
-use faulty_server_poller::polling::run_repository::PostgresRunRepository;
+use faulty_server_poller::polling::request_sender::{MeteredRequestSender, ReqwestRequestSender};
+use faulty_server_poller::polling::run_repository::{MeteredRunRepository, PostgresRunRepository};
+use faulty_server_poller::scheduling::schedule_repository::PostgresScheduleRepository;
+use faulty_server_poller::scheduling::scheduler::Scheduler;
+use faulty_server_poller::scheduling::scheduling_service::{
+    SchedulingService, SchedulingServiceImpl,
+};
+use faulty_server_poller::targets::target_repository::PostgresTargetRepository;
+use faulty_server_poller::targets::target_service::{TargetService, TargetServiceImpl};
+use faulty_server_poller::telemetry::log_filter::LogFilter;
+use faulty_server_poller::telemetry::middleware::RequestTracing;
+use faulty_server_poller::telemetry::{init_telemetry, shutdown_telemetry};
+use faulty_server_poller::webhooks::delivery_repository::PostgresDeliveryRepository;
+use faulty_server_poller::webhooks::delivery_service::{DeliveryService, DeliveryServiceImpl};
+use faulty_server_poller::webhooks::dispatcher::WebhookDispatcher;
+use faulty_server_poller::webhooks::webhook_sender::ReqwestWebhookSender;
 use sqlx::postgres::PgPoolOptions;
+use sqlx::PgPool;
 
 #[actix_web::main]
 async fn main() {
@@ -14,21 +32,81 @@ async fn main() {
 }
 
 async fn run_app() {
-    pretty_env_logger::init();
     let settings = get_settings().expect("Failed to get configuration");
-    let polling_service = build_polling_service(&settings).await;
+    let log_filter = init_telemetry(&settings.telemetry, &settings.logging);
+    let db_pool = build_db_pool(&settings).await;
+    let metrics = Metrics::default();
+    let target_repo = PostgresTargetRepository::new(db_pool.clone());
+    let postgres_run_repo = PostgresRunRepository::new(db_pool.clone());
+    let run_repo = MeteredRunRepository::new(postgres_run_repo.clone(), metrics.clone());
+    let events = RunEventBus::default();
+    let finished_runs = FinishedRunNotifier::default();
+    let polling_service = build_polling_service(
+        &settings,
+        run_repo.clone(),
+        target_repo.clone(),
+        events.clone(),
+        finished_runs.clone(),
+        metrics.clone(),
+    )
+    .await;
+    let target_service = TargetServiceImpl::new(target_repo.clone(), settings.polling.clone());
+    let delivery_repo = PostgresDeliveryRepository::new(db_pool.clone());
+    let delivery_service = DeliveryServiceImpl::new(delivery_repo.clone(), run_repo.clone());
+    let schedule_repo = PostgresScheduleRepository::new(db_pool);
+    let scheduling_service = SchedulingServiceImpl::new(
+        schedule_repo.clone(),
+        target_repo,
+        settings.polling.clone(),
+        settings.webhooks.clone(),
+    );
+
+    actix_web::rt::spawn(
+        finished_runs
+            .clone()
+            .forward_finished_events(events.clone()),
+    );
+    actix_web::rt::spawn(postgres_run_repo.forward_finished_runs(finished_runs));
+    actix_web::rt::spawn(
+        Scheduler::new(
+            schedule_repo,
+            polling_service.clone(),
+            settings.scheduling.clone(),
+        )
+        .run(),
+    );
+    actix_web::rt::spawn(
+        WebhookDispatcher::new(
+            delivery_repo,
+            run_repo,
+            ReqwestWebhookSender::new(settings.webhooks.secret.clone()),
+            settings.webhooks.clone(),
+        )
+        .run(),
+    );
 
+    let admin = settings.admin.clone();
     HttpServer::new(move || {
-        App::new().wrap(Logger::default()).configure(|cfg| {
-            configure_health_check(cfg);
-            configure_poller(cfg, polling_service.clone());
-        })
+        App::new()
+            .wrap(HttpMetrics::new(metrics.clone()))
+            .wrap(RequestTracing)
+            .configure(|cfg| {
+                configure_health_check(cfg);
+                configure_log_filter(cfg, log_filter.clone(), admin.clone());
+                configure_events(cfg, events.clone());
+                configure_metrics(cfg, metrics.clone(), polling_service.clone());
+                configure_poller(cfg, polling_service.clone());
+                configure_scheduling(cfg, scheduling_service.clone());
+                configure_targets(cfg, target_service.clone(), admin.clone());
+                configure_webhooks(cfg, delivery_service.clone());
+            })
     })
     .bind(settings.application.address())
     .expect("Unable to bind server to an address")
     .run()
     .await
     .expect("Failed to run the server");
+    shutdown_telemetry();
 }
 
 fn configure_health_check(cfg: &mut web::ServiceConfig) {
@@ -37,6 +115,36 @@ fn configure_health_check(cfg: &mut web::ServiceConfig) {
     controller::configure(cfg);
 }
 
+fn configure_log_filter(cfg: &mut web::ServiceConfig, log_filter: LogFilter, admin: AdminSettings) {
+    use faulty_server_poller::telemetry::controller;
+
+    let log_filter = web::Data::new(log_filter);
+    let admin = web::Data::new(admin);
+
+    controller::configure(log_filter, admin, cfg);
+}
+
+fn configure_events(cfg: &mut web::ServiceConfig, events: RunEventBus) {
+    use faulty_server_poller::events::controller;
+
+    let events = web::Data::new(events);
+
+    controller::configure(events, cfg);
+}
+
+fn configure_metrics(
+    cfg: &mut web::ServiceConfig,
+    metrics: Metrics,
+    service: impl PollingService + 'static,
+) {
+    use faulty_server_poller::metrics::controller;
+
+    let metrics = web::Data::new(metrics);
+    let service = web::Data::new(service);
+
+    controller::configure(metrics, service, cfg);
+}
+
 fn configure_poller(cfg: &mut web::ServiceConfig, service: impl PollingService + 'static) {
     use faulty_server_poller::polling::controller;
 
@@ -45,29 +153,82 @@ fn configure_poller(cfg: &mut web::ServiceConfig, service: impl PollingService +
     controller::configure(service, cfg);
 }
 
+fn configure_scheduling(cfg: &mut web::ServiceConfig, service: impl SchedulingService + 'static) {
+    use faulty_server_poller::scheduling::controller;
+
+    let service = web::Data::new(service);
+
+    controller::configure(service, cfg);
+}
+
+fn configure_targets(
+    cfg: &mut web::ServiceConfig,
+    service: impl TargetService + 'static,
+    admin: AdminSettings,
+) {
+    use faulty_server_poller::targets::controller;
+
+    let service = web::Data::new(service);
+    let admin = web::Data::new(admin);
+
+    controller::configure(service, admin, cfg);
+}
+
+fn configure_webhooks(cfg: &mut web::ServiceConfig, service: impl DeliveryService + 'static) {
+    use faulty_server_poller::webhooks::controller;
+
+    let service = web::Data::new(service);
+
+    controller::configure(service, cfg);
+}
+
+type RunRepositoryType = MeteredRunRepository<PostgresRunRepository>;
+
 type PollingServiceType = PollingServiceImpl<
-    PostgresRunRepository,
-    TokioBackgroundJobRunner<PostgresRunRepository, ReqwestRequestSender>,
+    RunRepositoryType,
+    TokioBackgroundJobRunner<RunRepositoryType, MeteredRequestSender<ReqwestRequestSender>>,
+    PostgresTargetRepository,
 >;
 
-async fn build_polling_service(settings: &Settings) -> PollingServiceType {
-    let db_pool = PgPoolOptions::new()
+async fn build_db_pool(settings: &Settings) -> PgPool {
+    PgPoolOptions::new()
         .connect_timeout(std::time::Duration::from_secs(
             settings.database.connect_timeout_sec,
         ))
         .connect_with(settings.database.connection_options())
         .await
-        .expect("Failed to connect to database");
-    let run_repo = PostgresRunRepository::new(db_pool);
+        .expect("Failed to connect to database")
+}
 
-    let request_sender = ReqwestRequestSender::new(
-        reqwest::Client::new(),
-        settings.polling.polling_address.clone(),
+async fn build_polling_service(
+    settings: &Settings,
+    run_repo: RunRepositoryType,
+    target_repo: PostgresTargetRepository,
+    events: RunEventBus,
+    finished_runs: FinishedRunNotifier,
+    metrics: Metrics,
+) -> PollingServiceType {
+    let request_sender = MeteredRequestSender::new(
+        ReqwestRequestSender::new(reqwest::Client::new()),
+        metrics.clone(),
     );
 
-    let job_runner =
-        TokioBackgroundJobRunner::new(run_repo.clone(), request_sender, settings.polling.clone())
-            .await;
+    let job_runner = TokioBackgroundJobRunner::new(
+        run_repo.clone(),
+        request_sender,
+        settings.polling.clone(),
+        events.clone(),
+        metrics,
+    )
+    .await;
 
-    PollingServiceImpl::new(run_repo, job_runner)
+    PollingServiceImpl::new(
+        run_repo,
+        job_runner,
+        target_repo,
+        settings.polling.clone(),
+        settings.webhooks.clone(),
+        events,
+        finished_runs,
+    )
 }
@@ -0,0 +1,77 @@
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::{web, HttpResponse, Responder};
+use prometheus::{Encoder, TextEncoder};
+
+use crate::metrics::registry::Metrics;
+use crate::polling::errors::ServiceResult;
+use crate::polling::polling_service::PollingService;
+
+async fn get_metrics<T: PollingService>(
+    metrics: web::Data<Metrics>,
+    service: web::Data<T>,
+) -> ServiceResult<impl Responder> {
+    // gauges of the runs are read from the job runner when scraped
+    metrics.observe_queue(&service.get_queue().await?);
+
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, TextEncoder::new().format_type()))
+        .body(metrics.encode()))
+}
+
+pub fn configure<T: 'static + PollingService>(
+    metrics: web::Data<Metrics>,
+    service: web::Data<T>,
+    cfg: &mut web::ServiceConfig,
+) {
+    cfg.app_data(metrics);
+    cfg.app_data(service);
+    cfg.route("/metrics", web::get().to(get_metrics::<T>));
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::{QueueInfo, RunId, RunningRunInfo};
+    use crate::polling::polling_service::MockPollingService;
+    use actix_web::{test, App};
+
+    #[actix_rt::test]
+    async fn get_metrics_with_runs_of_queue() {
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_get_queue().return_const(Ok(QueueInfo {
+                running: vec![RunningRunInfo {
+                    id: RunId::new_v4(),
+                    seconds: 30,
+                    started_at: chrono::Utc::now(),
+                    remaining_seconds: 10,
+                    paused: false,
+                }],
+                ..QueueInfo::default()
+            }));
+            web::Data::new(ps)
+        };
+        let metrics = web::Data::new(Metrics::default());
+
+        let app = test::init_service(
+            App::new().configure(|cfg| configure(metrics, polling_service, cfg)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/metrics").to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+        assert_eq!(
+            "text/plain; version=0.0.4",
+            response.headers().get(CONTENT_TYPE).unwrap()
+        );
+
+        let body = test::read_body(response).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body
+            .lines()
+            .any(|l| l == r#"poller_runs{state="running"} 1"#));
+    }
+}
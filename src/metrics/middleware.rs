@@ -0,0 +1,119 @@
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures::future::{self, LocalBoxFuture};
+
+use crate::metrics::registry::Metrics;
+
+/// Label of requests that matched no route
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Counts and times the requests served by the API, per route.
+pub struct HttpMetrics {
+    metrics: Metrics,
+}
+
+impl HttpMetrics {
+    pub fn new(metrics: Metrics) -> Self {
+        Self { metrics }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for HttpMetrics
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = HttpMetricsMiddleware<S>;
+    type InitError = ();
+    type Future = future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ok(HttpMetricsMiddleware {
+            service,
+            metrics: self.metrics.clone(),
+        })
+    }
+}
+
+pub struct HttpMetricsMiddleware<S> {
+    service: S,
+    metrics: Metrics,
+}
+
+impl<S, B> Service<ServiceRequest> for HttpMetricsMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let method = req.method().to_string();
+        let metrics = self.metrics.clone();
+        let response = self.service.call(req);
+
+        Box::pin(async move {
+            let response = response.await?;
+            // resolved by the router, so a run id does not make a series of its own
+            let route = response.request().match_pattern();
+            metrics.observe_http_request(
+                &method,
+                route.as_deref().unwrap_or(UNMATCHED_ROUTE),
+                response.status().as_u16(),
+                started.elapsed(),
+            );
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn not_found() -> HttpResponse {
+        HttpResponse::NotFound().finish()
+    }
+
+    #[actix_rt::test]
+    async fn count_requests_per_route() {
+        let metrics = Metrics::default();
+        let app = test::init_service(
+            App::new()
+                .wrap(HttpMetrics::new(metrics.clone()))
+                .route("/runs/{id}", web::get().to(not_found)),
+        )
+        .await;
+
+        for uri in &["/runs/1", "/runs/2", "/unknown"] {
+            let request = test::TestRequest::get().uri(uri).to_request();
+            test::call_service(&app, request).await;
+        }
+
+        let encoded = metrics.encode();
+        for line in &[
+            r#"poller_http_requests_total{method="GET",route="/runs/{id}",status="404"} 2"#,
+            r#"poller_http_requests_total{method="GET",route="unmatched",status="404"} 1"#,
+        ] {
+            assert!(
+                encoded.lines().any(|l| l == *line),
+                "{} is not in {}",
+                line,
+                encoded
+            );
+        }
+    }
+}
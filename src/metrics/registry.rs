@@ -0,0 +1,230 @@
+use prometheus::{
+    Encoder, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+
+use crate::polling::dto::{FaultyServerResponse, PolledResponse, QueueInfo};
+
+/// Bounds of latency histograms, in seconds
+const LATENCY_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Metrics of the service, registered in its own registry and served by `/metrics`.
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    upstream_requests: IntCounterVec,
+    upstream_request_duration: Histogram,
+    runs: IntGaugeVec,
+    rejected_runs: IntCounter,
+    repository_query_duration: HistogramVec,
+    http_requests: IntCounterVec,
+    http_request_duration: HistogramVec,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        let registry = Registry::new();
+        let metrics = Self {
+            upstream_requests: IntCounterVec::new(
+                Opts::new(
+                    "poller_upstream_requests_total",
+                    "Requests sent to polled servers",
+                ),
+                &["outcome", "status"],
+            )
+            .unwrap(),
+            upstream_request_duration: Histogram::with_opts(
+                HistogramOpts::new(
+                    "poller_upstream_request_duration_seconds",
+                    "Latency of requests sent to polled servers",
+                )
+                .buckets(LATENCY_BUCKETS.to_vec()),
+            )
+            .unwrap(),
+            runs: IntGaugeVec::new(
+                Opts::new("poller_runs", "Runs held by the job runner"),
+                &["state"],
+            )
+            .unwrap(),
+            rejected_runs: IntCounter::new(
+                "poller_rejected_runs_total",
+                "Runs the job runner had no room for",
+            )
+            .unwrap(),
+            repository_query_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "poller_repository_query_duration_seconds",
+                    "Duration of run repository queries",
+                )
+                .buckets(LATENCY_BUCKETS.to_vec()),
+                &["query"],
+            )
+            .unwrap(),
+            http_requests: IntCounterVec::new(
+                Opts::new("poller_http_requests_total", "Requests served by the API"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_request_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "poller_http_request_duration_seconds",
+                    "Time to respond to requests served by the API",
+                )
+                .buckets(LATENCY_BUCKETS.to_vec()),
+                &["method", "route"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.upstream_requests.clone()),
+            Box::new(metrics.upstream_request_duration.clone()),
+            Box::new(metrics.runs.clone()),
+            Box::new(metrics.rejected_runs.clone()),
+            Box::new(metrics.repository_query_duration.clone()),
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_request_duration.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("Failed to register metric");
+        }
+        metrics
+    }
+}
+
+impl std::fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Metrics").finish()
+    }
+}
+
+impl Metrics {
+    pub fn observe_upstream_request(
+        &self,
+        response: &PolledResponse,
+        latency: std::time::Duration,
+    ) {
+        let outcome = match response.result {
+            FaultyServerResponse::Ok { .. } => "ok",
+            FaultyServerResponse::Err { .. } => "error",
+        };
+        let status = response
+            .status
+            .map_or_else(|| "none".to_string(), |status| status.to_string());
+        self.upstream_requests
+            .with_label_values(&[outcome, &status])
+            .inc();
+        self.upstream_request_duration
+            .observe(latency.as_secs_f64());
+    }
+
+    pub fn observe_queue(&self, queue: &QueueInfo) {
+        for (state, count) in &[
+            ("scheduled", queue.scheduled.len()),
+            ("pending", queue.pending.len()),
+            ("running", queue.running.len()),
+        ] {
+            self.runs.with_label_values(&[state]).set(*count as i64);
+        }
+    }
+
+    pub fn count_rejected_run(&self) {
+        self.rejected_runs.inc();
+    }
+
+    pub fn observe_repository_query(&self, query: &str, duration: std::time::Duration) {
+        self.repository_query_duration
+            .with_label_values(&[query])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// Requests are labelled by the pattern of their route, so that run ids do not make new series
+    pub fn observe_http_request(
+        &self,
+        method: &str,
+        route: &str,
+        status: u16,
+        duration: std::time::Duration,
+    ) {
+        self.http_requests
+            .with_label_values(&[method, route, &status.to_string()])
+            .inc();
+        self.http_request_duration
+            .with_label_values(&[method, route])
+            .observe(duration.as_secs_f64());
+    }
+
+    /// All metrics in Prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Failed to encode metrics");
+        String::from_utf8(buffer).expect("Metrics are not UTF-8")
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::{PendingRunInfo, RunId, RunPriority};
+    use chrono::Utc;
+
+    #[test]
+    fn encode_observed_metrics() {
+        let metrics = Metrics::default();
+
+        metrics.observe_upstream_request(
+            &PolledResponse {
+                status: Some(503),
+                body: String::new(),
+                result: FaultyServerResponse::Err {
+                    error: "Service unavailable".into(),
+                },
+            },
+            std::time::Duration::from_millis(3),
+        );
+        metrics.observe_queue(&QueueInfo {
+            scheduled: Vec::new(),
+            pending: vec![PendingRunInfo {
+                id: RunId::new_v4(),
+                seconds: 30,
+                priority: RunPriority::Normal,
+                enqueued_at: Utc::now(),
+                estimated_start_at: Utc::now(),
+                expires_at: None,
+            }],
+            running: Vec::new(),
+        });
+        metrics.count_rejected_run();
+        metrics.observe_http_request(
+            "GET",
+            "/runs/{id}",
+            404,
+            std::time::Duration::from_millis(1),
+        );
+
+        let encoded = metrics.encode();
+        for line in &[
+            r#"poller_upstream_requests_total{outcome="error",status="503"} 1"#,
+            r#"poller_upstream_request_duration_seconds_bucket{le="0.005"} 1"#,
+            r#"poller_runs{state="pending"} 1"#,
+            r#"poller_runs{state="running"} 0"#,
+            "poller_rejected_runs_total 1",
+            r#"poller_http_requests_total{method="GET",route="/runs/{id}",status="404"} 1"#,
+        ] {
+            assert!(
+                encoded.lines().any(|l| l == *line),
+                "{} is not in {}",
+                line,
+                encoded
+            );
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use serde_json::{json, Value};
+
+use crate::polling::aggregators::Aggregator;
+use crate::polling::dto::ValueSum;
+
+#[derive(Debug, Default)]
+pub struct CountAggregator {
+    count: u64,
+}
+
+impl CountAggregator {
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+}
+
+impl Aggregator for CountAggregator {
+    fn add(&mut self, _value: i64) {
+        self.count += 1;
+    }
+
+    fn result(&self) -> Value {
+        json!(self.count)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct SumAggregator {
+    sum: ValueSum,
+}
+
+impl SumAggregator {
+    pub fn sum(&self) -> ValueSum {
+        self.sum
+    }
+}
+
+impl Aggregator for SumAggregator {
+    fn add(&mut self, value: i64) {
+        self.sum.add(value);
+    }
+
+    /// The sum is a decimal string, JSON numbers of `serde_json` are limited to 64 bits
+    fn result(&self) -> Value {
+        json!({"sum": self.sum.sum.to_string(), "overflowed": self.sum.overflowed})
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MinMaxAggregator {
+    min: Option<i64>,
+    max: Option<i64>,
+}
+
+impl Aggregator for MinMaxAggregator {
+    fn add(&mut self, value: i64) {
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+    }
+
+    fn result(&self) -> Value {
+        json!({"min": self.min, "max": self.max})
+    }
+}
+
+/// Order-independent checksum, so that runs polling the same values can be compared.
+#[derive(Debug, Default)]
+pub struct XorChecksumAggregator {
+    checksum: i64,
+}
+
+impl Aggregator for XorChecksumAggregator {
+    fn add(&mut self, value: i64) {
+        self.checksum ^= value;
+    }
+
+    fn result(&self) -> Value {
+        json!(self.checksum)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    fn aggregate(mut aggregator: impl Aggregator, values: &[i64]) -> Value {
+        for &value in values {
+            aggregator.add(value);
+        }
+        aggregator.result()
+    }
+
+    #[test]
+    fn aggregate_basic_summaries() {
+        let values = [5, -3, 12, 5];
+
+        assert_eq!(json!(4), aggregate(CountAggregator::default(), &values));
+        assert_eq!(
+            json!({"sum": "19", "overflowed": false}),
+            aggregate(SumAggregator::default(), &values)
+        );
+        assert_eq!(
+            json!({"min": -3, "max": 12}),
+            aggregate(MinMaxAggregator::default(), &values)
+        );
+        assert_eq!(
+            json!(-3 ^ 12),
+            aggregate(XorChecksumAggregator::default(), &values)
+        );
+    }
+
+    #[test]
+    fn report_sums_beyond_64_bits() {
+        assert_eq!(
+            json!({"sum": "18446744073709551614", "overflowed": false}),
+            aggregate(SumAggregator::default(), &[i64::MAX, i64::MAX])
+        );
+    }
+
+    #[test]
+    fn report_nothing_polled() {
+        assert_eq!(
+            json!({"min": null, "max": null}),
+            aggregate(MinMaxAggregator::default(), &[])
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use serde_json::{Map, Value};
+
+use crate::polling::dto::{AggregatorKind, RunStats, ValueSum};
+
+mod basic;
+mod distinct_count;
+mod stats;
+mod top_k;
+
+pub use basic::{CountAggregator, MinMaxAggregator, SumAggregator, XorChecksumAggregator};
+pub use distinct_count::DistinctCountAggregator;
+pub use stats::StatsAggregator;
+pub use top_k::TopKAggregator;
+
+/// Summary of the values polled by a run, fed one successful value at a time.
+pub trait Aggregator: Send {
+    fn add(&mut self, value: i64);
+    fn result(&self) -> Value;
+}
+
+impl AggregatorKind {
+    pub fn build(&self) -> Box<dyn Aggregator> {
+        match *self {
+            Self::Count => Box::new(CountAggregator::default()),
+            Self::Sum => Box::new(SumAggregator::default()),
+            Self::MinMax => Box::new(MinMaxAggregator::default()),
+            Self::DistinctCount => Box::new(DistinctCountAggregator::default()),
+            Self::TopK { k } => Box::new(TopKAggregator::new(k)),
+            Self::XorChecksum => Box::new(XorChecksumAggregator::default()),
+        }
+    }
+}
+
+/// Aggregators of a run: the count, sum and statistics kept for every run, and the ones
+/// chosen for it, keyed by their names.
+pub struct Aggregators {
+    count: CountAggregator,
+    sum: SumAggregator,
+    stats: StatsAggregator,
+    chosen: Vec<(&'static str, Box<dyn Aggregator>)>,
+}
+
+impl Aggregators {
+    pub fn new(kinds: &[AggregatorKind], histogram_buckets: &[i64]) -> Self {
+        Self {
+            count: CountAggregator::default(),
+            sum: SumAggregator::default(),
+            stats: StatsAggregator::new(histogram_buckets),
+            chosen: kinds
+                .iter()
+                .map(|kind| (kind.name(), kind.build()))
+                .collect(),
+        }
+    }
+
+    pub fn add(&mut self, value: i64) {
+        let kept: [&mut dyn Aggregator; 3] = [&mut self.count, &mut self.sum, &mut self.stats];
+        let chosen = self
+            .chosen
+            .iter_mut()
+            .map(|(_, aggregator)| aggregator.as_mut() as &mut dyn Aggregator);
+        for aggregator in IntoIterator::into_iter(kept).chain(chosen) {
+            aggregator.add(value);
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.count()
+    }
+
+    pub fn sum(&self) -> ValueSum {
+        self.sum.sum()
+    }
+
+    pub fn stats(&self) -> RunStats {
+        self.stats.run_stats()
+    }
+
+    /// Results of the chosen aggregators, `None` if the run chose none
+    pub fn results(&self) -> Option<Value> {
+        if self.chosen.is_empty() {
+            return None;
+        }
+
+        let results = self
+            .chosen
+            .iter()
+            .map(|(name, aggregator)| (name.to_string(), aggregator.result()))
+            .collect::<Map<_, _>>();
+        Some(Value::Object(results))
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn collect_results_by_aggregator_name() {
+        let mut aggregators = Aggregators::new(
+            &[
+                AggregatorKind::Count,
+                AggregatorKind::MinMax,
+                AggregatorKind::TopK { k: 1 },
+            ],
+            &[],
+        );
+        for value in [3, -1, 3] {
+            aggregators.add(value);
+        }
+
+        assert_eq!(
+            Some(json!({
+                "count": 3,
+                "min_max": {"min": -1, "max": 3},
+                "top_k": [{"value": 3, "count": 2}],
+            })),
+            aggregators.results()
+        );
+    }
+
+    #[test]
+    fn report_no_results_without_aggregators() {
+        let mut aggregators = Aggregators::new(&[], &[]);
+        aggregators.add(1);
+
+        assert_eq!(None, aggregators.results());
+    }
+
+    #[test]
+    fn keep_count_sum_and_stats_of_every_run() {
+        let mut aggregators = Aggregators::new(&[], &[0]);
+        for value in [-2, 5, 9] {
+            aggregators.add(value);
+        }
+
+        assert_eq!(3, aggregators.count());
+        assert_eq!(
+            ValueSum {
+                sum: 12,
+                overflowed: false
+            },
+            aggregators.sum()
+        );
+        let stats = aggregators.stats();
+        assert_eq!((3, Some(-2), Some(9)), (stats.count, stats.min, stats.max));
+        assert_eq!(
+            vec![1, 2],
+            stats.histogram.iter().map(|b| b.count).collect::<Vec<_>>()
+        );
+    }
+}
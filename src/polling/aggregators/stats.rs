@@ -0,0 +1,126 @@
+use serde_json::Value;
+
+use crate::polling::aggregators::Aggregator;
+use crate::polling::dto::{HistogramBucket, RunStats};
+
+/// Min, max, mean, variance and histogram of the values, updated as they arrive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatsAggregator {
+    count: u64,
+    min: Option<i64>,
+    max: Option<i64>,
+    mean: f64,
+    /// Sum of squared differences from the mean, as in Welford's algorithm
+    m2: f64,
+    histogram: Vec<HistogramBucket>,
+}
+
+impl StatsAggregator {
+    pub fn new(histogram_buckets: &[i64]) -> Self {
+        Self {
+            count: 0,
+            min: None,
+            max: None,
+            mean: 0.0,
+            m2: 0.0,
+            histogram: histogram_buckets
+                .iter()
+                .map(|&upper_bound| Some(upper_bound))
+                .chain(std::iter::once(None))
+                .map(|upper_bound| HistogramBucket {
+                    upper_bound,
+                    count: 0,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn run_stats(&self) -> RunStats {
+        let polled = self.count > 0;
+        RunStats {
+            count: self.count,
+            min: self.min,
+            max: self.max,
+            mean: Some(self.mean).filter(|_| polled),
+            variance: Some(self.m2 / self.count as f64).filter(|_| polled),
+            histogram: self.histogram.clone(),
+        }
+    }
+}
+
+impl Aggregator for StatsAggregator {
+    fn add(&mut self, value: i64) {
+        self.count += 1;
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+
+        let delta = value as f64 - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value as f64 - self.mean);
+
+        if let Some(bucket) = self
+            .histogram
+            .iter_mut()
+            .find(|bucket| !matches!(bucket.upper_bound, Some(upper_bound) if value > upper_bound))
+        {
+            bucket.count += 1;
+        }
+    }
+
+    fn result(&self) -> Value {
+        serde_json::to_value(self.run_stats()).expect("Failed to serialize run statistics")
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    #[test]
+    fn compute_stats_incrementally() {
+        let mut stats = StatsAggregator::new(&[0, 10]);
+        for value in [-4, 0, 2, 10, 12] {
+            stats.add(value);
+        }
+
+        assert_eq!(
+            RunStats {
+                count: 5,
+                min: Some(-4),
+                max: Some(12),
+                mean: Some(4.0),
+                variance: Some(36.8),
+                histogram: vec![
+                    HistogramBucket {
+                        upper_bound: Some(0),
+                        count: 2
+                    },
+                    HistogramBucket {
+                        upper_bound: Some(10),
+                        count: 2
+                    },
+                    HistogramBucket {
+                        upper_bound: None,
+                        count: 1
+                    },
+                ],
+            },
+            stats.run_stats()
+        );
+    }
+
+    #[test]
+    fn report_no_mean_without_values() {
+        let stats = StatsAggregator::new(&[]).run_stats();
+
+        assert_eq!(None, stats.mean);
+        assert_eq!(None, stats.variance);
+        assert_eq!(
+            vec![HistogramBucket {
+                upper_bound: None,
+                count: 0
+            }],
+            stats.histogram
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde_json::{json, Value};
+
+use crate::polling::aggregators::Aggregator;
+
+/// Counters kept per reported value, more of them make the counts more precise
+const COUNTERS_PER_VALUE: usize = 10;
+
+/// Most frequent values found by the Space-Saving algorithm, in memory bounded by `k`.
+///
+/// Counts of values that displaced others are overestimated by at most the displaced count.
+pub struct TopKAggregator {
+    k: usize,
+    counters: HashMap<i64, u64>,
+    /// Values of the counters by their counts, so that the rarest one is found without a scan
+    by_count: BTreeMap<u64, BTreeSet<i64>>,
+}
+
+impl TopKAggregator {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            counters: HashMap::with_capacity(k * COUNTERS_PER_VALUE),
+            by_count: BTreeMap::new(),
+        }
+    }
+
+    fn unindex(&mut self, value: i64, count: u64) {
+        if let Some(values) = self.by_count.get_mut(&count) {
+            values.remove(&value);
+            if values.is_empty() {
+                self.by_count.remove(&count);
+            }
+        }
+    }
+
+    fn set_count(&mut self, value: i64, count: u64) {
+        if let Some(previous) = self.counters.insert(value, count) {
+            self.unindex(value, previous);
+        }
+        self.by_count.entry(count).or_default().insert(value);
+    }
+
+    /// Removes the counter of the least frequent value, the lowest one among equally rare
+    fn evict_rarest(&mut self) -> Option<u64> {
+        let (&count, values) = self.by_count.iter().next()?;
+        let rarest = *values.iter().next()?;
+        self.unindex(rarest, count);
+        self.counters.remove(&rarest);
+        Some(count)
+    }
+
+    fn top(&self) -> Vec<(i64, u64)> {
+        let mut top = self
+            .counters
+            .iter()
+            .map(|(&value, &count)| (value, count))
+            .collect::<Vec<_>>();
+        top.sort_unstable_by(|(a_value, a_count), (b_value, b_count)| {
+            b_count.cmp(a_count).then(a_value.cmp(b_value))
+        });
+        top.truncate(self.k);
+        top
+    }
+}
+
+impl Aggregator for TopKAggregator {
+    fn add(&mut self, value: i64) {
+        if let Some(&count) = self.counters.get(&value) {
+            self.set_count(value, count + 1);
+        } else if self.counters.len() < self.k * COUNTERS_PER_VALUE {
+            self.set_count(value, 1);
+        } else if let Some(count) = self.evict_rarest() {
+            self.set_count(value, count + 1);
+        }
+    }
+
+    fn result(&self) -> Value {
+        self.top()
+            .into_iter()
+            .map(|(value, count)| json!({"value": value, "count": count}))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    #[test]
+    fn report_most_frequent_values() {
+        let mut aggregator = TopKAggregator::new(2);
+        for value in [1, 2, 2, 3, 3, 3, 4] {
+            aggregator.add(value);
+        }
+
+        assert_eq!(vec![(3, 3), (2, 2)], aggregator.top());
+    }
+
+    #[test]
+    fn keep_frequent_values_when_counters_run_out() {
+        let mut aggregator = TopKAggregator::new(1);
+        for value in 0..1_000 {
+            aggregator.add(7);
+            aggregator.add(value + 100);
+        }
+
+        assert_eq!(7, aggregator.top()[0].0);
+        assert_eq!(COUNTERS_PER_VALUE, aggregator.counters.len());
+    }
+
+    #[test]
+    fn displace_rarest_value() {
+        let mut aggregator = TopKAggregator::new(1);
+        for value in 0..COUNTERS_PER_VALUE as i64 {
+            for _ in 0..=value {
+                aggregator.add(value);
+            }
+        }
+
+        aggregator.add(-1);
+
+        assert_eq!(None, aggregator.counters.get(&0));
+        assert_eq!(Some(&2), aggregator.counters.get(&-1));
+        assert_eq!(
+            aggregator.counters.len(),
+            aggregator
+                .by_count
+                .values()
+                .map(BTreeSet::len)
+                .sum::<usize>()
+        );
+    }
+}
@@ -0,0 +1,923 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use chrono::{DateTime, Duration, Utc, MAX_DATETIME};
+use tokio::sync::watch;
+
+use crate::configuration::settings::PollingSettings;
+use crate::polling::background_job_runner::ResumeOutcome;
+use crate::polling::dto::{
+    PendingRunInfo, QueueInfo, RunId, RunJob, RunPriority, RunningRunInfo, ScheduledRunInfo,
+};
+
+#[derive(Debug)]
+struct PendingJob {
+    job: RunJob,
+    enqueued_at: DateTime<Utc>,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct RunningJob {
+    job: RunJob,
+    started_at: DateTime<Utc>,
+    /// Time spent running before the last pause
+    active_before: Duration,
+    /// `None` while the job is paused
+    resumed_at: Option<DateTime<Utc>>,
+    /// Whether a paused job lets pending jobs take its worker slot
+    lends_slot: bool,
+    /// Whether a paused job that lent its slot is to resume once a slot frees up
+    resume_requested: bool,
+    control: watch::Sender<JobControl>,
+}
+
+impl RunningJob {
+    /// Time spent running, not counting pauses.
+    fn elapsed(&self, now: DateTime<Utc>) -> Duration {
+        self.active_before
+            + self
+                .resumed_at
+                .map_or_else(Duration::zero, |resumed_at| now - resumed_at)
+    }
+
+    /// Expected finish time, assuming a paused job is resumed right away.
+    fn finishes_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        later_by(now, duration(&self.job) - self.elapsed(now)).max(now)
+    }
+
+    fn resume(&mut self, now: DateTime<Utc>) {
+        self.resumed_at = Some(now);
+        self.lends_slot = false;
+        self.resume_requested = false;
+        self.update_control();
+    }
+
+    fn update_control(&self) {
+        // the job may be finishing right now and have dropped its receiver
+        let _ = self.control.send(JobControl {
+            duration: self.job.duration,
+            paused: self.resumed_at.is_none(),
+        });
+    }
+}
+
+/// Parameters of a running job that can be changed while it executes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct JobControl {
+    /// Total running time of the job, not counting pauses
+    pub duration: std::time::Duration,
+    pub paused: bool,
+}
+
+/// Pending and running jobs of the background job runner.
+///
+/// Pending jobs are taken in priority order. Every `priority_aging_seconds` of waiting
+/// raises a job by one priority class, so low priority jobs are not starved.
+/// Jobs waiting longer than their maximum queue wait are never started.
+///
+/// Jobs with a deferred start are kept aside, up to `max_scheduled_runs` of them, and do not
+/// count against pending limits until they are due. Due jobs wait there, earliest first,
+/// while the pending limits leave no place for them.
+///
+/// A paused job keeps its worker slot unless it lends the slot out, in which case a pending
+/// job may start in its place. When resumed without a free slot, it stays paused until one
+/// frees up and takes it ahead of pending jobs.
+#[derive(Debug)]
+pub struct JobQueue {
+    settings: PollingSettings,
+    scheduled: Vec<RunJob>,
+    pending: Vec<PendingJob>,
+    running: Vec<RunningJob>,
+}
+
+impl JobQueue {
+    pub fn new(settings: PollingSettings) -> Self {
+        Self {
+            settings,
+            scheduled: Vec::new(),
+            pending: Vec::new(),
+            running: Vec::new(),
+        }
+    }
+
+    /// Enqueues the job and returns its estimated start time,
+    /// or the time a place in the queue is expected to free up if there is none.
+    pub fn try_push(
+        &mut self,
+        job: RunJob,
+        now: DateTime<Utc>,
+    ) -> Result<DateTime<Utc>, DateTime<Utc>> {
+        if let Some(start_at) = job.start_at.filter(|start_at| *start_at > now) {
+            if self.scheduled.len() >= self.settings.scheduled_runs_limit() {
+                // a place frees up once the earliest scheduled job is due
+                let earliest = self.scheduled.iter().filter_map(|job| job.start_at).min();
+                return Err(earliest.unwrap_or(now).max(now));
+            }
+            self.scheduled.push(job);
+            return Ok(start_at);
+        }
+        if let Err(full) = self.pending_place(job.priority) {
+            return Err(self.place_frees_at(now, full));
+        }
+
+        let id = job.id;
+        self.enqueue(job, now);
+        Ok(self.estimated_start_at(id, now).unwrap_or(now))
+    }
+
+    /// Enqueues either all of the jobs or none of them.
+    pub fn try_push_all(
+        &mut self,
+        jobs: Vec<RunJob>,
+        now: DateTime<Utc>,
+    ) -> Result<Vec<DateTime<Utc>>, DateTime<Utc>> {
+        let ids: Vec<RunId> = jobs.iter().map(|job| job.id).collect();
+        let mut estimated_starts = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            match self.try_push(job, now) {
+                Ok(estimated_start_at) => estimated_starts.push(estimated_start_at),
+                Err(retry_at) => {
+                    for id in &ids[..estimated_starts.len()] {
+                        self.cancel(*id);
+                    }
+                    return Err(retry_at);
+                }
+            }
+        }
+        // later jobs of the batch may have been placed ahead of earlier ones
+        Ok(ids
+            .iter()
+            .zip(estimated_starts)
+            .map(|(id, pushed_estimate)| {
+                self.estimated_start_at(*id, now).unwrap_or(pushed_estimate)
+            })
+            .collect())
+    }
+
+    /// Moves due scheduled jobs to pending ones while pending limits allow, earliest first,
+    /// returning their ids. The others stay scheduled until a place frees up.
+    pub fn enqueue_due(&mut self, now: DateTime<Utc>) -> Vec<RunId> {
+        let (mut due, scheduled): (Vec<_>, Vec<_>) = self
+            .scheduled
+            .drain(..)
+            .partition(|job| job.start_at.is_none_or(|start_at| start_at <= now));
+        self.scheduled = scheduled;
+        due.sort_by_key(|job| job.start_at);
+
+        let mut enqueued = Vec::new();
+        for job in due {
+            if self.pending_place(job.priority).is_ok() {
+                enqueued.push(job.id);
+                self.enqueue(job, now);
+            } else {
+                self.scheduled.push(job);
+            }
+        }
+        enqueued
+    }
+
+    /// Removes a job that has not started yet, returns false if there is no such job.
+    pub fn cancel(&mut self, id: RunId) -> bool {
+        let (scheduled, pending) = (self.scheduled.len(), self.pending.len());
+        self.scheduled.retain(|job| job.id != id);
+        self.pending.retain(|p| p.job.id != id);
+        scheduled != self.scheduled.len() || pending != self.pending.len()
+    }
+
+    fn enqueue(&mut self, job: RunJob, now: DateTime<Utc>) {
+        let expires_at = job
+            .max_queue_wait
+            .or_else(|| {
+                self.settings
+                    .max_queue_wait_seconds
+                    .map(std::time::Duration::from_secs)
+            })
+            .map(|wait| {
+                later_by(
+                    now,
+                    Duration::from_std(wait).unwrap_or_else(|_| Duration::max_value()),
+                )
+            });
+        self.pending.push(PendingJob {
+            job,
+            enqueued_at: now,
+            expires_at,
+        });
+    }
+
+    /// Resumes paused jobs waiting for the slots they lent out while there are free slots,
+    /// returning their ids.
+    pub fn resume_waiting(&mut self, now: DateTime<Utc>) -> Vec<RunId> {
+        let mut resumed = Vec::new();
+        while self.occupied_slots() < self.settings.max_concurrent_runs {
+            match self.running.iter_mut().find(|r| r.resume_requested) {
+                Some(running) => {
+                    running.resume(now);
+                    resumed.push(running.job.id);
+                }
+                None => break,
+            }
+        }
+        resumed
+    }
+
+    /// Takes the next job to execute and marks it as running, if there is a free worker slot.
+    /// Jobs waiting to resume are to get free slots first, with `resume_waiting`.
+    pub fn pop(&mut self, now: DateTime<Utc>) -> Option<(RunJob, watch::Receiver<JobControl>)> {
+        if self.occupied_slots() >= self.settings.max_concurrent_runs {
+            return None;
+        }
+        let position = self.dispatch_order(now).into_iter().next()?;
+        let pending = self.pending.remove(position);
+        let (control, control_rx) = watch::channel(JobControl {
+            duration: pending.job.duration,
+            paused: false,
+        });
+        self.running.push(RunningJob {
+            job: pending.job.clone(),
+            started_at: now,
+            active_before: Duration::zero(),
+            resumed_at: Some(now),
+            lends_slot: false,
+            resume_requested: false,
+            control,
+        });
+        Some((pending.job, control_rx))
+    }
+
+    /// Changes the remaining duration of a running job, returns false if there is no such job.
+    pub fn set_remaining_duration(
+        &mut self,
+        id: RunId,
+        remaining: std::time::Duration,
+        now: DateTime<Utc>,
+    ) -> bool {
+        match self.running.iter_mut().find(|r| r.job.id == id) {
+            Some(running) => {
+                let elapsed = running.elapsed(now).to_std().unwrap_or_default();
+                // saturates, overflowing would poison the lock of the queue
+                running.job.duration = elapsed
+                    .checked_add(remaining)
+                    .unwrap_or(std::time::Duration::MAX);
+                running.update_control();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Pauses a running job, returns false if there is no such job or it is already paused.
+    pub fn pause(&mut self, id: RunId, lend_slot: bool, now: DateTime<Utc>) -> bool {
+        match self
+            .running
+            .iter_mut()
+            .find(|r| r.job.id == id && r.resumed_at.is_some())
+        {
+            Some(running) => {
+                running.active_before = running.elapsed(now);
+                running.resumed_at = None;
+                running.lends_slot = lend_slot;
+                running.update_control();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Resumes a paused job, or lets it resume once a worker slot frees up if it lent its
+    /// slot out.
+    pub fn resume(&mut self, id: RunId, now: DateTime<Utc>) -> ResumeOutcome {
+        let slot_free = self.occupied_slots() < self.settings.max_concurrent_runs;
+        match self
+            .running
+            .iter_mut()
+            .find(|r| r.job.id == id && r.resumed_at.is_none())
+        {
+            Some(running) if running.lends_slot && !slot_free => {
+                running.resume_requested = true;
+                ResumeOutcome::WaitingForSlot
+            }
+            Some(running) => {
+                running.resume(now);
+                ResumeOutcome::Resumed
+            }
+            None => ResumeOutcome::NotPaused,
+        }
+    }
+
+    pub fn finish(&mut self, id: RunId) {
+        self.running.retain(|r| r.job.id != id);
+    }
+
+    /// Drops pending jobs that have waited for too long, returning their ids.
+    pub fn remove_expired(&mut self, now: DateTime<Utc>) -> Vec<RunId> {
+        let (expired, pending) = self.pending.drain(..).partition(|p| is_expired(p, now));
+        self.pending = pending;
+        expired.into_iter().map(|p: PendingJob| p.job.id).collect()
+    }
+
+    pub fn info(&self, now: DateTime<Utc>) -> QueueInfo {
+        let scheduled = self
+            .scheduled
+            .iter()
+            .filter_map(|job| {
+                job.start_at.map(|start_at| ScheduledRunInfo {
+                    id: job.id,
+                    seconds: job.duration.as_secs(),
+                    priority: job.priority,
+                    start_at,
+                })
+            })
+            .collect();
+
+        let running = self
+            .running
+            .iter()
+            .map(|r| RunningRunInfo {
+                id: r.job.id,
+                seconds: r.job.duration.as_secs(),
+                started_at: r.started_at,
+                remaining_seconds: seconds_until(r.finishes_at(now), now),
+                paused: r.resumed_at.is_none(),
+            })
+            .collect();
+
+        let pending = self
+            .estimated_starts(now)
+            .into_iter()
+            .map(|(p, estimated_start_at)| PendingRunInfo {
+                id: p.job.id,
+                seconds: p.job.duration.as_secs(),
+                priority: p.job.priority,
+                enqueued_at: p.enqueued_at,
+                estimated_start_at,
+                expires_at: p.expires_at,
+            })
+            .collect();
+
+        QueueInfo {
+            scheduled,
+            pending,
+            running,
+        }
+    }
+
+    fn estimated_start_at(&self, id: RunId, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.estimated_starts(now)
+            .into_iter()
+            .find(|(p, _)| p.job.id == id)
+            .map(|(_, start)| start)
+    }
+
+    /// Earliest time a pending job (of given priority, if any) is expected to leave the queue,
+    /// freeing up a place in it.
+    fn place_frees_at(&self, now: DateTime<Utc>, priority: Option<RunPriority>) -> DateTime<Utc> {
+        self.estimated_starts(now)
+            .into_iter()
+            .filter(|(p, _)| priority.is_none_or(|priority| p.job.priority == priority))
+            .map(|(_, start)| start)
+            .min()
+            .unwrap_or(now)
+    }
+
+    /// Pending jobs in dispatch order with their estimated start times.
+    fn estimated_starts(&self, now: DateTime<Utc>) -> Vec<(&PendingJob, DateTime<Utc>)> {
+        let mut slots = self.free_slots(now);
+        // paused jobs waiting to take their slot back go first
+        for running in self.running.iter().filter(|r| r.resume_requested) {
+            Self::take_slot(
+                &mut slots,
+                duration(&running.job) - running.elapsed(now),
+                now,
+            );
+        }
+        self.dispatch_order(now)
+            .into_iter()
+            .map(|position| {
+                let pending = &self.pending[position];
+                (
+                    pending,
+                    Self::take_slot(&mut slots, duration(&pending.job), now),
+                )
+            })
+            .collect()
+    }
+
+    /// Positions of unexpired pending jobs, sorted by effective priority, then by enqueue order.
+    fn dispatch_order(&self, now: DateTime<Utc>) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.pending.len())
+            .filter(|&position| !is_expired(&self.pending[position], now))
+            .collect();
+        order.sort_by_key(|&position| self.effective_rank(&self.pending[position], now));
+        order
+    }
+
+    fn effective_rank(&self, pending: &PendingJob, now: DateTime<Utc>) -> i64 {
+        let rank = pending.job.priority.rank();
+        match self.settings.priority_aging_seconds {
+            0 => rank,
+            aging => rank - (now - pending.enqueued_at).num_seconds() / aging as i64,
+        }
+    }
+
+    /// Whether a job of the priority may become pending, otherwise the class whose limit is
+    /// reached, `None` for the limit of all pending jobs
+    fn pending_place(&self, priority: RunPriority) -> Result<(), Option<RunPriority>> {
+        if self.pending.len() >= self.settings.max_pending_runs.max(1) {
+            return Err(None);
+        }
+        match priority.pending_limit(&self.settings.max_pending_runs_per_priority) {
+            Some(limit) if self.pending_of(priority).count() >= limit => Err(Some(priority)),
+            _ => Ok(()),
+        }
+    }
+
+    fn pending_of(&self, priority: RunPriority) -> impl Iterator<Item = &PendingJob> {
+        self.pending
+            .iter()
+            .filter(move |p| p.job.priority == priority)
+    }
+
+    fn occupied_slots(&self) -> usize {
+        self.running.iter().filter(|r| !r.lends_slot).count()
+    }
+
+    /// Times at which each worker slot is expected to become free.
+    fn free_slots(&self, now: DateTime<Utc>) -> BinaryHeap<Reverse<DateTime<Utc>>> {
+        let idle = self
+            .settings
+            .max_concurrent_runs
+            .saturating_sub(self.occupied_slots());
+
+        self.running
+            .iter()
+            .filter(|r| !r.lends_slot)
+            .map(|r| r.finishes_at(now))
+            .chain(std::iter::repeat_n(now, idle))
+            .map(Reverse)
+            .collect()
+    }
+
+    fn take_slot(
+        slots: &mut BinaryHeap<Reverse<DateTime<Utc>>>,
+        duration: Duration,
+        now: DateTime<Utc>,
+    ) -> DateTime<Utc> {
+        let start = slots.pop().map(|Reverse(t)| t).unwrap_or(now);
+        slots.push(Reverse(later_by(start, duration)));
+        start
+    }
+}
+
+fn is_expired(pending: &PendingJob, now: DateTime<Utc>) -> bool {
+    pending
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+}
+
+fn duration(job: &RunJob) -> Duration {
+    Duration::from_std(job.duration).unwrap_or_else(|_| Duration::max_value())
+}
+
+/// Saturates instead of panicking, which would poison the lock of the queue
+fn later_by(time: DateTime<Utc>, duration: Duration) -> DateTime<Utc> {
+    time.checked_add_signed(duration).unwrap_or(MAX_DATETIME)
+}
+
+fn seconds_until(time: DateTime<Utc>, now: DateTime<Utc>) -> u64 {
+    (time - now).num_seconds().max(0) as u64
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::configuration::settings::PriorityLimits;
+    use crate::targets::dto::TargetDefinition;
+
+    fn settings(max_concurrent_runs: usize, max_pending_runs: usize) -> PollingSettings {
+        PollingSettings {
+            polling_address: "127.0.0.1:0".into(),
+            max_concurrent_runs,
+            max_pending_runs,
+            concurrent_requests_per_run: 3,
+            max_pending_runs_per_priority: PriorityLimits::default(),
+            priority_aging_seconds: 0,
+            max_queue_wait_seconds: None,
+            max_scheduled_runs: None,
+            max_concurrent_requests_per_run: None,
+            allowed_polling_addresses: Vec::new(),
+            histogram_buckets: Vec::new(),
+            sample_interval_seconds: None,
+        }
+    }
+
+    fn job(seconds: u64) -> RunJob {
+        prioritized_job(seconds, RunPriority::Normal)
+    }
+
+    fn prioritized_job(seconds: u64, priority: RunPriority) -> RunJob {
+        RunJob {
+            id: RunId::new_v4(),
+            duration: std::time::Duration::from_secs(seconds),
+            priority,
+            max_queue_wait: None,
+            start_at: None,
+            concurrent_requests: 3,
+            target: TargetDefinition::from_url("127.0.0.1:0".into()),
+            histogram_buckets: Vec::new(),
+            aggregators: Vec::new(),
+            capture: None,
+        }
+    }
+
+    #[test]
+    fn estimate_start_of_pending_jobs_from_running_ones() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(2, 4));
+        let (first, second, third, fourth) = (job(10), job(20), job(5), job(5));
+        for j in [&first, &second, &third, &fourth].iter() {
+            queue.try_push((*j).clone(), now).unwrap();
+        }
+        queue.pop(now);
+        queue.pop(now);
+
+        let info = queue.info(now);
+
+        assert_eq!(
+            vec![(first.id, 10), (second.id, 20)],
+            info.running
+                .iter()
+                .map(|r| (r.id, r.remaining_seconds))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![
+                (third.id, now + Duration::seconds(10)),
+                (fourth.id, now + Duration::seconds(15)),
+            ],
+            info.pending
+                .iter()
+                .map(|p| (p.id, p.estimated_start_at))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn estimate_start_after_job_of_unrepresentable_duration() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 2));
+        queue.try_push(job(u64::MAX), now).unwrap();
+        queue.pop(now);
+
+        assert_eq!(Ok(MAX_DATETIME), queue.try_push(job(u64::MAX), now));
+        assert_eq!(Ok(MAX_DATETIME), queue.try_push(job(10), now));
+        assert!(queue.info(now).running[0].remaining_seconds > 0);
+    }
+
+    #[test]
+    fn never_expire_job_of_unrepresentable_queue_wait() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 1));
+        queue
+            .try_push(
+                RunJob {
+                    max_queue_wait: Some(std::time::Duration::from_secs(u64::MAX)),
+                    ..job(10)
+                },
+                now,
+            )
+            .unwrap();
+
+        assert_eq!(Some(MAX_DATETIME), queue.info(now).pending[0].expires_at);
+    }
+
+    #[test]
+    fn forget_finished_jobs() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 1));
+        let j = job(10);
+        queue.try_push(j.clone(), now).unwrap();
+        queue.pop(now);
+        queue.finish(j.id);
+
+        assert_eq!(QueueInfo::default(), queue.info(now));
+    }
+
+    #[test]
+    fn expect_queue_place_to_free_up_when_first_running_job_finishes() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(2, 1));
+        queue.try_push(job(30), now).unwrap();
+        queue.pop(now);
+        queue.try_push(job(20), now).unwrap();
+        queue.pop(now);
+
+        assert_eq!(Ok(now + Duration::seconds(20)), queue.try_push(job(5), now));
+        assert_eq!(
+            Err(now + Duration::seconds(20)),
+            queue.try_push(job(5), now)
+        );
+    }
+
+    #[test]
+    fn take_jobs_in_priority_order() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(3, 3));
+        let low = prioritized_job(5, RunPriority::Low);
+        let normal = prioritized_job(5, RunPriority::Normal);
+        let high = prioritized_job(5, RunPriority::High);
+        for j in [&low, &normal, &high].iter() {
+            queue.try_push((*j).clone(), now).unwrap();
+        }
+
+        assert_eq!(Some(high.id), queue.pop(now).map(|(j, _)| j.id));
+        assert_eq!(Some(normal.id), queue.pop(now).map(|(j, _)| j.id));
+        assert_eq!(Some(low.id), queue.pop(now).map(|(j, _)| j.id));
+        assert!(queue.pop(now).is_none());
+    }
+
+    #[test]
+    fn raise_priority_of_long_waiting_jobs() {
+        let now = Utc::now();
+        let later = now + Duration::seconds(20);
+        let mut queue = JobQueue::new(PollingSettings {
+            priority_aging_seconds: 10,
+            ..settings(1, 3)
+        });
+        let low = prioritized_job(5, RunPriority::Low);
+        queue.try_push(low.clone(), now).unwrap();
+        queue
+            .try_push(prioritized_job(5, RunPriority::High), later)
+            .unwrap();
+
+        assert_eq!(Some(low.id), queue.pop(later).map(|(j, _)| j.id));
+    }
+
+    #[test]
+    fn reject_jobs_exceeding_priority_limit() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(PollingSettings {
+            max_pending_runs_per_priority: PriorityLimits {
+                low: Some(1),
+                ..PriorityLimits::default()
+            },
+            ..settings(1, 3)
+        });
+
+        assert!(queue
+            .try_push(prioritized_job(5, RunPriority::Low), now)
+            .is_ok());
+        assert!(queue
+            .try_push(prioritized_job(5, RunPriority::Low), now)
+            .is_err());
+        assert!(queue
+            .try_push(prioritized_job(5, RunPriority::Normal), now)
+            .is_ok());
+    }
+
+    #[test]
+    fn drop_jobs_waiting_longer_than_allowed() {
+        let now = Utc::now();
+        let later = now + Duration::seconds(10);
+        let mut queue = JobQueue::new(PollingSettings {
+            max_queue_wait_seconds: Some(30),
+            ..settings(1, 3)
+        });
+        let impatient = RunJob {
+            max_queue_wait: Some(std::time::Duration::from_secs(5)),
+            ..job(5)
+        };
+        let patient = job(5);
+        queue.try_push(impatient.clone(), now).unwrap();
+        queue.try_push(patient.clone(), now).unwrap();
+
+        assert_eq!(Some(patient.id), queue.pop(later).map(|(j, _)| j.id));
+        assert_eq!(vec![impatient.id], queue.remove_expired(later));
+        assert!(queue.info(later).pending.is_empty());
+    }
+
+    #[test]
+    fn keep_deferred_jobs_aside_until_due() {
+        let now = Utc::now();
+        let start_at = now + Duration::seconds(30);
+        let mut queue = JobQueue::new(settings(2, 1));
+        let deferred = RunJob {
+            start_at: Some(start_at),
+            ..job(5)
+        };
+
+        assert_eq!(Ok(start_at), queue.try_push(deferred.clone(), now));
+        assert!(queue.try_push(job(5), now).is_ok());
+        assert_ne!(Some(deferred.id), queue.pop(now).map(|(j, _)| j.id));
+        assert!(queue.enqueue_due(now).is_empty());
+
+        assert_eq!(vec![deferred.id], queue.enqueue_due(start_at));
+        assert_eq!(Some(deferred.id), queue.pop(start_at).map(|(j, _)| j.id));
+    }
+
+    #[test]
+    fn reject_deferred_jobs_beyond_limit() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(PollingSettings {
+            max_scheduled_runs: Some(2),
+            ..settings(1, 1)
+        });
+        let deferred = |seconds| RunJob {
+            start_at: Some(now + Duration::seconds(seconds)),
+            ..job(5)
+        };
+
+        assert!(queue.try_push(deferred(30), now).is_ok());
+        assert!(queue.try_push(deferred(20), now).is_ok());
+
+        assert_eq!(
+            Err(now + Duration::seconds(20)),
+            queue.try_push(deferred(10), now)
+        );
+        assert_eq!(2, queue.info(now).scheduled.len());
+    }
+
+    #[test]
+    fn keep_due_jobs_scheduled_while_pending_limits_are_reached() {
+        let now = Utc::now();
+        let start_at = now + Duration::seconds(30);
+        let mut queue = JobQueue::new(PollingSettings {
+            max_pending_runs_per_priority: PriorityLimits {
+                low: Some(1),
+                ..PriorityLimits::default()
+            },
+            ..settings(3, 2)
+        });
+        let deferred = |seconds, priority| RunJob {
+            start_at: Some(start_at + Duration::seconds(seconds)),
+            ..prioritized_job(5, priority)
+        };
+        let (first_low, second_low, normal, late) = (
+            deferred(0, RunPriority::Low),
+            deferred(1, RunPriority::Low),
+            deferred(2, RunPriority::Normal),
+            deferred(3, RunPriority::Normal),
+        );
+        for j in [&late, &normal, &second_low, &first_low].iter() {
+            queue.try_push((*j).clone(), now).unwrap();
+        }
+
+        let due_at = start_at + Duration::seconds(3);
+        assert_eq!(vec![first_low.id, normal.id], queue.enqueue_due(due_at));
+        assert_eq!(2, queue.info(due_at).scheduled.len());
+
+        // the class of the other low priority job is still full
+        queue.pop(due_at);
+        assert_eq!(vec![late.id], queue.enqueue_due(due_at));
+        queue.pop(due_at);
+        assert!(queue.enqueue_due(due_at).is_empty());
+
+        queue.pop(due_at);
+        assert_eq!(vec![second_low.id], queue.enqueue_due(due_at));
+        assert!(queue.info(due_at).scheduled.is_empty());
+    }
+
+    #[test]
+    fn cancel_jobs_not_started_yet() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 2));
+        let (running, pending) = (job(5), job(5));
+        let deferred = RunJob {
+            start_at: Some(now + Duration::seconds(30)),
+            ..job(5)
+        };
+        queue.try_push(running.clone(), now).unwrap();
+        queue.pop(now);
+        queue.try_push(pending.clone(), now).unwrap();
+        queue.try_push(deferred.clone(), now).unwrap();
+
+        assert!(!queue.cancel(running.id));
+        assert!(queue.cancel(pending.id));
+        assert!(queue.cancel(deferred.id));
+        assert!(queue.info(now).pending.is_empty());
+        assert!(queue.info(now).scheduled.is_empty());
+    }
+
+    #[test]
+    fn enqueue_all_jobs_or_none_of_them() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 2));
+
+        assert!(queue
+            .try_push_all(vec![job(5), job(5), job(5)], now)
+            .is_err());
+        assert!(queue.info(now).pending.is_empty());
+
+        assert_eq!(
+            Ok(vec![now, now + Duration::seconds(5)]),
+            queue.try_push_all(vec![job(5), job(5)], now)
+        );
+    }
+
+    #[test]
+    fn change_remaining_duration_of_running_job() {
+        let now = Utc::now();
+        let later = now + Duration::seconds(10);
+        let mut queue = JobQueue::new(settings(1, 1));
+        let j = job(30);
+        queue.try_push(j.clone(), now).unwrap();
+        let (_, control) = queue.pop(now).unwrap();
+
+        assert!(queue.set_remaining_duration(j.id, std::time::Duration::from_secs(60), later));
+        assert_eq!(
+            std::time::Duration::from_secs(70),
+            control.borrow().duration
+        );
+        assert_eq!(60, queue.info(later).running[0].remaining_seconds);
+        assert!(!queue.set_remaining_duration(
+            RunId::new_v4(),
+            std::time::Duration::from_secs(60),
+            later
+        ));
+
+        assert!(queue.set_remaining_duration(j.id, std::time::Duration::MAX, later));
+        assert_eq!(std::time::Duration::MAX, control.borrow().duration);
+    }
+
+    #[test]
+    fn not_start_jobs_without_free_slot() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 1));
+        queue.try_push(job(5), now).unwrap();
+        queue.pop(now);
+        queue.try_push(job(5), now).unwrap();
+
+        assert!(queue.pop(now).is_none());
+    }
+
+    #[test]
+    fn not_count_paused_time_as_running() {
+        let now = Utc::now();
+        let (paused_at, resumed_at) = (now + Duration::seconds(10), now + Duration::seconds(40));
+        let mut queue = JobQueue::new(settings(1, 1));
+        let j = job(30);
+        queue.try_push(j.clone(), now).unwrap();
+        let (_, control) = queue.pop(now).unwrap();
+
+        assert!(queue.pause(j.id, false, paused_at));
+        assert!(control.borrow().paused);
+        assert!(!queue.pause(j.id, false, paused_at));
+
+        assert_eq!(ResumeOutcome::Resumed, queue.resume(j.id, resumed_at));
+        assert!(!control.borrow().paused);
+        assert_eq!(ResumeOutcome::NotPaused, queue.resume(j.id, resumed_at));
+        assert_eq!(20, queue.info(resumed_at).running[0].remaining_seconds);
+    }
+
+    #[test]
+    fn start_pending_job_in_slot_lent_by_paused_one() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 1));
+        let (paused, pending) = (job(30), job(5));
+        queue.try_push(paused.clone(), now).unwrap();
+        queue.pop(now);
+        queue.try_push(pending.clone(), now).unwrap();
+
+        assert!(queue.pause(paused.id, false, now));
+        assert!(queue.pop(now).is_none());
+
+        assert_eq!(ResumeOutcome::Resumed, queue.resume(paused.id, now));
+        assert!(queue.pause(paused.id, true, now));
+        assert_eq!(Some(pending.id), queue.pop(now).map(|(j, _)| j.id));
+    }
+
+    #[test]
+    fn resume_job_that_lent_its_slot_once_a_slot_frees_up() {
+        let now = Utc::now();
+        let mut queue = JobQueue::new(settings(1, 1));
+        let (paused, pending, next) = (job(30), job(5), job(5));
+        queue.try_push(paused.clone(), now).unwrap();
+        let (_, control) = queue.pop(now).unwrap();
+        queue.try_push(pending.clone(), now).unwrap();
+        assert!(queue.pause(paused.id, true, now));
+        queue.pop(now).unwrap();
+
+        assert_eq!(ResumeOutcome::WaitingForSlot, queue.resume(paused.id, now));
+        assert!(control.borrow().paused);
+        assert_eq!(1, queue.occupied_slots());
+
+        assert_eq!(
+            Ok(now + Duration::seconds(35)),
+            queue.try_push(next.clone(), now)
+        );
+        queue.finish(pending.id);
+        assert_eq!(vec![paused.id], queue.resume_waiting(now));
+        assert!(queue.pop(now).is_none());
+        assert!(!control.borrow().paused);
+        assert_eq!(
+            vec![(paused.id, false)],
+            queue
+                .info(now)
+                .running
+                .iter()
+                .map(|r| (r.id, r.paused))
+                .collect::<Vec<_>>()
+        );
+    }
+}
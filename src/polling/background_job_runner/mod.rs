@@ -1,13 +1,44 @@
+mod job_queue;
+mod response_capture;
+mod run_spans;
 mod tokio_background_job_runner;
 
 pub use tokio_background_job_runner::TokioBackgroundJobRunner;
 
-use crate::polling::dto::RunJob;
+use crate::polling::dto::{QueueInfo, RunId, RunJob};
 use crate::polling::errors::ServiceResult;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+
+/// What resuming a paused job did.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ResumeOutcome {
+    Resumed,
+    /// The job lent its worker slot out and resumes once a slot frees up
+    WaitingForSlot,
+    /// There is no such paused job
+    NotPaused,
+}
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait(?Send)]
 pub trait BackgroundJobRunner {
-    async fn try_push_job(&self, run_job: RunJob) -> ServiceResult<()>;
+    async fn try_push_job(&self, run_job: RunJob) -> ServiceResult<DateTime<Utc>>;
+    /// Pushes a batch of jobs, either all of them or as many as possible.
+    /// In the former case, all jobs are rejected if any of them is.
+    async fn try_push_jobs(
+        &self,
+        run_jobs: Vec<RunJob>,
+        all_or_nothing: bool,
+    ) -> Vec<ServiceResult<DateTime<Utc>>>;
+    /// Changes how long a running job has left, returns false if there is no such job
+    async fn set_remaining_duration(&self, id: RunId, remaining: std::time::Duration) -> bool;
+    /// Stops sending requests of a running job until it is resumed,
+    /// optionally letting a pending job use its worker slot meanwhile
+    async fn pause_job(&self, id: RunId, lend_slot: bool) -> bool;
+    /// Resumes a paused job, or lets it resume once a worker slot frees up if it lent its slot out
+    async fn resume_job(&self, id: RunId) -> ResumeOutcome;
+    /// Removes a job that has not started yet, returns false if there is no such job
+    async fn cancel_job(&self, id: RunId) -> bool;
+    async fn get_queue(&self) -> QueueInfo;
 }
@@ -0,0 +1,204 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::FutureExt;
+use tokio::sync::mpsc;
+
+use crate::polling::dto::{
+    CaptureMode, CapturedResponse, FaultyServerResponse, PolledResponse, RunId,
+};
+use crate::polling::run_repository::RunRepository;
+
+/// Captured responses waiting to be written, more are dropped
+const CAPTURE_BUFFER: usize = 10_000;
+const WRITE_BATCH_SIZE: usize = 500;
+
+/// Selects responses of a job to capture and hands them to the writer without waiting for it.
+pub struct ResponseCapture {
+    mode: CaptureMode,
+    /// Share of a response accumulated towards the next capture in `CaptureMode::Fraction`
+    due: Mutex<f64>,
+    dropped: AtomicU64,
+    sender: mpsc::Sender<CapturedResponse>,
+}
+
+impl ResponseCapture {
+    pub fn new(mode: CaptureMode) -> (Self, mpsc::Receiver<CapturedResponse>) {
+        let (sender, receiver) = mpsc::channel(CAPTURE_BUFFER);
+        let capture = Self {
+            mode,
+            due: Mutex::new(0.0),
+            dropped: AtomicU64::new(0),
+            sender,
+        };
+        (capture, receiver)
+    }
+
+    pub fn offer(
+        &self,
+        slot: usize,
+        at: DateTime<Utc>,
+        latency: Duration,
+        response: PolledResponse,
+    ) {
+        if !self.selects(&response.result) {
+            return;
+        }
+
+        let body = Some(response.body)
+            .filter(|body| !body.is_empty())
+            .map(|body| serde_json::from_str(&body).unwrap_or(serde_json::Value::String(body)));
+        let error = match response.result {
+            FaultyServerResponse::Ok { .. } => None,
+            FaultyServerResponse::Err { error } => Some(error),
+        };
+        let captured = CapturedResponse {
+            at,
+            latency_us: latency.as_micros() as u64,
+            slot,
+            status: response.status,
+            body,
+            error,
+        };
+        if self.sender.try_send(captured).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Responses selected but not captured, because the writer fell behind
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    fn selects(&self, result: &FaultyServerResponse) -> bool {
+        match self.mode {
+            CaptureMode::All => true,
+            CaptureMode::Errors => matches!(result, FaultyServerResponse::Err { .. }),
+            CaptureMode::Fraction { fraction } => {
+                let mut due = self.due.lock().unwrap();
+                *due += fraction;
+                if *due >= 1.0 {
+                    *due -= 1.0;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+}
+
+/// Stores captured responses in batches as they arrive, until the capture is dropped.
+pub async fn write_captured<R: RunRepository>(
+    run_repo: &R,
+    run_id: RunId,
+    mut receiver: mpsc::Receiver<CapturedResponse>,
+) {
+    while let Some(response) = receiver.recv().await {
+        let mut batch = vec![response];
+        while batch.len() < WRITE_BATCH_SIZE {
+            match receiver.recv().now_or_never() {
+                Some(Some(response)) => batch.push(response),
+                _ => break,
+            }
+        }
+
+        if let Err(e) = run_repo.save_run_responses(run_id, &batch).await {
+            tracing::error!(
+                run_id = %run_id,
+                count = batch.len(),
+                error = %e,
+                "Failed to save captured responses of run"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::run_repository::MockRunRepository;
+
+    fn response(result: FaultyServerResponse) -> PolledResponse {
+        PolledResponse {
+            status: Some(200),
+            body: r#"{"value": 1}"#.into(),
+            result,
+        }
+    }
+
+    fn ok() -> PolledResponse {
+        response(FaultyServerResponse::Ok { value: 1 })
+    }
+
+    fn err() -> PolledResponse {
+        response(FaultyServerResponse::Err {
+            error: "Timed out".into(),
+        })
+    }
+
+    fn capture_all(mode: CaptureMode, responses: Vec<PolledResponse>) -> Vec<CapturedResponse> {
+        let (capture, mut receiver) = ResponseCapture::new(mode);
+        for (slot, response) in responses.into_iter().enumerate() {
+            capture.offer(slot, Utc::now(), Duration::from_millis(5), response);
+        }
+        drop(capture);
+
+        std::iter::from_fn(|| receiver.recv().now_or_never().flatten()).collect()
+    }
+
+    #[test]
+    fn capture_parsed_responses() {
+        let captured = capture_all(CaptureMode::All, vec![ok(), err()]);
+
+        assert_eq!(2, captured.len());
+        assert_eq!(Some(serde_json::json!({"value": 1})), captured[0].body);
+        assert_eq!(5000, captured[0].latency_us);
+        assert_eq!(None, captured[0].error);
+        assert_eq!(
+            (1, Some("Timed out".into())),
+            (captured[1].slot, captured[1].error.clone())
+        );
+    }
+
+    #[test]
+    fn capture_only_errors() {
+        let captured = capture_all(CaptureMode::Errors, vec![ok(), err(), ok()]);
+
+        assert_eq!(vec![1], captured.iter().map(|c| c.slot).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn capture_evenly_spread_fraction() {
+        let captured = capture_all(
+            CaptureMode::Fraction { fraction: 0.25 },
+            std::iter::repeat_with(ok).take(10).collect(),
+        );
+
+        assert_eq!(
+            vec![3, 7],
+            captured.iter().map(|c| c.slot).collect::<Vec<_>>()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn write_captured_responses_in_batches() {
+        let run_id = RunId::new_v4();
+        let (capture, receiver) = ResponseCapture::new(CaptureMode::All);
+        for slot in 0..3 {
+            capture.offer(slot, Utc::now(), Duration::from_millis(1), ok());
+        }
+        drop(capture);
+
+        let mut run_repo = MockRunRepository::new();
+        run_repo
+            .expect_save_run_responses()
+            .withf(move |id, batch| *id == run_id && batch.len() == 3)
+            .times(1)
+            .return_const(Ok(()));
+
+        write_captured(&run_repo, run_id, receiver).await;
+    }
+}
@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use tracing::Span;
+
+use crate::polling::dto::RunId;
+
+/// Spans of the runs held by the job runner, each one lasting from the run being queued
+/// until it ends, so that its trace covers the wait in the queue.
+#[derive(Clone, Debug, Default)]
+pub struct RunSpans {
+    spans: Arc<Mutex<HashMap<RunId, Span>>>,
+}
+
+impl RunSpans {
+    pub fn open(&self, id: RunId, estimated_start_at: DateTime<Utc>) {
+        let span = new_span(id);
+        span.in_scope(|| tracing::info!(run_id = %id, %estimated_start_at, "Run queued"));
+        self.spans.lock().unwrap().insert(id, span);
+    }
+
+    /// Takes the span of a run leaving the queue, the run ends along with it
+    pub fn take(&self, id: RunId) -> Span {
+        self.spans
+            .lock()
+            .unwrap()
+            .remove(&id)
+            .unwrap_or_else(|| new_span(id))
+    }
+}
+
+fn new_span(id: RunId) -> Span {
+    // runs are traced on their own rather than as part of the request starting them
+    tracing::info_span!(parent: None, "run", run_id = %id)
+}
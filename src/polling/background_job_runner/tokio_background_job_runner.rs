@@ -1,20 +1,87 @@
 use std::marker::PhantomData;
 use std::sync::{Arc, Mutex};
 
-use async_channel::{Receiver, Sender};
 use async_trait::async_trait;
-use futures::{stream, StreamExt};
+use chrono::{DateTime, Utc};
+use futures::{future, stream, Future, StreamExt};
+use tokio::sync::{oneshot, watch, Notify};
+use tokio::time::Instant;
+use tracing::Instrument;
 
 use crate::configuration::settings::PollingSettings;
-use crate::polling::background_job_runner::BackgroundJobRunner;
-use crate::polling::dto::{FaultyServerResponse, Run, RunJob, RunJobResult, RunStatus};
-use crate::polling::errors::ServiceResult;
+use crate::events::dto::RunEventKind;
+use crate::events::event_bus::RunEventBus;
+use crate::metrics::registry::Metrics;
+use crate::polling::aggregators::Aggregators;
+use crate::polling::background_job_runner::job_queue::{JobControl, JobQueue};
+use crate::polling::background_job_runner::response_capture::{write_captured, ResponseCapture};
+use crate::polling::background_job_runner::run_spans::RunSpans;
+use crate::polling::background_job_runner::{BackgroundJobRunner, ResumeOutcome};
+use crate::polling::dto::{
+    FaultyServerResponse, QueueInfo, Run, RunId, RunJob, RunJobResult, RunSample, RunStatus,
+    ValueSum,
+};
+use crate::polling::errors::{ServiceError, ServiceResult};
 use crate::polling::request_sender::RequestSender;
 use crate::polling::run_repository::RunRepository;
 
+const QUEUE_MAINTENANCE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+/// Deadline of jobs whose end is not representable as an `Instant`
+const FAR_FUTURE: std::time::Duration = std::time::Duration::from_secs(30 * 365 * 24 * 60 * 60);
+
+/// Counters of a job since its last sample
+#[derive(Debug, Default)]
+struct JobProgress {
+    attempts: u64,
+    successes: u64,
+    errors: u64,
+    sum: ValueSum,
+}
+
+impl JobProgress {
+    fn add_success(&mut self, value: i64) {
+        self.attempts += 1;
+        self.successes += 1;
+        self.sum.add(value);
+    }
+
+    fn add_error(&mut self) {
+        self.attempts += 1;
+        self.errors += 1;
+    }
+
+    fn take_sample(&mut self, at: DateTime<Utc>) -> RunSample {
+        let progress = std::mem::take(self);
+        RunSample {
+            at,
+            attempts: progress.attempts,
+            successes: progress.successes,
+            errors: progress.errors,
+            sum: progress.sum.sum,
+        }
+    }
+}
+
+/// Handles shared by the runtime of the runner and every job it runs
+#[derive(Clone)]
+struct RunnerContext<R, S> {
+    run_repo: R,
+    queue: Arc<Mutex<JobQueue>>,
+    /// Notified whenever a job may be taken from the queue
+    queue_changed: Arc<Notify>,
+    request_sender: S,
+    sample_interval: Option<std::time::Duration>,
+    events: RunEventBus,
+    run_spans: RunSpans,
+}
+
 #[derive(Clone, Debug)]
 pub struct TokioBackgroundJobRunner<R, S> {
-    tx: Sender<RunJob>,
+    queue: Arc<Mutex<JobQueue>>,
+    queue_changed: Arc<Notify>,
+    events: RunEventBus,
+    metrics: Metrics,
+    run_spans: RunSpans,
     // required because we do not use request sender in struct itself
     request_sender_type: PhantomData<S>,
     run_repo_type: PhantomData<R>,
@@ -25,110 +92,507 @@ where
     R: RunRepository + 'static,
     S: RequestSender + 'static,
 {
-    pub async fn new(run_repo: R, request_sender: S, settings: PollingSettings) -> Self {
-        let (tx, rx) = async_channel::bounded(settings.max_pending_runs.max(1));
-        {
-            std::thread::spawn(move || Self::init_runtime(run_repo, rx, request_sender, settings));
-        }
+    pub async fn new(
+        run_repo: R,
+        request_sender: S,
+        settings: PollingSettings,
+        events: RunEventBus,
+        metrics: Metrics,
+    ) -> Self {
+        let sample_interval = settings.sample_interval();
+        let queue = Arc::new(Mutex::new(JobQueue::new(settings)));
+        let queue_changed = Arc::new(Notify::new());
+        let run_spans = RunSpans::default();
+        let context = RunnerContext {
+            run_repo,
+            queue: Arc::clone(&queue),
+            queue_changed: Arc::clone(&queue_changed),
+            request_sender,
+            sample_interval,
+            events: events.clone(),
+            run_spans: run_spans.clone(),
+        };
+        std::thread::spawn(move || Self::init_runtime(context));
 
         Self {
-            tx,
+            queue,
+            queue_changed,
+            events,
+            metrics,
+            run_spans,
             request_sender_type: PhantomData,
             run_repo_type: PhantomData,
         }
     }
 
     #[tokio::main]
-    async fn init_runtime(
-        run_repo: R,
-        rx: Receiver<RunJob>,
-        request_sender: S,
-        settings: PollingSettings,
+    async fn init_runtime(context: RunnerContext<R, S>) {
+        tokio::spawn(Self::maintain_queue(context.clone()));
+
+        // the queue only hands out jobs while there are free worker slots
+        loop {
+            let (job, control) = Self::next_job(&context).await;
+            let span = context.run_spans.take(job.id);
+            tokio::spawn(Self::process_run_job(job, control, context.clone()).instrument(span));
+        }
+    }
+
+    async fn process_run_job(
+        job: RunJob,
+        control: watch::Receiver<JobControl>,
+        context: RunnerContext<R, S>,
     ) {
-        for _ in 0..settings.max_concurrent_runs - 1 {
+        let RunnerContext {
+            run_repo,
+            queue,
+            queue_changed,
+            request_sender,
+            sample_interval,
+            events,
+            ..
+        } = context;
+        let requested_seconds = job.duration.as_secs();
+        tracing::info!(
+            run_id = %job.id,
+            concurrent_requests = job.concurrent_requests,
+            seconds = requested_seconds,
+            "Run started"
+        );
+        events.publish(job.id, RunEventKind::Started);
+
+        let id = job.id;
+        let progress = Mutex::new(JobProgress::default());
+        let (finished_tx, finished_rx) = oneshot::channel();
+        let (capture, captured) = match job.capture {
+            Some(mode) => {
+                let (capture, captured) = ResponseCapture::new(mode);
+                (Some(capture), Some(captured))
+            }
+            None => (None, None),
+        };
+        if let Some(captured) = captured {
+            // the writer may still be catching up once the run is finished,
+            // its slot is not held for it
             let run_repo = run_repo.clone();
-            let settings = settings.clone();
-            let rx = rx.clone();
-            let request_sender = request_sender.clone();
+            tokio::spawn(
+                async move { write_captured(&run_repo, id, captured).await }.in_current_span(),
+            );
+        }
+        let sampled_control = control.clone();
+        let execution = async {
+            let result = Self::execute_job(job, control, &request_sender, &progress, capture).await;
+            let _ = finished_tx.send(());
+            result
+        };
+        // the last sample is written before the run is reported finished
+        let (result, ()) = tokio::join!(
+            execution,
+            Self::sample_progress(
+                &run_repo,
+                &events,
+                id,
+                &progress,
+                sampled_control,
+                sample_interval,
+                finished_rx
+            )
+        );
+        queue.lock().unwrap().finish(result.id);
+        queue_changed.notify_one();
 
-            tokio::spawn(async move {
-                Self::process_run_jobs(run_repo, rx, request_sender, settings).await;
-            });
+        let run = Run {
+            id: result.id,
+            status: RunStatus::Finished,
+            successful_responses_count: result.successful_responses_count,
+            sum: result.sum.sum,
+            sum_overflowed: result.sum.overflowed,
+            requested_seconds: Some(requested_seconds),
+            effective_seconds: Some(result.duration.as_secs()),
+            rerun_of: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            aggregates: result.aggregates,
+        };
+        if let Err(e) = run_repo.finish_run(&run, &result.stats).await {
+            tracing::error!(run_id = %result.id, error = %e, "Failed to save finished run");
+            events.publish(
+                result.id,
+                RunEventKind::Failed {
+                    reason: "Failed to save results of the run".into(),
+                },
+            );
+            return;
         }
-        Self::process_run_jobs(run_repo, rx, request_sender, settings).await;
+        tracing::info!(
+            run_id = %result.id,
+            successful_responses_count = result.successful_responses_count,
+            effective_seconds = result.duration.as_secs(),
+            "Run finished"
+        );
+        events.publish(
+            result.id,
+            RunEventKind::Finished {
+                successful_responses_count: result.successful_responses_count,
+            },
+        );
     }
 
-    async fn process_run_jobs(
-        run_repo: R,
-        rx: Receiver<RunJob>,
-        request_sender: S,
-        settings: PollingSettings,
+    /// Saves and publishes counters of the job every interval, and once more when it finishes.
+    /// While the job is paused only responses of requests sent before the pause are sampled.
+    async fn sample_progress(
+        run_repo: &R,
+        events: &RunEventBus,
+        id: RunId,
+        progress: &Mutex<JobProgress>,
+        control: watch::Receiver<JobControl>,
+        interval: Option<std::time::Duration>,
+        mut finished: oneshot::Receiver<()>,
     ) {
+        let interval = match interval {
+            Some(interval) => interval,
+            None => return,
+        };
+        let mut ticks = tokio::time::interval_at(Instant::now() + interval, interval);
         loop {
-            let job = rx.recv().await.expect("Channel is closed");
+            let finished = tokio::select! {
+                _ = ticks.tick() => false,
+                _ = &mut finished => true,
+            };
 
-            let result =
-                Self::execute_job(job, &request_sender, settings.concurrent_requests_per_run).await;
+            let idle = finished || control.borrow().paused;
+            let sample = progress.lock().unwrap().take_sample(Utc::now());
+            if !idle || sample.attempts > 0 {
+                events.publish(
+                    id,
+                    RunEventKind::Progress {
+                        attempts: sample.attempts,
+                        successes: sample.successes,
+                        errors: sample.errors,
+                    },
+                );
+                if let Err(e) = run_repo.save_run_sample(id, &sample).await {
+                    tracing::error!(run_id = %id, error = %e, "Failed to save sample of run");
+                }
+            }
+            if finished {
+                return;
+            }
+        }
+    }
 
-            run_repo
-                .update_run(&Run {
-                    id: result.id,
-                    status: RunStatus::Finished,
-                    successful_responses_count: result.successful_responses,
-                    sum: result.value_sum,
-                })
-                .await
-                .expect("Failed to update run in repository");
+    /// Enqueues due deferred jobs and drops expired pending ones
+    async fn maintain_queue(context: RunnerContext<R, S>) {
+        let RunnerContext {
+            run_repo,
+            queue,
+            queue_changed,
+            events,
+            run_spans,
+            ..
+        } = context;
+        let mut interval = tokio::time::interval(QUEUE_MAINTENANCE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let (due, expired) = {
+                let mut queue = queue.lock().unwrap();
+                let now = Utc::now();
+                (queue.enqueue_due(now), queue.remove_expired(now))
+            };
+            for id in due {
+                queue_changed.notify_one();
+                if let Err(e) = run_repo.update_run_status(id, RunStatus::InProgress).await {
+                    tracing::error!(run_id = %id, error = %e, "Failed to save start of deferred run");
+                }
+            }
+            for id in expired {
+                run_spans.take(id).in_scope(
+                    || tracing::warn!(run_id = %id, "Run expired while waiting in the queue"),
+                );
+                let saved = run_repo
+                    .update_run(&Run {
+                        id,
+                        status: RunStatus::Expired,
+                        successful_responses_count: 0,
+                        sum: 0,
+                        sum_overflowed: false,
+                        requested_seconds: None,
+                        effective_seconds: None,
+                        rerun_of: None,
+                        concurrent_requests: None,
+                        polling_address: None,
+                        target_id: None,
+                        aggregates: None,
+                    })
+                    .await;
+                match saved {
+                    Ok(()) => {}
+                    // Jobs are queued before their runs are saved, so the row can be missing
+                    Err(ServiceError::NotFound) => {
+                        tracing::warn!(run_id = %id, "Expired run has not been saved, skipping it");
+                        continue;
+                    }
+                    Err(e) => {
+                        tracing::error!(run_id = %id, error = %e, "Failed to save expiry of run")
+                    }
+                }
+                events.publish(
+                    id,
+                    RunEventKind::Failed {
+                        reason: "Expired while waiting in the queue".into(),
+                    },
+                );
+            }
+        }
+    }
+
+    /// Waits for the next job to execute, first resuming paused jobs whose lent slots are free
+    async fn next_job(context: &RunnerContext<R, S>) -> (RunJob, watch::Receiver<JobControl>) {
+        loop {
+            let (resumed, job) = {
+                let mut queue = context.queue.lock().unwrap();
+                let now = Utc::now();
+                (queue.resume_waiting(now), queue.pop(now))
+            };
+            for id in resumed {
+                tracing::info!(run_id = %id, "Run resumed in a freed slot");
+                if let Err(e) = context
+                    .run_repo
+                    .update_run_status(id, RunStatus::InProgress)
+                    .await
+                {
+                    tracing::error!(run_id = %id, error = %e, "Failed to save resumption of run");
+                }
+                context.events.publish(id, RunEventKind::Resumed);
+            }
+            if let Some(job) = job {
+                return job;
+            }
+            context.queue_changed.notified().await;
         }
     }
 
     async fn execute_job(
         job: RunJob,
+        control: watch::Receiver<JobControl>,
         request_sender: &S,
-        concurrent_requests_per_run: usize,
+        progress: &Mutex<JobProgress>,
+        capture: Option<ResponseCapture>,
     ) -> RunJobResult {
-        let value_sum = Arc::new(Mutex::new(0u64));
-        let successful_responses = Arc::new(Mutex::new(0u64));
+        let aggregators = Mutex::new(Aggregators::new(&job.aggregators, &job.histogram_buckets));
+
+        // no more requests are in flight than there are slots, so one is always free
+        let free_slots = Mutex::new((0..job.concurrent_requests).rev().collect::<Vec<_>>());
 
         let requests = stream::repeat(())
             .map(|_| {
-                let id = job.id;
-                async move { request_sender.send_request(id).await }
+                let (job, free_slots) = (&job, &free_slots);
+                async move {
+                    let slot = free_slots.lock().unwrap().pop().expect("No free slot");
+                    let at = Utc::now();
+                    let started = Instant::now();
+                    // carries the run id as well, filters changed at runtime only apply to
+                    // spans opened afterwards
+                    let span = tracing::debug_span!(
+                        "upstream_request",
+                        run_id = %job.id,
+                        slot,
+                        status = tracing::field::Empty
+                    );
+                    let response = request_sender
+                        .send_request(&job.target, job.id)
+                        .instrument(span.clone())
+                        .await;
+                    let latency = started.elapsed();
+                    if let Some(status) = response.status {
+                        span.record("status", &status);
+                    }
+                    free_slots.lock().unwrap().push(slot);
+                    (slot, at, latency, response)
+                }
             })
-            .buffer_unordered(concurrent_requests_per_run);
-
-        let fut = requests.for_each_concurrent(None, |response| async {
-            let successful_responses = Arc::clone(&successful_responses);
-            let value_sum = Arc::clone(&value_sum);
+            .buffer_unordered(job.concurrent_requests);
 
-            async move {
-                if let FaultyServerResponse::Ok { value } = response {
-                    *successful_responses.lock().unwrap() += 1;
-                    *value_sum.lock().unwrap() += value as u64;
+        let fut = requests.for_each(|(slot, at, latency, response)| {
+            match response.result {
+                FaultyServerResponse::Ok { value } => {
+                    aggregators.lock().unwrap().add(value);
+                    progress.lock().unwrap().add_success(value);
                 }
+                FaultyServerResponse::Err { .. } => progress.lock().unwrap().add_error(),
             }
-            .await
+            if let Some(capture) = &capture {
+                capture.offer(slot, at, latency, response);
+            }
+            future::ready(())
         });
 
-        tokio::time::timeout(job.duration, fut)
-            .await
-            .expect_err("Run finished earlier than expected timeout");
+        let duration = Self::run_for_duration(fut, control).await;
+
+        if let Some(dropped) = capture.map(|capture| capture.dropped()).filter(|&d| d > 0) {
+            tracing::warn!(run_id = %job.id, dropped, "Dropped captured responses of run");
+        }
 
-        let successful_responses = *successful_responses.lock().unwrap();
-        let value_sum = *value_sum.lock().unwrap();
+        let aggregators = aggregators.into_inner().unwrap();
+        let sum = aggregators.sum();
+        if sum.overflowed {
+            tracing::warn!(run_id = %job.id, "Sum of values of run overflowed");
+        }
 
         RunJobResult {
             id: job.id,
-            successful_responses,
-            value_sum,
+            successful_responses_count: aggregators.count(),
+            sum,
+            stats: aggregators.stats(),
+            aggregates: aggregators.results(),
+            duration,
+        }
+    }
+
+    /// Polls the future until the job duration, which may be changed meanwhile, elapses.
+    /// The future is not polled while the job is paused, so no new requests are sent.
+    /// Returns the time actually spent running.
+    async fn run_for_duration(
+        fut: impl Future<Output = ()>,
+        mut control: watch::Receiver<JobControl>,
+    ) -> std::time::Duration {
+        let mut active_before = std::time::Duration::default();
+        let mut resumed_at = Some(Instant::now());
+        let mut control_open = true;
+        tokio::pin!(fut);
+
+        loop {
+            let current = *control.borrow();
+            resumed_at = match (current.paused, resumed_at) {
+                (true, Some(resumed_at)) => {
+                    active_before += resumed_at.elapsed();
+                    None
+                }
+                (false, None) => Some(Instant::now()),
+                (_, resumed_at) => resumed_at,
+            };
+
+            match resumed_at {
+                None if control_open => control_open = control.changed().await.is_ok(),
+                // nobody is left to resume the job
+                None => return active_before,
+                Some(resumed_at) => {
+                    let deadline = resumed_at
+                        .checked_add(current.duration.saturating_sub(active_before))
+                        .unwrap_or_else(|| Instant::now() + FAR_FUTURE);
+                    tokio::select! {
+                        _ = &mut fut => unreachable!("Run finished earlier than expected timeout"),
+                        _ = tokio::time::sleep_until(deadline) => {
+                            return active_before + resumed_at.elapsed()
+                        }
+                        changed = control.changed(), if control_open => {
+                            control_open = changed.is_ok()
+                        }
+                    }
+                }
+            }
         }
     }
 }
 
 #[async_trait(? Send)]
 impl<R: RunRepository, S: RequestSender> BackgroundJobRunner for TokioBackgroundJobRunner<R, S> {
-    async fn try_push_job(&self, run_job: RunJob) -> ServiceResult<()> {
-        Ok(self.tx.try_send(run_job)?)
+    async fn try_push_job(&self, run_job: RunJob) -> ServiceResult<DateTime<Utc>> {
+        let id = run_job.id;
+        let estimated_start_at = {
+            let mut queue = self.queue.lock().unwrap();
+            let estimated_start_at = queue.try_push(run_job, Utc::now()).map_err(|retry_at| {
+                self.metrics.count_rejected_run();
+                ServiceError::TooManyRequests {
+                    retry_at: Some(retry_at),
+                }
+            })?;
+            // published before the job can be taken, which publishes its start
+            self.events
+                .publish(id, RunEventKind::Queued { estimated_start_at });
+            self.run_spans.open(id, estimated_start_at);
+            estimated_start_at
+        };
+        self.queue_changed.notify_one();
+        Ok(estimated_start_at)
+    }
+
+    async fn try_push_jobs(
+        &self,
+        run_jobs: Vec<RunJob>,
+        all_or_nothing: bool,
+    ) -> Vec<ServiceResult<DateTime<Utc>>> {
+        let now = Utc::now();
+        let ids = run_jobs.iter().map(|job| job.id).collect::<Vec<_>>();
+        let too_many_requests = |retry_at| ServiceError::TooManyRequests {
+            retry_at: Some(retry_at),
+        };
+
+        let results = {
+            let mut queue = self.queue.lock().unwrap();
+            let results = if all_or_nothing {
+                let count = run_jobs.len();
+                match queue.try_push_all(run_jobs, now) {
+                    Ok(estimated_starts) => estimated_starts.into_iter().map(Ok).collect(),
+                    Err(retry_at) => vec![Err(too_many_requests(retry_at)); count],
+                }
+            } else {
+                run_jobs
+                    .into_iter()
+                    .map(|job| queue.try_push(job, now).map_err(too_many_requests))
+                    .collect::<Vec<_>>()
+            };
+            for (&id, result) in ids.iter().zip(&results) {
+                match *result {
+                    Ok(estimated_start_at) => {
+                        self.events
+                            .publish(id, RunEventKind::Queued { estimated_start_at });
+                        self.run_spans.open(id, estimated_start_at);
+                    }
+                    Err(_) => self.metrics.count_rejected_run(),
+                }
+            }
+            results
+        };
+
+        for _ in results.iter().filter(|r| r.is_ok()) {
+            self.queue_changed.notify_one();
+        }
+        results
+    }
+
+    async fn set_remaining_duration(&self, id: RunId, remaining: std::time::Duration) -> bool {
+        self.queue
+            .lock()
+            .unwrap()
+            .set_remaining_duration(id, remaining, Utc::now())
+    }
+
+    async fn pause_job(&self, id: RunId, lend_slot: bool) -> bool {
+        let paused = self.queue.lock().unwrap().pause(id, lend_slot, Utc::now());
+        if paused && lend_slot {
+            self.queue_changed.notify_one();
+        }
+        paused
+    }
+
+    async fn resume_job(&self, id: RunId) -> ResumeOutcome {
+        self.queue.lock().unwrap().resume(id, Utc::now())
+    }
+
+    async fn cancel_job(&self, id: RunId) -> bool {
+        let cancelled = self.queue.lock().unwrap().cancel(id);
+        if cancelled {
+            self.run_spans
+                .take(id)
+                .in_scope(|| tracing::info!(run_id = %id, "Run cancelled"));
+        }
+        cancelled
+    }
+
+    async fn get_queue(&self) -> QueueInfo {
+        self.queue.lock().unwrap().info(Utc::now())
     }
 }
 
@@ -137,10 +601,10 @@ mod should {
     use std::str::FromStr;
 
     use super::*;
-    use crate::polling::dto::RunId;
-    use crate::polling::errors::ServiceError;
-    use crate::polling::request_sender::MockRequestSender;
+    use crate::configuration::settings::PriorityLimits;
+    use crate::polling::dto::{PolledResponse, RunPriority};
     use crate::polling::run_repository::MockRunRepository;
+    use crate::targets::dto::TargetDefinition;
     use tokio::time::sleep;
 
     fn mock_run_repo() -> MockRunRepository {
@@ -149,31 +613,82 @@ mod should {
         r
     }
 
-    fn mock_request_sender() -> MockRequestSender {
-        let mut r = MockRequestSender::new();
-        r.expect_clone().returning(|| {
-            let mut r = MockRequestSender::new();
-            r.expect_send_request()
-                .return_const(FaultyServerResponse::Ok { value: 50 });
-            r
-        });
-        r.expect_send_request()
-            .return_const(FaultyServerResponse::Ok { value: 50 });
-        r
+    /// Answers every request, never within the poll that sent it, as a server on the network
+    #[derive(Clone)]
+    struct FakeRequestSender;
+
+    #[async_trait]
+    impl RequestSender for FakeRequestSender {
+        async fn send_request(&self, _: &TargetDefinition, _: RunId) -> PolledResponse {
+            tokio::task::yield_now().await;
+            polled_response()
+        }
+    }
+
+    fn polled_response() -> PolledResponse {
+        PolledResponse {
+            status: Some(200),
+            body: r#"{"value": 50}"#.into(),
+            result: FaultyServerResponse::Ok { value: 50 },
+        }
+    }
+
+    fn settings(max_concurrent_runs: usize, max_pending_runs: usize) -> PollingSettings {
+        PollingSettings {
+            polling_address: "127.0.0.1:0".into(),
+            max_concurrent_runs,
+            max_pending_runs,
+            concurrent_requests_per_run: 3,
+            max_pending_runs_per_priority: PriorityLimits::default(),
+            priority_aging_seconds: 0,
+            max_queue_wait_seconds: None,
+            max_scheduled_runs: None,
+            max_concurrent_requests_per_run: None,
+            allowed_polling_addresses: Vec::new(),
+            histogram_buckets: Vec::new(),
+            sample_interval_seconds: None,
+        }
+    }
+
+    fn run_job(seconds: u64) -> RunJob {
+        RunJob {
+            id: RunId::new_v4(),
+            duration: std::time::Duration::from_secs(seconds),
+            priority: RunPriority::Normal,
+            max_queue_wait: None,
+            start_at: None,
+            concurrent_requests: 3,
+            target: TargetDefinition::from_url("127.0.0.1:0".into()),
+            histogram_buckets: Vec::new(),
+            aggregators: Vec::new(),
+            capture: None,
+        }
+    }
+
+    async fn runner(
+        run_repo: MockRunRepository,
+        settings: PollingSettings,
+        events: RunEventBus,
+    ) -> TokioBackgroundJobRunner<MockRunRepository, FakeRequestSender> {
+        TokioBackgroundJobRunner::new(
+            run_repo,
+            FakeRequestSender,
+            settings,
+            events,
+            Metrics::default(),
+        )
+        .await
     }
 
     #[actix_rt::test]
     async fn successfully_execute_single_job() {
-        let job = RunJob {
-            id: RunId::new_v4(),
-            duration: std::time::Duration::from_secs(3),
-        };
+        let job = run_job(3);
 
         let run_repo = {
             let mut r = mock_run_repo();
             let expected_id = job.id;
-            r.expect_update_run()
-                .withf(move |r| {
+            r.expect_finish_run()
+                .withf(move |r, _| {
                     r.id == expected_id
                         && r.status == RunStatus::Finished
                         && r.successful_responses_count > 0
@@ -182,43 +697,517 @@ mod should {
                 .return_const(ServiceResult::Ok(()));
             r
         };
-        let request_sender = mock_request_sender();
-        let settings = PollingSettings {
-            polling_address: "127.0.0.1:0".into(),
-            max_concurrent_runs: 3,
-            max_pending_runs: 3,
-            concurrent_requests_per_run: 3,
-        };
-
-        let runner = TokioBackgroundJobRunner::new(run_repo, request_sender, settings).await;
+        let runner = runner(run_repo, settings(3, 3), RunEventBus::default()).await;
 
         let actual_result = runner.try_push_job(job).await;
         sleep(std::time::Duration::from_secs(4)).await;
-        assert_eq!(Ok(()), actual_result);
+        assert!(actual_result.is_ok());
     }
 
     #[actix_rt::test]
-    async fn return_too_many_requests_error_when_exceeds_run_concurrency() {
-        let run_repo = mock_run_repo();
-        let request_sender = mock_request_sender();
+    async fn save_stats_of_finished_job() {
+        let job = RunJob {
+            histogram_buckets: vec![0, 50],
+            ..run_job(1)
+        };
+
+        let (stats_tx, stats_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(move || {
+                let stats_tx = stats_tx.clone();
+                let mut r = MockRunRepository::new();
+                r.expect_save_run_sample().return_const(Ok(()));
+                r.expect_finish_run().returning(move |run, stats| {
+                    stats_tx.send((run.id, stats.clone())).unwrap();
+                    Ok(())
+                });
+                r
+            });
+            r
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+        let runner = runner(run_repo, settings(1, 1), events).await;
+
+        let estimated_start_at = runner.try_push_job(job.clone()).await.unwrap();
+
+        let (id, stats) = stats_rx
+            .recv_timeout(std::time::Duration::from_secs(3))
+            .unwrap();
+        assert_eq!(job.id, id);
+        assert!(stats.count > 0);
+        let mut kinds = Vec::new();
+        while let Ok(Ok(event)) =
+            tokio::time::timeout(std::time::Duration::from_secs(1), published.recv()).await
+        {
+            assert_eq!(job.id, event.run_id);
+            kinds.push(event.kind);
+        }
+        assert_eq!(
+            vec![
+                RunEventKind::Queued { estimated_start_at },
+                RunEventKind::Started
+            ],
+            kinds[..2]
+        );
+        assert_eq!(
+            Some(&RunEventKind::Finished {
+                successful_responses_count: stats.count
+            }),
+            kinds.last()
+        );
+        assert!(kinds[2..kinds.len() - 1]
+            .iter()
+            .all(|kind| matches!(kind, RunEventKind::Progress { .. })));
+        assert_eq!((Some(50), Some(50)), (stats.min, stats.max));
+        assert_eq!(Some(0.0), stats.variance);
+        assert_eq!(
+            vec![0, stats.count, 0],
+            stats.histogram.iter().map(|b| b.count).collect::<Vec<_>>()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn report_failure_when_finished_run_cannot_be_saved() {
+        let job = RunJob {
+            concurrent_requests: 1,
+            ..run_job(1)
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(|| {
+                let mut r = MockRunRepository::new();
+                r.expect_save_run_sample().return_const(Ok(()));
+                r.expect_finish_run()
+                    .return_const(Err(ServiceError::InternalServerError));
+                r
+            });
+            r
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+        let runner = runner(run_repo, settings(1, 1), events).await;
+
+        runner.try_push_job(job.clone()).await.unwrap();
+
+        let mut last = None;
+        while let Ok(Ok(event)) =
+            tokio::time::timeout(std::time::Duration::from_secs(2), published.recv()).await
+        {
+            last = Some(event.kind);
+        }
+        assert_eq!(
+            Some(RunEventKind::Failed {
+                reason: "Failed to save results of the run".into()
+            }),
+            last
+        );
+    }
+
+    #[actix_rt::test]
+    async fn sample_counters_of_running_job() {
+        let job = run_job(2);
+
+        let (sample_tx, sample_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(move || {
+                let sample_tx = sample_tx.clone();
+                let mut r = MockRunRepository::new();
+                r.expect_clone().returning(MockRunRepository::new);
+                r.expect_finish_run().return_const(Ok(()));
+                r.expect_save_run_sample().returning(move |_, sample| {
+                    sample_tx.send(sample.clone()).unwrap();
+                    Ok(())
+                });
+                r
+            });
+            r
+        };
         let settings = PollingSettings {
-            polling_address: "127.0.0.1:0".into(),
-            max_concurrent_runs: 1,
-            max_pending_runs: 1,
-            concurrent_requests_per_run: 3,
+            sample_interval_seconds: Some(1),
+            ..settings(1, 1)
         };
+        let runner = runner(run_repo, settings, RunEventBus::default()).await;
+
+        runner.try_push_job(job).await.unwrap();
 
-        let runner = TokioBackgroundJobRunner::new(run_repo, request_sender, settings).await;
+        let sample = sample_rx
+            .recv_timeout(std::time::Duration::from_secs(3))
+            .unwrap();
+        assert!(sample.successes > 0);
+        assert_eq!(sample.attempts, sample.successes + sample.errors);
+        assert_eq!(50 * sample.successes as i128, sample.sum);
+    }
+
+    #[actix_rt::test]
+    async fn return_too_many_requests_error_when_exceeds_run_concurrency() {
+        let metrics = Metrics::default();
+        let runner = TokioBackgroundJobRunner::new(
+            mock_run_repo(),
+            FakeRequestSender,
+            settings(1, 1),
+            RunEventBus::default(),
+            metrics.clone(),
+        )
+        .await;
 
         let job = RunJob {
             id: RunId::from_str("247fe111-0018-485e-9971-66cb27308221").unwrap(),
-            duration: std::time::Duration::from_secs(10),
+            ..run_job(10)
         };
         runner.try_push_job(job.clone()).await.unwrap();
         sleep(std::time::Duration::from_secs(1)).await;
         runner.try_push_job(job.clone()).await.unwrap();
         sleep(std::time::Duration::from_secs(1)).await;
         let actual_result = runner.try_push_job(job).await;
-        assert_eq!(Err(ServiceError::TooManyRequests), actual_result);
+        match actual_result {
+            Err(ServiceError::TooManyRequests {
+                retry_at: Some(retry_at),
+            }) => assert!(retry_at > Utc::now() + chrono::Duration::seconds(5)),
+            other => panic!("Expected too many requests error, got {:?}", other),
+        }
+        assert!(metrics
+            .encode()
+            .lines()
+            .any(|l| l == "poller_rejected_runs_total 1"));
+    }
+
+    #[actix_rt::test]
+    async fn report_running_and_pending_jobs() {
+        let runner = runner(mock_run_repo(), settings(1, 2), RunEventBus::default()).await;
+
+        let running = run_job(10);
+        let pending = run_job(5);
+        runner.try_push_job(running.clone()).await.unwrap();
+        sleep(std::time::Duration::from_secs(1)).await;
+        runner.try_push_job(pending.clone()).await.unwrap();
+
+        let queue = runner.get_queue().await;
+
+        assert_eq!(
+            vec![running.id],
+            queue.running.iter().map(|r| r.id).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![pending.id],
+            queue.pending.iter().map(|p| p.id).collect::<Vec<_>>()
+        );
+        assert!(queue.pending[0].estimated_start_at > queue.pending[0].enqueued_at);
+    }
+
+    #[actix_rt::test]
+    async fn expire_jobs_waiting_longer_than_allowed() {
+        let (expired_tx, expired_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(move || {
+                let expired_tx = expired_tx.clone();
+                let mut r = MockRunRepository::new();
+                r.expect_update_run()
+                    .withf(|r| r.status == RunStatus::Expired)
+                    .returning(move |r| {
+                        expired_tx.send(r.id).unwrap();
+                        Ok(())
+                    });
+                r
+            });
+            r
+        };
+
+        let runner = runner(run_repo, settings(1, 2), RunEventBus::default()).await;
+
+        let running = run_job(10);
+        let expiring = RunJob {
+            max_queue_wait: Some(std::time::Duration::from_secs(1)),
+            ..run_job(5)
+        };
+        runner.try_push_job(running).await.unwrap();
+        sleep(std::time::Duration::from_secs(1)).await;
+        runner.try_push_job(expiring.clone()).await.unwrap();
+        sleep(std::time::Duration::from_secs(3)).await;
+
+        assert_eq!(Ok(expiring.id), expired_rx.try_recv());
+        assert!(runner.get_queue().await.pending.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn skip_expired_job_whose_run_was_not_saved() {
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(|| {
+                let mut r = MockRunRepository::new();
+                r.expect_save_run_sample().return_const(Ok(()));
+                r.expect_update_run()
+                    .return_const(Err(ServiceError::NotFound));
+                r
+            });
+            r
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+        let runner = runner(run_repo, settings(1, 2), events).await;
+
+        let running = run_job(10);
+        let expiring = RunJob {
+            max_queue_wait: Some(std::time::Duration::from_secs(1)),
+            ..run_job(5)
+        };
+        runner.try_push_job(running).await.unwrap();
+        sleep(std::time::Duration::from_secs(1)).await;
+        runner.try_push_job(expiring.clone()).await.unwrap();
+        sleep(std::time::Duration::from_secs(3)).await;
+
+        assert!(runner.get_queue().await.pending.is_empty());
+        while let Ok(event) = published.try_recv() {
+            assert!(
+                event.run_id != expiring.id || !matches!(event.kind, RunEventKind::Failed { .. })
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn enqueue_deferred_job_when_due() {
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(move || {
+                let started_tx = started_tx.clone();
+                let mut r = MockRunRepository::new();
+                r.expect_update_run_status()
+                    .withf(|_, status| *status == RunStatus::InProgress)
+                    .returning(move |id, _| {
+                        started_tx.send(id).unwrap();
+                        Ok(())
+                    });
+                r
+            });
+            r
+        };
+
+        let runner = runner(run_repo, settings(1, 1), RunEventBus::default()).await;
+
+        let start_at = Utc::now() + chrono::Duration::seconds(1);
+        let deferred = RunJob {
+            start_at: Some(start_at),
+            ..run_job(10)
+        };
+
+        assert_eq!(Ok(start_at), runner.try_push_job(deferred.clone()).await);
+        assert_eq!(
+            vec![deferred.id],
+            runner
+                .get_queue()
+                .await
+                .scheduled
+                .iter()
+                .map(|s| s.id)
+                .collect::<Vec<_>>()
+        );
+        sleep(std::time::Duration::from_secs(3)).await;
+
+        assert_eq!(Ok(deferred.id), started_rx.try_recv());
+        assert_eq!(
+            vec![deferred.id],
+            runner
+                .get_queue()
+                .await
+                .running
+                .iter()
+                .map(|r| r.id)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[actix_rt::test]
+    async fn keep_enqueueing_deferred_jobs_when_status_cannot_be_saved() {
+        let (started_tx, started_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(move || {
+                let started_tx = started_tx.clone();
+                let mut r = MockRunRepository::new();
+                r.expect_update_run_status().returning(move |id, _| {
+                    started_tx.send(id).unwrap();
+                    Err(ServiceError::InternalServerError)
+                });
+                r
+            });
+            r
+        };
+
+        let runner = runner(run_repo, settings(2, 0), RunEventBus::default()).await;
+
+        let first = RunJob {
+            start_at: Some(Utc::now() + chrono::Duration::seconds(1)),
+            ..run_job(10)
+        };
+        let second = RunJob {
+            start_at: Some(Utc::now() + chrono::Duration::seconds(2)),
+            ..run_job(10)
+        };
+        runner.try_push_job(first.clone()).await.unwrap();
+        runner.try_push_job(second.clone()).await.unwrap();
+        sleep(std::time::Duration::from_secs(4)).await;
+
+        assert_eq!(
+            vec![first.id, second.id],
+            started_rx.try_iter().collect::<Vec<_>>()
+        );
+        assert_eq!(2, runner.get_queue().await.running.len());
+    }
+
+    #[actix_rt::test]
+    async fn start_pending_job_in_slot_lent_by_paused_one() {
+        let run_repo = mock_run_repo();
+
+        let runner = runner(run_repo, settings(1, 1), RunEventBus::default()).await;
+
+        let paused = run_job(10);
+        let pending = run_job(10);
+        runner.try_push_job(paused.clone()).await.unwrap();
+        sleep(std::time::Duration::from_secs(1)).await;
+        runner.try_push_job(pending.clone()).await.unwrap();
+
+        assert!(runner.pause_job(paused.id, true).await);
+        sleep(std::time::Duration::from_secs(1)).await;
+
+        let queue = runner.get_queue().await;
+        assert_eq!(
+            vec![(paused.id, true), (pending.id, false)],
+            queue
+                .running
+                .iter()
+                .map(|r| (r.id, r.paused))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            ResumeOutcome::WaitingForSlot,
+            runner.resume_job(paused.id).await
+        );
+    }
+
+    #[actix_rt::test]
+    async fn report_job_resumed_once_its_lent_slot_frees_up() {
+        let (resumed_tx, resumed_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_clone().returning(|| {
+                let mut r = MockRunRepository::new();
+                r.expect_save_run_sample().return_const(Ok(()));
+                r.expect_finish_run().return_const(Ok(()));
+                r
+            });
+            r.expect_update_run_status()
+                .withf(|_, status| *status == RunStatus::InProgress)
+                .returning(move |id, _| {
+                    resumed_tx.send(id).unwrap();
+                    Ok(())
+                });
+            r
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+        let runner = runner(run_repo, settings(1, 1), events).await;
+
+        let paused = run_job(10);
+        let pending = run_job(1);
+        runner.try_push_job(paused.clone()).await.unwrap();
+        sleep(std::time::Duration::from_secs(1)).await;
+        assert!(runner.pause_job(paused.id, true).await);
+        runner.try_push_job(pending).await.unwrap();
+        sleep(std::time::Duration::from_millis(100)).await;
+
+        assert_eq!(
+            ResumeOutcome::WaitingForSlot,
+            runner.resume_job(paused.id).await
+        );
+        assert!(resumed_rx.try_recv().is_err());
+        sleep(std::time::Duration::from_secs(2)).await;
+
+        assert_eq!(Ok(paused.id), resumed_rx.try_recv());
+        let mut resumed_events = Vec::new();
+        while let Ok(event) = published.try_recv() {
+            if event.kind == RunEventKind::Resumed {
+                resumed_events.push(event.run_id);
+            }
+        }
+        assert_eq!(vec![paused.id], resumed_events);
+    }
+
+    #[actix_rt::test]
+    async fn skip_samples_while_job_is_paused() {
+        let (control, control_rx) = watch::channel(JobControl {
+            duration: std::time::Duration::from_secs(1),
+            paused: true,
+        });
+        let (sample_tx, sample_rx) = std::sync::mpsc::channel();
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_save_run_sample().returning(move |_, sample| {
+                sample_tx.send(sample.clone()).unwrap();
+                Ok(())
+            });
+            r
+        };
+        let events = RunEventBus::default();
+        let progress = Mutex::new(JobProgress::default());
+        let (finished_tx, finished_rx) = oneshot::channel();
+        let pause_then_resume = async {
+            sleep(std::time::Duration::from_millis(350)).await;
+            assert!(sample_rx.try_recv().is_err());
+            control
+                .send(JobControl {
+                    duration: std::time::Duration::from_secs(1),
+                    paused: false,
+                })
+                .unwrap();
+            sleep(std::time::Duration::from_millis(150)).await;
+            finished_tx.send(()).unwrap();
+        };
+
+        futures::join!(
+            TokioBackgroundJobRunner::<MockRunRepository, FakeRequestSender>::sample_progress(
+                &run_repo,
+                &events,
+                RunId::new_v4(),
+                &progress,
+                control_rx,
+                Some(std::time::Duration::from_millis(100)),
+                finished_rx
+            ),
+            pause_then_resume
+        );
+
+        assert!(sample_rx.try_recv().is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn run_job_of_unrepresentable_duration_until_shortened() {
+        let (control, control_rx) = watch::channel(JobControl {
+            duration: std::time::Duration::MAX,
+            paused: false,
+        });
+        let shorten = async {
+            sleep(std::time::Duration::from_millis(100)).await;
+            control
+                .send(JobControl {
+                    duration: std::time::Duration::from_millis(200),
+                    paused: false,
+                })
+                .unwrap();
+        };
+
+        let (duration, ()) = futures::join!(
+            TokioBackgroundJobRunner::<MockRunRepository, FakeRequestSender>::run_for_duration(
+                future::pending(),
+                control_rx
+            ),
+            shorten
+        );
+
+        assert!(duration >= std::time::Duration::from_millis(200));
     }
 }
@@ -1,6 +1,12 @@
-use actix_web::{guard, web, Responder};
+use actix_web::http::header::CONTENT_TYPE;
+use actix_web::web::Bytes;
+use actix_web::{guard, web, HttpResponse, Responder};
+use futures::{stream, StreamExt};
 
-use crate::polling::dto::{RunId, StartRunRequestDto};
+use crate::polling::dto::{
+    BatchStartRunRequestDto, ExportRunsRequestDto, GetRunRequestDto, PauseRunRequestDto, RunId,
+    StartRunRequestDto, UpdateRunRequestDto,
+};
 use crate::polling::errors::ServiceResult;
 use crate::polling::polling_service::PollingService;
 
@@ -14,11 +20,132 @@ async fn start_run<T: PollingService>(
         .map(web::Json)
 }
 
+async fn start_runs<T: PollingService>(
+    service: web::Data<T>,
+    request_payload: web::Json<BatchStartRunRequestDto>,
+) -> ServiceResult<impl Responder> {
+    service
+        .start_runs(request_payload.into_inner())
+        .await
+        .map(web::Json)
+}
+
 async fn get_run<T: PollingService>(
     service: web::Data<T>,
     id: web::Path<RunId>,
+    get_run_request_dto: web::Query<GetRunRequestDto>,
+) -> ServiceResult<impl Responder> {
+    let id = id.into_inner();
+    match get_run_request_dto.wait {
+        Some(seconds) => {
+            service
+                .wait_for_run(id, std::time::Duration::from_secs(seconds))
+                .await
+        }
+        None => service.get_run(id).await,
+    }
+    .map(web::Json)
+}
+
+async fn get_run_stats<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    service.get_run_stats(id.into_inner()).await.map(web::Json)
+}
+
+async fn get_run_timeseries<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    service
+        .get_run_timeseries(id.into_inner())
+        .await
+        .map(web::Json)
+}
+
+async fn get_run_responses<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    let responses = service.get_run_responses(id.into_inner()).await?;
+
+    let lines = responses.map(|response| {
+        response.map(|response| {
+            let line = serde_json::to_string(&response).expect("Failed to serialize response");
+            Bytes::from(line + "\n")
+        })
+    });
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, "application/x-ndjson"))
+        .streaming(lines))
+}
+
+async fn export_runs<T: PollingService>(
+    service: web::Data<T>,
+    query: web::Query<ExportRunsRequestDto>,
+) -> ServiceResult<impl Responder> {
+    let ExportRunsRequestDto { format, filter } = query.into_inner();
+    let runs = service.export_runs(filter).await?;
+
+    let header = stream::iter(format.header().map(|header| Ok(Bytes::from(header))));
+    let rows = runs.map(move |run| run.map(|run| Bytes::from(format.format(&run))));
+    Ok(HttpResponse::Ok()
+        .insert_header((CONTENT_TYPE, format.content_type()))
+        .streaming(header.chain(rows)))
+}
+
+async fn rerun<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    service.rerun(id.into_inner()).await.map(web::Json)
+}
+
+async fn update_run<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+    update_run_request_dto: web::Json<UpdateRunRequestDto>,
 ) -> ServiceResult<impl Responder> {
-    service.get_run(id.into_inner()).await.map(web::Json)
+    service
+        .update_run(id.into_inner(), update_run_request_dto.into_inner())
+        .await
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+async fn pause_run<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+    pause_run_request_dto: web::Query<PauseRunRequestDto>,
+) -> ServiceResult<impl Responder> {
+    service
+        .pause_run(id.into_inner(), pause_run_request_dto.into_inner())
+        .await
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+async fn resume_run<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    service
+        .resume_run(id.into_inner())
+        .await
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+async fn cancel_run<T: PollingService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    service
+        .cancel_run(id.into_inner())
+        .await
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+async fn get_queue<T: PollingService>(service: web::Data<T>) -> ServiceResult<impl Responder> {
+    service.get_queue().await.map(web::Json)
 }
 
 pub fn configure<T: 'static + PollingService>(service: web::Data<T>, cfg: &mut web::ServiceConfig) {
@@ -29,22 +156,69 @@ pub fn configure<T: 'static + PollingService>(service: web::Data<T>, cfg: &mut w
             .guard(guard::Header("Content-Type", "application/json"))
             .to(start_run::<T>),
     );
+    cfg.route(
+        "/runs/batch",
+        web::post()
+            .guard(guard::Header("Content-Type", "application/json"))
+            .to(start_runs::<T>),
+    );
+    // registered ahead of the run routes, which would take "export" for a run id
+    cfg.route("/runs/export", web::get().to(export_runs::<T>));
     cfg.route("/runs/{id}", web::get().to(get_run::<T>));
+    cfg.route(
+        "/runs/{id}",
+        web::patch()
+            .guard(guard::Header("Content-Type", "application/json"))
+            .to(update_run::<T>),
+    );
+    cfg.route("/runs/{id}/stats", web::get().to(get_run_stats::<T>));
+    cfg.route(
+        "/runs/{id}/timeseries",
+        web::get().to(get_run_timeseries::<T>),
+    );
+    cfg.route(
+        "/runs/{id}/responses.ndjson",
+        web::get().to(get_run_responses::<T>),
+    );
+    cfg.route("/runs/{id}/rerun", web::post().to(rerun::<T>));
+    cfg.route("/runs/{id}/cancel", web::post().to(cancel_run::<T>));
+    cfg.route("/runs/{id}/pause", web::post().to(pause_run::<T>));
+    cfg.route("/runs/{id}/resume", web::post().to(resume_run::<T>));
+    cfg.route("/queue", web::get().to(get_queue::<T>));
 }
 
 #[cfg(test)]
 mod should {
     use super::*;
-    use crate::polling::dto::{Run, RunStatus, StartRunResponseDto};
+    use crate::polling::dto::{
+        BatchStartRunResponseDto, BatchStartRunResultDto, CapturedResponse, ExportFormat,
+        ExportedRun, HistogramBucket, PendingRunInfo, QueueInfo, Run, RunFilter, RunPriority,
+        RunSample, RunStats, RunStatus, StartRunResponseDto,
+    };
+    use crate::polling::errors::{ServiceError, TooManyRequestsResponseDto};
     use crate::polling::polling_service::MockPollingService;
+    use actix_web::http::StatusCode;
     use actix_web::{test, App};
     use mockall::predicate::*;
 
     #[actix_rt::test]
     async fn start_new_run() {
-        let request_payload = StartRunRequestDto { seconds: 30 };
+        let request_payload = StartRunRequestDto {
+            seconds: 30,
+            priority: RunPriority::Normal,
+            max_queue_wait_seconds: None,
+            start_at: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            histogram_buckets: None,
+            aggregators: Vec::new(),
+            capture: None,
+            callback_url: None,
+        };
         let expected_response = StartRunResponseDto {
             id: RunId::new_v4(),
+            estimated_start_at: chrono::Utc::now(),
         };
 
         let polling_service = {
@@ -71,6 +245,106 @@ mod should {
         assert_eq!(expected_response, actual_response);
     }
 
+    #[actix_rt::test]
+    async fn reply_with_retry_after_when_queue_is_full() {
+        let request_payload = StartRunRequestDto {
+            seconds: 30,
+            priority: RunPriority::Normal,
+            max_queue_wait_seconds: None,
+            start_at: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            histogram_buckets: None,
+            aggregators: Vec::new(),
+            capture: None,
+            callback_url: None,
+        };
+        let retry_at = chrono::Utc::now() + chrono::Duration::seconds(12);
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_start_run()
+                .return_const(Err(ServiceError::TooManyRequests {
+                    retry_at: Some(retry_at),
+                }));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::post()
+            .uri("/runs")
+            .set_json(&request_payload)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        assert_eq!(
+            "12",
+            response
+                .headers()
+                .get("Retry-After")
+                .unwrap()
+                .to_str()
+                .unwrap()
+        );
+
+        let actual_response: TooManyRequestsResponseDto = test::read_body_json(response).await;
+        assert_eq!(Some(retry_at), actual_response.retry_at);
+    }
+
+    #[actix_rt::test]
+    async fn start_batch_of_runs() {
+        let request_payload = BatchStartRunRequestDto {
+            runs: vec![StartRunRequestDto {
+                seconds: 30,
+                priority: RunPriority::Normal,
+                max_queue_wait_seconds: None,
+                start_at: None,
+                concurrent_requests: None,
+                polling_address: None,
+                target_id: None,
+                histogram_buckets: None,
+                aggregators: Vec::new(),
+                capture: None,
+                callback_url: None,
+            }],
+            all_or_nothing: true,
+        };
+        let expected_response = BatchStartRunResponseDto {
+            runs: vec![BatchStartRunResultDto::Accepted(StartRunResponseDto {
+                id: RunId::new_v4(),
+                estimated_start_at: chrono::Utc::now(),
+            })],
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_start_runs()
+                .with(eq(request_payload.clone()))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::post()
+            .uri("/runs/batch")
+            .set_json(&request_payload)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: BatchStartRunResponseDto = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
     #[actix_rt::test]
     async fn get_existing_run() {
         let run_id = RunId::new_v4();
@@ -79,6 +353,14 @@ mod should {
             status: RunStatus::Finished,
             successful_responses_count: 10,
             sum: 300,
+            sum_overflowed: false,
+            requested_seconds: None,
+            effective_seconds: None,
+            rerun_of: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            aggregates: None,
         };
 
         let polling_service = {
@@ -93,7 +375,49 @@ mod should {
             test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
 
         let request = test::TestRequest::get()
-            .uri(&*format!("/runs/{}", run_id))
+            .uri(&format!("/runs/{}", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: Run = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn wait_for_run() {
+        let run_id = RunId::new_v4();
+        let expected_response = Run {
+            id: run_id,
+            status: RunStatus::Finished,
+            successful_responses_count: 10,
+            sum: 150,
+            sum_overflowed: false,
+            requested_seconds: None,
+            effective_seconds: None,
+            rerun_of: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            aggregates: None,
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_get_run().never();
+            ps.expect_wait_for_run()
+                .with(eq(run_id), eq(std::time::Duration::from_secs(30)))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/runs/{}?wait=30", run_id))
             .to_request();
 
         let response = test::call_service(&app, request).await;
@@ -103,4 +427,371 @@ mod should {
         let actual_response: Run = test::read_body_json(response).await;
         assert_eq!(expected_response, actual_response);
     }
+
+    #[actix_rt::test]
+    async fn get_run_stats() {
+        let run_id = RunId::new_v4();
+        let expected_response = RunStats {
+            count: 2,
+            min: Some(1),
+            max: Some(3),
+            mean: Some(2.0),
+            variance: Some(1.0),
+            histogram: vec![HistogramBucket {
+                upper_bound: None,
+                count: 2,
+            }],
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_get_run_stats()
+                .with(eq(run_id))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/runs/{}/stats", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: RunStats = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn get_run_timeseries() {
+        let run_id = RunId::new_v4();
+        let expected_response = vec![RunSample {
+            at: chrono::Utc::now(),
+            attempts: 10,
+            successes: 7,
+            errors: 3,
+            sum: 35,
+        }];
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_get_run_timeseries()
+                .with(eq(run_id))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/runs/{}/timeseries", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: Vec<RunSample> = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn get_run_responses_as_ndjson() {
+        let run_id = RunId::new_v4();
+        let captured = CapturedResponse {
+            at: chrono::Utc::now(),
+            latency_us: 1200,
+            slot: 3,
+            status: Some(200),
+            body: Some(serde_json::json!({"value": 5})),
+            error: None,
+        };
+        let expected_response = vec![
+            captured.clone(),
+            CapturedResponse {
+                status: None,
+                body: None,
+                error: Some("Timed out".into()),
+                ..captured
+            },
+        ];
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            let responses = expected_response.clone();
+            ps.expect_get_run_responses()
+                .with(eq(run_id))
+                .returning(
+                    move |_| Ok(stream::iter(responses.clone().into_iter().map(Ok)).boxed()),
+                );
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/runs/{}/responses.ndjson", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+        assert_eq!(
+            "application/x-ndjson",
+            response.headers().get(CONTENT_TYPE).unwrap()
+        );
+
+        let body = test::read_body(response).await;
+        let actual_response = std::str::from_utf8(&body)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect::<Vec<CapturedResponse>>();
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn export_runs_as_csv() {
+        let exported = ExportedRun {
+            created_at: chrono::Utc::now(),
+            run: Run {
+                id: RunId::new_v4(),
+                status: RunStatus::Finished,
+                successful_responses_count: 10,
+                sum: 150,
+                sum_overflowed: false,
+                requested_seconds: Some(30),
+                effective_seconds: Some(30),
+                rerun_of: None,
+                concurrent_requests: None,
+                polling_address: None,
+                target_id: None,
+                aggregates: None,
+            },
+        };
+        let expected_filter = RunFilter {
+            status: Some(RunStatus::Finished),
+            ..RunFilter::default()
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            let exported = exported.clone();
+            ps.expect_export_runs()
+                .with(eq(expected_filter))
+                .returning(move |_| Ok(stream::iter(vec![Ok(exported.clone())]).boxed()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri("/runs/export?format=csv&status=Finished")
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+        assert_eq!("text/csv", response.headers().get(CONTENT_TYPE).unwrap());
+
+        let body = test::read_body(response).await;
+        let expected_body =
+            ExportFormat::Csv.header().unwrap().to_string() + &ExportFormat::Csv.format(&exported);
+        assert_eq!(expected_body.as_bytes(), &body[..]);
+    }
+
+    #[actix_rt::test]
+    async fn reject_export_in_unknown_format() {
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_export_runs().never();
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri("/runs/export?format=xlsx")
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn get_queue() {
+        let now = chrono::Utc::now();
+        let expected_response = QueueInfo {
+            pending: vec![PendingRunInfo {
+                id: RunId::new_v4(),
+                seconds: 30,
+                priority: RunPriority::Normal,
+                enqueued_at: now,
+                estimated_start_at: now + chrono::Duration::seconds(10),
+                expires_at: None,
+            }],
+            running: vec![],
+            scheduled: vec![],
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_get_queue()
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::get().uri("/queue").to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: QueueInfo = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn cancel_run() {
+        let run_id = RunId::new_v4();
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_cancel_run()
+                .with(eq(run_id))
+                .times(1)
+                .return_const(Ok(()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::post()
+            .uri(&format!("/runs/{}/cancel", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn update_run() {
+        let run_id = RunId::new_v4();
+        let request_payload = UpdateRunRequestDto {
+            remaining_seconds: 10,
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_update_run()
+                .with(eq(run_id), eq(request_payload.clone()))
+                .times(1)
+                .return_const(Ok(()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::patch()
+            .uri(&format!("/runs/{}", run_id))
+            .set_json(&request_payload)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn pause_run_lending_its_slot() {
+        let run_id = RunId::new_v4();
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_pause_run()
+                .with(eq(run_id), eq(PauseRunRequestDto { lend_slot: true }))
+                .times(1)
+                .return_const(Ok(()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::post()
+            .uri(&format!("/runs/{}/pause?lend_slot=true", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn resume_run() {
+        let run_id = RunId::new_v4();
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_resume_run()
+                .with(eq(run_id))
+                .times(1)
+                .return_const(Ok(()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::post()
+            .uri(&format!("/runs/{}/resume", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn rerun() {
+        let run_id = RunId::new_v4();
+        let expected_response = StartRunResponseDto {
+            id: RunId::new_v4(),
+            estimated_start_at: chrono::Utc::now(),
+        };
+
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_rerun()
+                .with(eq(run_id))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ps)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(polling_service, cfg))).await;
+
+        let request = test::TestRequest::post()
+            .uri(&format!("/runs/{}/rerun", run_id))
+            .to_request();
+
+        let response: StartRunResponseDto = test::read_response_json(&app, request).await;
+
+        assert_eq!(expected_response, response);
+    }
 }
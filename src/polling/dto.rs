@@ -1,4 +1,7 @@
-use crate::polling::errors::ServiceError;
+use crate::configuration::settings::PriorityLimits;
+use crate::polling::errors::{ServiceError, TooManyRequestsResponseDto};
+use crate::targets::dto::{TargetDefinition, TargetId};
+use chrono::{DateTime, Utc};
 use std::time::Duration;
 use uuid::Uuid;
 
@@ -7,13 +10,21 @@ pub type RunId = Uuid;
 #[derive(Debug, Clone, PartialEq)]
 pub struct NewRun {
     pub id: RunId,
-    pub seconds: u64,
+    /// Kept so that the run can be started again with the same parameters
+    pub spec: StartRunRequestDto,
+    pub status: RunStatus,
+    pub start_at: Option<DateTime<Utc>>,
+    pub rerun_of: Option<RunId>,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum RunStatus {
     InProgress = 0,
     Finished = 1,
+    Expired = 2,
+    Scheduled = 3,
+    Cancelled = 4,
+    Paused = 5,
 }
 
 impl std::convert::TryFrom<i16> for RunStatus {
@@ -23,19 +34,150 @@ impl std::convert::TryFrom<i16> for RunStatus {
         match value {
             0 => Ok(Self::InProgress),
             1 => Ok(Self::Finished),
+            2 => Ok(Self::Expired),
+            3 => Ok(Self::Scheduled),
+            4 => Ok(Self::Cancelled),
+            5 => Ok(Self::Paused),
             _ => Err(ServiceError::InternalServerError),
         }
     }
 }
 
+impl RunStatus {
+    /// The run will not change anymore
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Finished | Self::Expired | Self::Cancelled)
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunPriority {
+    High,
+    #[default]
+    Normal,
+    Low,
+}
+
+impl RunPriority {
+    /// Lower rank is executed first
+    pub fn rank(&self) -> i64 {
+        match self {
+            Self::High => 0,
+            Self::Normal => 1,
+            Self::Low => 2,
+        }
+    }
+
+    pub fn pending_limit(&self, limits: &PriorityLimits) -> Option<usize> {
+        match self {
+            Self::High => limits.high,
+            Self::Normal => limits.normal,
+            Self::Low => limits.low,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct StartRunRequestDto {
     pub seconds: u64,
+    #[serde(default)]
+    pub priority: RunPriority,
+    #[serde(default)]
+    pub max_queue_wait_seconds: Option<u64>,
+    /// Deferred start time, the run is not queued until then
+    #[serde(default)]
+    pub start_at: Option<DateTime<Utc>>,
+    /// Overrides `PollingSettings::concurrent_requests_per_run`
+    #[serde(default)]
+    pub concurrent_requests: Option<usize>,
+    /// Overrides `PollingSettings::polling_address`, must be one of the allowed addresses
+    #[serde(default)]
+    pub polling_address: Option<String>,
+    /// Registered target to poll instead of a polling address
+    #[serde(default)]
+    pub target_id: Option<TargetId>,
+    /// Inclusive upper bounds of value histogram buckets, overrides `PollingSettings::histogram_buckets`
+    #[serde(default)]
+    pub histogram_buckets: Option<Vec<i64>>,
+    /// Summaries reported in the `aggregates` of the run, on top of the count, sum and
+    /// statistics computed for every run
+    #[serde(default)]
+    pub aggregators: Vec<AggregatorKind>,
+    /// Which upstream responses are stored, none if not set
+    #[serde(default)]
+    pub capture: Option<CaptureMode>,
+    /// Receives the run, signed, once it is finished, expired or cancelled
+    #[serde(default)]
+    pub callback_url: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CaptureMode {
+    All,
+    /// Responses not counted as successful, including failed requests
+    Errors,
+    /// Evenly spread share of responses, e.g. 0.01 for every hundredth one
+    Fraction {
+        fraction: f64,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AggregatorKind {
+    Count,
+    Sum,
+    MinMax,
+    /// Estimated by HyperLogLog
+    DistinctCount,
+    /// Most frequent values, counts are approximate for values seen late
+    TopK {
+        k: usize,
+    },
+    XorChecksum,
+}
+
+impl AggregatorKind {
+    /// Key of the aggregator's result in `Run::aggregates`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Count => "count",
+            Self::Sum => "sum",
+            Self::MinMax => "min_max",
+            Self::DistinctCount => "distinct_count",
+            Self::TopK { .. } => "top_k",
+            Self::XorChecksum => "xor_checksum",
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct StartRunResponseDto {
     pub id: RunId,
+    pub estimated_start_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct BatchStartRunRequestDto {
+    pub runs: Vec<StartRunRequestDto>,
+    /// Reject the whole batch if any of the runs does not fit into the queue
+    #[serde(default)]
+    pub all_or_nothing: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct BatchStartRunResponseDto {
+    /// One result per requested run, in request order
+    pub runs: Vec<BatchStartRunResultDto>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum BatchStartRunResultDto {
+    Accepted(StartRunResponseDto),
+    Rejected(TooManyRequestsResponseDto),
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
@@ -43,24 +185,244 @@ pub struct Run {
     pub id: RunId,
     pub status: RunStatus,
     pub successful_responses_count: u64,
-    pub sum: u64,
+    pub sum: i128,
+    /// The sum stopped at the last value that still fit
+    pub sum_overflowed: bool,
+    pub requested_seconds: Option<u64>,
+    /// Set once the run finishes
+    pub effective_seconds: Option<u64>,
+    /// The run this one repeats, if any
+    pub rerun_of: Option<RunId>,
+    pub concurrent_requests: Option<usize>,
+    pub polling_address: Option<String>,
+    pub target_id: Option<TargetId>,
+    /// Results of the aggregators chosen for the run, by aggregator name
+    pub aggregates: Option<serde_json::Value>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Ndjson,
+}
+
+/// Criteria of runs to export, unset ones match any run
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RunFilter {
+    pub status: Option<RunStatus>,
+    pub target_id: Option<TargetId>,
+    /// Runs created at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Runs created before this time
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ExportRunsRequestDto {
+    pub format: ExportFormat,
+    #[serde(flatten)]
+    pub filter: RunFilter,
+}
+
+/// Flattened, so it is only serialized: `serde` cannot read flattened 128-bit sums back
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct ExportedRun {
+    pub created_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub run: Run,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct UpdateRunRequestDto {
+    pub remaining_seconds: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct GetRunRequestDto {
+    /// Seconds to hold the request until the run reaches a terminal status
+    #[serde(default)]
+    pub wait: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PauseRunRequestDto {
+    /// Lets a pending run start in the worker slot of the paused one
+    #[serde(default)]
+    pub lend_slot: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
 #[serde(untagged)]
 pub enum FaultyServerResponse {
-    Ok { value: u32 },
+    Ok { value: i64 },
     Err { error: String },
 }
 
+/// Response of a target, with the parsed result and what is needed to capture it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PolledResponse {
+    /// `None` if the request failed without a response
+    pub status: Option<u16>,
+    pub body: String,
+    pub result: FaultyServerResponse,
+}
+
+/// Upstream response stored for later inspection
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CapturedResponse {
+    pub at: DateTime<Utc>,
+    pub latency_us: u64,
+    /// Which of the run's concurrent requests it was
+    pub slot: usize,
+    pub status: Option<u16>,
+    /// JSON body, or a string holding a body that is not JSON
+    pub body: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct RunJob {
     pub id: RunId,
     pub duration: Duration,
+    pub priority: RunPriority,
+    /// Falls back to `PollingSettings::max_queue_wait_seconds` if not set
+    pub max_queue_wait: Option<Duration>,
+    pub start_at: Option<DateTime<Utc>>,
+    pub concurrent_requests: usize,
+    pub target: TargetDefinition,
+    pub histogram_buckets: Vec<i64>,
+    pub aggregators: Vec<AggregatorKind>,
+    pub capture: Option<CaptureMode>,
 }
 
 pub struct RunJobResult {
     pub id: RunId,
-    pub successful_responses: u64,
-    pub value_sum: u64,
+    pub successful_responses_count: u64,
+    pub sum: ValueSum,
+    pub stats: RunStats,
+    /// Results of the aggregators chosen for the run
+    pub aggregates: Option<serde_json::Value>,
+    pub duration: Duration,
+}
+
+/// Sum of polled values, which stops growing on overflow instead of wrapping around.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct ValueSum {
+    pub sum: i128,
+    pub overflowed: bool,
+}
+
+impl ValueSum {
+    pub fn add(&mut self, value: i64) {
+        if self.overflowed {
+            return;
+        }
+        match self.sum.checked_add(value.into()) {
+            Some(sum) => self.sum = sum,
+            None => self.overflowed = true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RunStats {
+    pub count: u64,
+    pub min: Option<i64>,
+    pub max: Option<i64>,
+    pub mean: Option<f64>,
+    /// Population variance
+    pub variance: Option<f64>,
+    pub histogram: Vec<HistogramBucket>,
+}
+
+/// Counters of a run over the sampling interval ending at `at`
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RunSample {
+    pub at: DateTime<Utc>,
+    pub attempts: u64,
+    pub successes: u64,
+    pub errors: u64,
+    pub sum: i128,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct HistogramBucket {
+    /// Inclusive, `None` for the last bucket holding the values above all bounds
+    pub upper_bound: Option<i64>,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct QueueInfo {
+    pub scheduled: Vec<ScheduledRunInfo>,
+    pub pending: Vec<PendingRunInfo>,
+    pub running: Vec<RunningRunInfo>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ScheduledRunInfo {
+    pub id: RunId,
+    pub seconds: u64,
+    pub priority: RunPriority,
+    pub start_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct PendingRunInfo {
+    pub id: RunId,
+    pub seconds: u64,
+    pub priority: RunPriority,
+    pub enqueued_at: DateTime<Utc>,
+    pub estimated_start_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct RunningRunInfo {
+    pub id: RunId,
+    pub seconds: u64,
+    pub started_at: DateTime<Utc>,
+    pub remaining_seconds: u64,
+    pub paused: bool,
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    #[test]
+    fn stop_summing_values_on_overflow() {
+        let mut value_sum = ValueSum::default();
+        value_sum.add(-1);
+        value_sum.add(i64::MAX);
+        assert_eq!(
+            ValueSum {
+                sum: i64::MAX as i128 - 1,
+                overflowed: false
+            },
+            value_sum
+        );
+
+        value_sum.sum = i128::MAX - 1;
+        value_sum.add(2);
+        value_sum.add(-2);
+        assert_eq!(
+            ValueSum {
+                sum: i128::MAX - 1,
+                overflowed: true
+            },
+            value_sum
+        );
+    }
+
+    #[test]
+    fn parse_signed_values() {
+        assert_eq!(
+            FaultyServerResponse::Ok {
+                value: -2_147_483_648
+            },
+            serde_json::from_str(r#"{"value": -2147483648}"#).unwrap()
+        );
+    }
 }
@@ -1,5 +1,5 @@
 use actix_web::{HttpResponse, ResponseError};
-use async_channel::TrySendError;
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 
 #[derive(Debug, Error, Clone, PartialEq)]
@@ -7,8 +7,35 @@ pub enum ServiceError {
     #[error("Internal Server Error")]
     InternalServerError,
 
+    #[error("Bad request: {0}")]
+    BadRequest(String),
+
+    #[error("Not found")]
+    NotFound,
+
+    #[error("Unauthorized")]
+    Unauthorized,
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
     #[error("Too many requests")]
-    TooManyRequests,
+    TooManyRequests { retry_at: Option<DateTime<Utc>> },
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct TooManyRequestsResponseDto {
+    pub error: String,
+    pub retry_at: Option<DateTime<Utc>>,
+}
+
+impl TooManyRequestsResponseDto {
+    pub fn new(retry_at: Option<DateTime<Utc>>) -> Self {
+        Self {
+            error: "Too many requests, please try again later".into(),
+            retry_at,
+        }
+    }
 }
 
 impl ResponseError for ServiceError {
@@ -16,25 +43,34 @@ impl ResponseError for ServiceError {
         match self {
             ServiceError::InternalServerError => HttpResponse::InternalServerError()
                 .json("Internal server error, please try again later"),
-            ServiceError::TooManyRequests => {
-                HttpResponse::TooManyRequests().json("Too many requests, please try again later")
+            ServiceError::BadRequest(reason) => HttpResponse::BadRequest().json(reason),
+            ServiceError::NotFound => HttpResponse::NotFound().json("Not found"),
+            ServiceError::Unauthorized => HttpResponse::Unauthorized()
+                .insert_header(("WWW-Authenticate", "Bearer"))
+                .json("Unauthorized"),
+            ServiceError::Conflict(reason) => HttpResponse::Conflict().json(reason),
+            ServiceError::TooManyRequests { retry_at } => {
+                let mut response = HttpResponse::TooManyRequests();
+                if let Some(retry_at) = retry_at {
+                    response.insert_header(("Retry-After", retry_after_seconds(*retry_at)));
+                }
+                response.json(&TooManyRequestsResponseDto::new(*retry_at))
             }
         }
     }
 }
 
-impl<T> From<TrySendError<T>> for ServiceError {
-    fn from(e: TrySendError<T>) -> Self {
-        match e {
-            TrySendError::Closed(_) => Self::InternalServerError,
-            TrySendError::Full(_) => Self::TooManyRequests,
-        }
-    }
+fn retry_after_seconds(retry_at: DateTime<Utc>) -> i64 {
+    let millis = (retry_at - Utc::now()).num_milliseconds().max(0);
+    (millis + 999) / 1000
 }
 
 impl From<sqlx::Error> for ServiceError {
-    fn from(_: sqlx::Error) -> Self {
-        Self::InternalServerError
+    fn from(e: sqlx::Error) -> Self {
+        match e {
+            sqlx::Error::RowNotFound => Self::NotFound,
+            _ => Self::InternalServerError,
+        }
     }
 }
 
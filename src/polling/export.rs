@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+
+use crate::polling::dto::{ExportFormat, ExportedRun};
+
+const CSV_HEADER: &str = "id,created_at,status,requested_seconds,effective_seconds,\
+successful_responses_count,sum,sum_overflowed,concurrent_requests,polling_address,\
+target_id,rerun_of,aggregates\n";
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    /// Written once before the exported runs
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            Self::Csv => Some(CSV_HEADER),
+            Self::Ndjson => None,
+        }
+    }
+
+    /// One line describing the run, ending with a newline
+    pub fn format(&self, exported: &ExportedRun) -> String {
+        match self {
+            Self::Csv => csv_record(exported),
+            Self::Ndjson => {
+                serde_json::to_string(exported).expect("Failed to serialize exported run") + "\n"
+            }
+        }
+    }
+}
+
+fn csv_record(exported: &ExportedRun) -> String {
+    fn optional<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map(T::to_string).unwrap_or_default()
+    }
+
+    let run = &exported.run;
+    let fields = [
+        run.id.to_string(),
+        exported.created_at.to_rfc3339(),
+        format!("{:?}", run.status),
+        optional(&run.requested_seconds),
+        optional(&run.effective_seconds),
+        run.successful_responses_count.to_string(),
+        run.sum.to_string(),
+        run.sum_overflowed.to_string(),
+        optional(&run.concurrent_requests),
+        optional(&run.polling_address),
+        optional(&run.target_id),
+        optional(&run.rerun_of),
+        optional(&run.aggregates),
+    ];
+
+    let mut record = fields
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",");
+    record.push('\n');
+    record
+}
+
+/// Quotes a field containing separators, quotes or line breaks
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::{Run, RunId, RunStatus};
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    fn exported_run() -> ExportedRun {
+        ExportedRun {
+            created_at: Utc.ymd(2021, 4, 1).and_hms(12, 30, 0),
+            run: Run {
+                id: RunId::nil(),
+                status: RunStatus::Finished,
+                successful_responses_count: 7,
+                sum: -35,
+                sum_overflowed: false,
+                requested_seconds: Some(30),
+                effective_seconds: Some(30),
+                rerun_of: None,
+                concurrent_requests: Some(3),
+                polling_address: Some("http://localhost:9911/?a=1,b=2".into()),
+                target_id: None,
+                aggregates: Some(json!({"count": 7})),
+            },
+        }
+    }
+
+    #[test]
+    fn format_runs_as_csv_records() {
+        let format = ExportFormat::Csv;
+
+        assert_eq!(13, format.header().unwrap().trim_end().split(',').count());
+        assert_eq!(
+            "00000000-0000-0000-0000-000000000000,2021-04-01T12:30:00+00:00,Finished,30,30,7,-35,\
+            false,3,\"http://localhost:9911/?a=1,b=2\",,,\"{\"\"count\"\":7}\"\n",
+            format.format(&exported_run())
+        );
+    }
+
+    #[test]
+    fn format_runs_as_json_lines() {
+        let format = ExportFormat::Ndjson;
+        let line = format.format(&exported_run());
+
+        assert_eq!(None, format.header());
+        assert!(line.ends_with('\n'));
+        let run = serde_json::to_string(&exported_run().run).unwrap();
+        let mut expected = serde_json::from_str::<serde_json::Value>(&run).unwrap();
+        expected["created_at"] = json!("2021-04-01T12:30:00Z");
+        assert_eq!(
+            expected,
+            serde_json::from_str::<serde_json::Value>(&line).unwrap()
+        );
+    }
+}
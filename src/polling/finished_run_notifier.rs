@@ -0,0 +1,52 @@
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::event_bus::RunEventBus;
+use crate::polling::dto::RunId;
+
+/// Runs noticed between two reads of a waiter, more make it check its run again
+const NOTIFICATION_BUFFER: usize = 1024;
+
+/// Wakes requests waiting for runs to reach a terminal status.
+///
+/// Raised by terminal run events of this replica, and for runs of other replicas
+/// by the Postgres notifications forwarded to it.
+#[derive(Clone, Debug)]
+pub struct FinishedRunNotifier {
+    sender: broadcast::Sender<RunId>,
+}
+
+impl Default for FinishedRunNotifier {
+    fn default() -> Self {
+        Self {
+            sender: broadcast::channel(NOTIFICATION_BUFFER).0,
+        }
+    }
+}
+
+impl FinishedRunNotifier {
+    pub fn notify(&self, run_id: RunId) {
+        // nobody may be waiting
+        let _ = self.sender.send(run_id);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<RunId> {
+        self.sender.subscribe()
+    }
+
+    /// Notifies runs reaching a terminal status in the published events
+    pub async fn forward_finished_events(self, events: RunEventBus) {
+        let mut published = events.subscribe();
+        loop {
+            match published.recv().await {
+                Ok(event) if event.is_terminal() => self.notify(event.run_id),
+                Ok(_) => {}
+                // their waiters still notice them once they time out
+                Err(RecvError::Lagged(missed)) => {
+                    log::warn!("Missed {} run events while notifying finished runs", missed)
+                }
+                Err(RecvError::Closed) => return,
+            }
+        }
+    }
+}
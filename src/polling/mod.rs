@@ -1,7 +1,10 @@
+pub mod aggregators;
 pub mod background_job_runner;
 pub mod controller;
 pub mod dto;
 pub mod errors;
+mod export;
+pub mod finished_run_notifier;
 pub mod polling_service;
 pub mod request_sender;
 pub mod run_repository;
@@ -1,11 +1,18 @@
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 
 pub use polling_service_impl::PollingServiceImpl;
+pub use run_spec::job_from_spec;
 
-use crate::polling::dto::{Run, RunId, StartRunRequestDto, StartRunResponseDto};
+use crate::polling::dto::{
+    BatchStartRunRequestDto, BatchStartRunResponseDto, CapturedResponse, ExportedRun,
+    PauseRunRequestDto, QueueInfo, Run, RunFilter, RunId, RunSample, RunStats, StartRunRequestDto,
+    StartRunResponseDto, UpdateRunRequestDto,
+};
 use crate::polling::errors::ServiceResult;
 
 mod polling_service_impl;
+mod run_spec;
 
 #[cfg_attr(test, mockall::automock)]
 #[async_trait(? Send)]
@@ -14,5 +21,40 @@ pub trait PollingService {
         &self,
         start_run_request_dto: StartRunRequestDto,
     ) -> ServiceResult<StartRunResponseDto>;
+    async fn start_runs(
+        &self,
+        batch_start_run_request_dto: BatchStartRunRequestDto,
+    ) -> ServiceResult<BatchStartRunResponseDto>;
     async fn get_run(&self, run_id: RunId) -> ServiceResult<Run>;
+    /// Replies once the run reaches a terminal status, or with its current state after the timeout
+    async fn wait_for_run(&self, run_id: RunId, timeout: std::time::Duration)
+        -> ServiceResult<Run>;
+    /// Starts a new run with the parameters of an existing one
+    async fn rerun(&self, run_id: RunId) -> ServiceResult<StartRunResponseDto>;
+    async fn update_run(
+        &self,
+        run_id: RunId,
+        update_run_request_dto: UpdateRunRequestDto,
+    ) -> ServiceResult<()>;
+    async fn pause_run(
+        &self,
+        run_id: RunId,
+        pause_run_request_dto: PauseRunRequestDto,
+    ) -> ServiceResult<()>;
+    async fn resume_run(&self, run_id: RunId) -> ServiceResult<()>;
+    async fn cancel_run(&self, run_id: RunId) -> ServiceResult<()>;
+    /// Statistics of values polled by a finished run
+    async fn get_run_stats(&self, run_id: RunId) -> ServiceResult<RunStats>;
+    /// Counters of a run sampled while it was running
+    async fn get_run_timeseries(&self, run_id: RunId) -> ServiceResult<Vec<RunSample>>;
+    /// Upstream responses captured while a run was running
+    async fn get_run_responses(
+        &self,
+        run_id: RunId,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<CapturedResponse>>>;
+    async fn export_runs(
+        &self,
+        filter: RunFilter,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<ExportedRun>>>;
+    async fn get_queue(&self) -> ServiceResult<QueueInfo>;
 }
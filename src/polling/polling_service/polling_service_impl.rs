@@ -1,60 +1,417 @@
-use crate::polling::background_job_runner::BackgroundJobRunner;
-use crate::polling::dto::{NewRun, Run, RunId, RunJob, StartRunRequestDto, StartRunResponseDto};
-use crate::polling::errors::ServiceResult;
-use crate::polling::polling_service::PollingService;
+use crate::configuration::settings::{PollingSettings, WebhookSettings};
+use crate::events::dto::RunEventKind;
+use crate::events::event_bus::RunEventBus;
+use crate::polling::background_job_runner::{BackgroundJobRunner, ResumeOutcome};
+use crate::polling::dto::{
+    BatchStartRunRequestDto, BatchStartRunResponseDto, BatchStartRunResultDto, CapturedResponse,
+    ExportedRun, NewRun, PauseRunRequestDto, QueueInfo, Run, RunFilter, RunId, RunJob, RunSample,
+    RunStats, RunStatus, StartRunRequestDto, StartRunResponseDto, UpdateRunRequestDto,
+};
+use crate::polling::errors::{ServiceError, ServiceResult, TooManyRequestsResponseDto};
+use crate::polling::finished_run_notifier::FinishedRunNotifier;
+use crate::polling::polling_service::run_spec::MAX_RUN_SECONDS;
+use crate::polling::polling_service::{job_from_spec, PollingService};
 use crate::polling::run_repository::RunRepository;
+use crate::targets::target_repository::TargetRepository;
 use async_trait::async_trait;
+use futures::stream::BoxStream;
+use tokio::sync::broadcast::error::RecvError;
+
+const MAX_RUN_WAIT: std::time::Duration = std::time::Duration::from_secs(300);
 
 #[derive(Clone, Debug)]
-pub struct PollingServiceImpl<R, J> {
+pub struct PollingServiceImpl<R, J, T> {
     run_repo: R,
     job_runner: J,
+    target_repo: T,
+    settings: PollingSettings,
+    webhook_settings: WebhookSettings,
+    events: RunEventBus,
+    finished_runs: FinishedRunNotifier,
 }
 
 #[async_trait(? Send)]
-impl<R, J> PollingService for PollingServiceImpl<R, J>
+impl<R, J, T> PollingService for PollingServiceImpl<R, J, T>
 where
     R: RunRepository,
     J: BackgroundJobRunner,
+    T: TargetRepository,
 {
     async fn start_run(
         &self,
         start_run_request_dto: StartRunRequestDto,
     ) -> ServiceResult<StartRunResponseDto> {
-        let id = self.run_repo.generate_run_id().await;
+        self.start(start_run_request_dto, None).await
+    }
 
-        self.job_runner
-            .try_push_job(RunJob {
-                id,
-                duration: std::time::Duration::from_secs(start_run_request_dto.seconds),
-            })
+    async fn start_runs(
+        &self,
+        batch_start_run_request_dto: BatchStartRunRequestDto,
+    ) -> ServiceResult<BatchStartRunResponseDto> {
+        let requests = batch_start_run_request_dto.runs;
+        if requests.is_empty() {
+            return Err(ServiceError::BadRequest("Batch contains no runs".into()));
+        }
+
+        let mut jobs = Vec::with_capacity(requests.len());
+        for request in &requests {
+            let id = self.run_repo.generate_run_id().await;
+            jobs.push(self.run_job(id, request).await?);
+        }
+
+        let results = self
+            .job_runner
+            .try_push_jobs(jobs.clone(), batch_start_run_request_dto.all_or_nothing)
+            .await;
+
+        if batch_start_run_request_dto.all_or_nothing {
+            if let Some(Err(e)) = results.iter().find(|r| r.is_err()) {
+                return Err(e.clone());
+            }
+        }
+
+        let accepted = jobs
+            .iter()
+            .zip(&results)
+            .filter(|(_, result)| result.is_ok())
+            .map(|(job, _)| job.id)
+            .collect::<Vec<_>>();
+        let mut saved = Vec::with_capacity(accepted.len());
+        let mut runs = Vec::with_capacity(results.len());
+        for ((job, request), result) in jobs.iter().zip(&requests).zip(results) {
+            runs.push(match result {
+                Ok(estimated_start_at) => {
+                    let new_run = Self::new_run(job, request, None);
+                    if let Err(e) = self.run_repo.save_run(&new_run).await {
+                        self.withdraw_jobs(&accepted, &saved).await;
+                        return Err(e);
+                    }
+                    saved.push(job.id);
+                    tracing::info!(run_id = %job.id, %estimated_start_at, "Run accepted");
+                    BatchStartRunResultDto::Accepted(StartRunResponseDto {
+                        id: job.id,
+                        estimated_start_at,
+                    })
+                }
+                Err(ServiceError::TooManyRequests { retry_at }) => {
+                    BatchStartRunResultDto::Rejected(TooManyRequestsResponseDto::new(retry_at))
+                }
+                Err(e) => return Err(e),
+            });
+        }
+
+        Ok(BatchStartRunResponseDto { runs })
+    }
+
+    async fn get_run(&self, run_id: RunId) -> ServiceResult<Run> {
+        self.run_repo.get_run_by_id(run_id).await
+    }
+
+    async fn wait_for_run(
+        &self,
+        run_id: RunId,
+        timeout: std::time::Duration,
+    ) -> ServiceResult<Run> {
+        if timeout > MAX_RUN_WAIT {
+            return Err(ServiceError::BadRequest(format!(
+                "Runs can be waited for at most {} seconds",
+                MAX_RUN_WAIT.as_secs()
+            )));
+        }
+
+        // subscribed before the run is read, so that its notification cannot be missed
+        let mut finished = self.finished_runs.subscribe();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let run = self.run_repo.get_run_by_id(run_id).await?;
+            if run.status.is_terminal() {
+                return Ok(run);
+            }
+
+            loop {
+                match tokio::time::timeout_at(deadline, finished.recv()).await {
+                    Err(_) => return self.run_repo.get_run_by_id(run_id).await,
+                    Ok(Ok(id)) if id != run_id => continue,
+                    // the run may have been among the missed ones
+                    Ok(Ok(_)) | Ok(Err(RecvError::Lagged(_))) => break,
+                    Ok(Err(RecvError::Closed)) => unreachable!("Notifier is kept by the service"),
+                }
+            }
+        }
+    }
+
+    async fn rerun(&self, run_id: RunId) -> ServiceResult<StartRunResponseDto> {
+        let spec =
+            self.run_repo.get_run_spec(run_id).await?.ok_or_else(|| {
+                ServiceError::Conflict("Parameters of the run were not saved".into())
+            })?;
+
+        self.start(
+            StartRunRequestDto {
+                start_at: None,
+                ..spec
+            },
+            Some(run_id),
+        )
+        .await
+    }
+
+    async fn update_run(
+        &self,
+        run_id: RunId,
+        update_run_request_dto: UpdateRunRequestDto,
+    ) -> ServiceResult<()> {
+        if update_run_request_dto.remaining_seconds > MAX_RUN_SECONDS {
+            return Err(ServiceError::BadRequest(format!(
+                "Runs may last at most {} more seconds",
+                MAX_RUN_SECONDS
+            )));
+        }
+        let remaining = std::time::Duration::from_secs(update_run_request_dto.remaining_seconds);
+        if !self
+            .job_runner
+            .set_remaining_duration(run_id, remaining)
+            .await
+        {
+            // distinguishes unknown runs from the ones that are not running
+            self.run_repo.get_run_by_id(run_id).await?;
+            return Err(ServiceError::Conflict("Run is not in progress".into()));
+        }
+
+        tracing::info!(
+            run_id = %run_id,
+            remaining_seconds = update_run_request_dto.remaining_seconds,
+            "Remaining duration of run changed"
+        );
+        Ok(())
+    }
+
+    async fn pause_run(
+        &self,
+        run_id: RunId,
+        pause_run_request_dto: PauseRunRequestDto,
+    ) -> ServiceResult<()> {
+        if !self
+            .job_runner
+            .pause_job(run_id, pause_run_request_dto.lend_slot)
+            .await
+        {
+            self.run_repo.get_run_by_id(run_id).await?;
+            return Err(ServiceError::Conflict("Run is not in progress".into()));
+        }
+
+        self.run_repo
+            .update_run_status(run_id, RunStatus::Paused)
             .await?;
+        tracing::info!(
+            run_id = %run_id,
+            lend_slot = pause_run_request_dto.lend_slot,
+            "Run paused"
+        );
+        self.events.publish(run_id, RunEventKind::Paused);
+        Ok(())
+    }
+
+    async fn resume_run(&self, run_id: RunId) -> ServiceResult<()> {
+        match self.job_runner.resume_job(run_id).await {
+            ResumeOutcome::Resumed => {}
+            // the job runner saves and reports the run once it resumes
+            ResumeOutcome::WaitingForSlot => {
+                tracing::info!(run_id = %run_id, "Run resumes once a worker slot frees up");
+                return Ok(());
+            }
+            ResumeOutcome::NotPaused => {
+                self.run_repo.get_run_by_id(run_id).await?;
+                return Err(ServiceError::Conflict("Run is not paused".into()));
+            }
+        }
 
         self.run_repo
-            .save_run(&NewRun {
-                id,
-                seconds: start_run_request_dto.seconds,
-            })
+            .update_run_status(run_id, RunStatus::InProgress)
+            .await?;
+        tracing::info!(run_id = %run_id, "Run resumed");
+        self.events.publish(run_id, RunEventKind::Resumed);
+        Ok(())
+    }
+
+    async fn cancel_run(&self, run_id: RunId) -> ServiceResult<()> {
+        if !self.job_runner.cancel_job(run_id).await {
+            // distinguishes unknown runs from the ones that have already started
+            self.run_repo.get_run_by_id(run_id).await?;
+            return Err(ServiceError::Conflict(
+                "Run has already started and cannot be cancelled".into(),
+            ));
+        }
+
+        self.run_repo
+            .update_run_status(run_id, RunStatus::Cancelled)
             .await?;
+        self.events.publish(run_id, RunEventKind::Cancelled);
+        Ok(())
+    }
 
-        Ok(StartRunResponseDto { id })
+    async fn get_run_stats(&self, run_id: RunId) -> ServiceResult<RunStats> {
+        match self.run_repo.get_run_stats(run_id).await? {
+            Some(stats) => Ok(stats),
+            None => {
+                self.run_repo.get_run_by_id(run_id).await?;
+                Err(ServiceError::Conflict(
+                    "Statistics are only collected for finished runs".into(),
+                ))
+            }
+        }
     }
 
-    async fn get_run(&self, run_id: RunId) -> ServiceResult<Run> {
-        self.run_repo.get_run_by_id(run_id).await
+    async fn get_run_timeseries(&self, run_id: RunId) -> ServiceResult<Vec<RunSample>> {
+        let samples = self.run_repo.get_run_samples(run_id).await?;
+        if samples.is_empty() {
+            // tells unknown runs from the ones not sampled yet
+            self.run_repo.get_run_by_id(run_id).await?;
+        }
+
+        Ok(samples)
+    }
+
+    async fn get_run_responses(
+        &self,
+        run_id: RunId,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<CapturedResponse>>> {
+        // unknown runs are told apart before the response starts
+        self.run_repo.get_run_by_id(run_id).await?;
+
+        Ok(self.run_repo.get_run_responses(run_id))
+    }
+
+    async fn export_runs(
+        &self,
+        filter: RunFilter,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<ExportedRun>>> {
+        if let (Some(from), Some(to)) = (filter.from, filter.to) {
+            if from >= to {
+                return Err(ServiceError::BadRequest(
+                    "Start of the export period must precede its end".into(),
+                ));
+            }
+        }
+
+        Ok(self.run_repo.export_runs(filter))
+    }
+
+    async fn get_queue(&self) -> ServiceResult<QueueInfo> {
+        Ok(self.job_runner.get_queue().await)
     }
 }
 
-impl<R, J> PollingServiceImpl<R, J>
+impl<R, J, T> PollingServiceImpl<R, J, T>
 where
     R: RunRepository,
     J: BackgroundJobRunner,
+    T: TargetRepository,
 {
     #[allow(dead_code)]
-    pub fn new(run_repo: R, job_runner: J) -> Self {
+    pub fn new(
+        run_repo: R,
+        job_runner: J,
+        target_repo: T,
+        settings: PollingSettings,
+        webhook_settings: WebhookSettings,
+        events: RunEventBus,
+        finished_runs: FinishedRunNotifier,
+    ) -> Self {
         Self {
             run_repo,
             job_runner,
+            target_repo,
+            settings,
+            webhook_settings,
+            events,
+            finished_runs,
+        }
+    }
+
+    async fn start(
+        &self,
+        start_run_request_dto: StartRunRequestDto,
+        rerun_of: Option<RunId>,
+    ) -> ServiceResult<StartRunResponseDto> {
+        let id = self.run_repo.generate_run_id().await;
+        let job = self.run_job(id, &start_run_request_dto).await?;
+
+        let estimated_start_at = self.job_runner.try_push_job(job.clone()).await?;
+
+        let new_run = Self::new_run(&job, &start_run_request_dto, rerun_of);
+        if let Err(e) = self.run_repo.save_run(&new_run).await {
+            self.withdraw_jobs(&[id], &[]).await;
+            return Err(e);
+        }
+        tracing::info!(run_id = %id, %estimated_start_at, "Run accepted");
+
+        Ok(StartRunResponseDto {
+            id,
+            estimated_start_at,
+        })
+    }
+
+    /// Takes back queued jobs whose runs could not all be saved, cancelling the saved ones.
+    /// Jobs that have already started are left running.
+    async fn withdraw_jobs(&self, queued: &[RunId], saved: &[RunId]) {
+        for &id in queued {
+            if !self.job_runner.cancel_job(id).await {
+                tracing::warn!(run_id = %id, "Run to withdraw has already started");
+                continue;
+            }
+            if saved.contains(&id) {
+                if let Err(e) = self
+                    .run_repo
+                    .update_run_status(id, RunStatus::Cancelled)
+                    .await
+                {
+                    tracing::error!(run_id = %id, error = %e, "Failed to cancel withdrawn run");
+                }
+            }
+            self.events.publish(id, RunEventKind::Cancelled);
+        }
+    }
+
+    /// Builds the job to execute, resolving overrides of the polling settings.
+    async fn run_job(
+        &self,
+        id: RunId,
+        start_run_request_dto: &StartRunRequestDto,
+    ) -> ServiceResult<RunJob> {
+        job_from_spec(
+            id,
+            start_run_request_dto,
+            &self.settings,
+            &self.webhook_settings,
+            &self.target_repo,
+        )
+        .await
+    }
+
+    fn new_run(
+        job: &RunJob,
+        start_run_request_dto: &StartRunRequestDto,
+        rerun_of: Option<RunId>,
+    ) -> NewRun {
+        NewRun {
+            id: job.id,
+            // effective values are saved, so that reruns do not depend on current settings
+            spec: StartRunRequestDto {
+                concurrent_requests: Some(job.concurrent_requests),
+                histogram_buckets: Some(job.histogram_buckets.clone()),
+                polling_address: match start_run_request_dto.target_id {
+                    Some(_) => None,
+                    None => Some(job.target.url.clone()),
+                },
+                ..start_run_request_dto.clone()
+            },
+            status: match job.start_at {
+                Some(_) => RunStatus::Scheduled,
+                None => RunStatus::InProgress,
+            },
+            start_at: job.start_at,
+            rerun_of,
         }
     }
 }
@@ -62,15 +419,99 @@ where
 #[cfg(test)]
 mod should {
     use super::*;
+    use crate::configuration::settings::PriorityLimits;
     use crate::polling::background_job_runner::MockBackgroundJobRunner;
-    use crate::polling::dto::RunStatus;
+    use crate::polling::dto::{AggregatorKind, CaptureMode, RunPriority};
     use crate::polling::run_repository::MockRunRepository;
+    use crate::targets::dto::{HttpMethod, Target, TargetDefinition, TargetId};
+    use crate::targets::target_repository::MockTargetRepository;
+    use chrono::Utc;
     use mockall::predicate::eq;
 
+    fn settings() -> PollingSettings {
+        PollingSettings {
+            polling_address: "127.0.0.1:0".into(),
+            max_concurrent_runs: 1,
+            max_pending_runs: 1,
+            concurrent_requests_per_run: 3,
+            max_pending_runs_per_priority: PriorityLimits::default(),
+            priority_aging_seconds: 0,
+            max_queue_wait_seconds: None,
+            max_scheduled_runs: None,
+            max_concurrent_requests_per_run: Some(10),
+            allowed_polling_addresses: vec!["127.0.0.2:0".into(), "http://127.0.0.3".into()],
+            histogram_buckets: vec![0, 100],
+            sample_interval_seconds: None,
+        }
+    }
+
+    fn webhook_settings() -> WebhookSettings {
+        WebhookSettings {
+            secret: "secret".into(),
+            check_interval_seconds: 1,
+            max_attempts: 3,
+            retry_base_seconds: 5,
+            allowed_callback_origins: vec!["http://127.0.0.1:9000".into()],
+        }
+    }
+
+    fn start_run_request() -> StartRunRequestDto {
+        StartRunRequestDto {
+            seconds: 15,
+            priority: RunPriority::Normal,
+            max_queue_wait_seconds: None,
+            start_at: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            histogram_buckets: None,
+            aggregators: Vec::new(),
+            capture: None,
+            callback_url: None,
+        }
+    }
+
+    fn run_with_status(id: RunId, status: RunStatus) -> Run {
+        Run {
+            id,
+            status,
+            successful_responses_count: 0,
+            sum: 0,
+            sum_overflowed: false,
+            requested_seconds: None,
+            effective_seconds: None,
+            rerun_of: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            aggregates: None,
+        }
+    }
+
+    fn service(
+        run_repo: MockRunRepository,
+        job_runner: MockBackgroundJobRunner,
+    ) -> PollingServiceImpl<MockRunRepository, MockBackgroundJobRunner, MockTargetRepository> {
+        PollingServiceImpl::new(
+            run_repo,
+            job_runner,
+            MockTargetRepository::new(),
+            settings(),
+            webhook_settings(),
+            RunEventBus::default(),
+            FinishedRunNotifier::default(),
+        )
+    }
+
     #[actix_rt::test]
     async fn start_run_correctly() {
         let id = RunId::new_v4();
-        let request = StartRunRequestDto { seconds: 15 };
+        let estimated_start_at = chrono::Utc::now();
+        let request = StartRunRequestDto {
+            priority: RunPriority::High,
+            max_queue_wait_seconds: Some(60),
+            ..start_run_request()
+        };
 
         let run_repo = {
             let mut r = MockRunRepository::new();
@@ -78,7 +519,15 @@ mod should {
             r.expect_save_run()
                 .with(eq(NewRun {
                     id,
-                    seconds: request.seconds,
+                    spec: StartRunRequestDto {
+                        concurrent_requests: Some(3),
+                        polling_address: Some("127.0.0.1:0".into()),
+                        histogram_buckets: Some(vec![0, 100]),
+                        ..request.clone()
+                    },
+                    status: RunStatus::InProgress,
+                    start_at: None,
+                    rerun_of: None,
                 }))
                 .return_const(ServiceResult::Ok(()));
             r
@@ -89,40 +538,945 @@ mod should {
                 .with(eq(RunJob {
                     id,
                     duration: std::time::Duration::from_secs(request.seconds),
+                    priority: RunPriority::High,
+                    max_queue_wait: Some(std::time::Duration::from_secs(60)),
+                    start_at: None,
+                    concurrent_requests: 3,
+                    target: TargetDefinition::from_url("127.0.0.1:0".into()),
+                    histogram_buckets: vec![0, 100],
+                    aggregators: Vec::new(),
+                    capture: None,
                 }))
-                .return_const(ServiceResult::Ok(()));
+                .return_const(ServiceResult::Ok(estimated_start_at));
             j
         };
 
-        let service = PollingServiceImpl::new(run_repo, job_runner);
+        let service = service(run_repo, job_runner);
 
         let actual_result = service.start_run(request).await;
-        assert_eq!(Ok(StartRunResponseDto { id }), actual_result)
+        assert_eq!(
+            Ok(StartRunResponseDto {
+                id,
+                estimated_start_at
+            }),
+            actual_result
+        );
     }
 
     #[actix_rt::test]
-    async fn get_run_correctly() {
+    async fn save_deferred_run_as_scheduled() {
         let id = RunId::new_v4();
-        let expected_result = ServiceResult::Ok(Run {
-            id,
-            status: RunStatus::Finished,
-            successful_responses_count: 10,
-            sum: 150,
-        });
+        let start_at = chrono::Utc::now() + chrono::Duration::minutes(5);
+        let request = StartRunRequestDto {
+            start_at: Some(start_at),
+            ..start_run_request()
+        };
 
         let run_repo = {
             let mut r = MockRunRepository::new();
             r.expect_generate_run_id().return_const(id);
+            r.expect_save_run()
+                .withf(move |r| r.status == RunStatus::Scheduled && r.start_at == Some(start_at))
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_job()
+                .withf(move |j| j.start_at == Some(start_at))
+                .return_const(ServiceResult::Ok(start_at));
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        let actual_result = service.start_run(request).await;
+        assert_eq!(
+            Ok(StartRunResponseDto {
+                id,
+                estimated_start_at: start_at
+            }),
+            actual_result
+        )
+    }
+
+    fn batch_run_repo(ids: Vec<RunId>, saved: usize) -> MockRunRepository {
+        let mut r = MockRunRepository::new();
+        let mut ids = ids.into_iter();
+        r.expect_generate_run_id()
+            .returning(move || ids.next().unwrap());
+        r.expect_save_run()
+            .times(saved)
+            .return_const(ServiceResult::Ok(()));
+        r
+    }
+
+    fn batch_request(size: usize, all_or_nothing: bool) -> BatchStartRunRequestDto {
+        BatchStartRunRequestDto {
+            runs: vec![start_run_request(); size],
+            all_or_nothing,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn start_as_many_runs_of_batch_as_possible() {
+        let ids = vec![RunId::new_v4(), RunId::new_v4()];
+        let now = chrono::Utc::now();
+
+        let run_repo = batch_run_repo(ids.clone(), 1);
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_jobs()
+                .withf(|jobs, all_or_nothing| jobs.len() == 2 && !*all_or_nothing)
+                .return_const(vec![
+                    Ok(now),
+                    Err(ServiceError::TooManyRequests {
+                        retry_at: Some(now),
+                    }),
+                ]);
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        let actual_result = service.start_runs(batch_request(2, false)).await;
+        assert_eq!(
+            Ok(BatchStartRunResponseDto {
+                runs: vec![
+                    BatchStartRunResultDto::Accepted(StartRunResponseDto {
+                        id: ids[0],
+                        estimated_start_at: now,
+                    }),
+                    BatchStartRunResultDto::Rejected(TooManyRequestsResponseDto::new(Some(now))),
+                ]
+            }),
+            actual_result
+        );
+    }
+
+    #[actix_rt::test]
+    async fn reject_whole_batch_if_any_run_does_not_fit() {
+        let ids = vec![RunId::new_v4(), RunId::new_v4()];
+        let now = chrono::Utc::now();
+        let error = ServiceError::TooManyRequests {
+            retry_at: Some(now),
+        };
+
+        let run_repo = batch_run_repo(ids, 0);
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_jobs()
+                .withf(|_, all_or_nothing| *all_or_nothing)
+                .return_const(vec![Err(error.clone()), Err(error.clone())]);
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        let actual_result = service.start_runs(batch_request(2, true)).await;
+        assert_eq!(Err(error), actual_result);
+    }
+
+    #[actix_rt::test]
+    async fn withdraw_queued_runs_of_batch_when_saving_fails() {
+        let ids = vec![RunId::new_v4(), RunId::new_v4(), RunId::new_v4()];
+        let now = chrono::Utc::now();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            let mut generated = ids.clone().into_iter();
+            r.expect_generate_run_id()
+                .returning(move || generated.next().unwrap());
+            let first = ids[0];
+            r.expect_save_run().times(2).returning(move |run| {
+                if run.id == first {
+                    Ok(())
+                } else {
+                    Err(ServiceError::InternalServerError)
+                }
+            });
+            r.expect_update_run_status()
+                .with(eq(ids[0]), eq(RunStatus::Cancelled))
+                .times(1)
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_jobs()
+                .return_const(vec![Ok(now), Ok(now), Ok(now)]);
+            for &id in &ids {
+                j.expect_cancel_job()
+                    .with(eq(id))
+                    .times(1)
+                    .return_const(true);
+            }
+            j
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+
+        let service = PollingServiceImpl {
+            events,
+            ..service(run_repo, job_runner)
+        };
+
+        let actual_result = service.start_runs(batch_request(3, false)).await;
+        assert_eq!(Err(ServiceError::InternalServerError), actual_result);
+        for &id in &ids {
+            let event = published.try_recv().unwrap();
+            assert_eq!((id, RunEventKind::Cancelled), (event.run_id, event.kind));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn cancel_run_not_started_yet() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_update_run_status()
+                .with(eq(id), eq(RunStatus::Cancelled))
+                .times(1)
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_cancel_job().with(eq(id)).return_const(true);
+            j
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+
+        let service = PollingServiceImpl {
+            events,
+            ..service(run_repo, job_runner)
+        };
+
+        assert_eq!(Ok(()), service.cancel_run(id).await);
+        let event = published.try_recv().unwrap();
+        assert_eq!((id, RunEventKind::Cancelled), (event.run_id, event.kind));
+    }
+
+    #[actix_rt::test]
+    async fn refuse_to_cancel_started_run() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
             r.expect_get_run_by_id()
                 .with(eq(id))
-                .return_const(expected_result.clone());
+                .return_const(Ok(run_with_status(id, RunStatus::InProgress)));
+            r.expect_update_run_status().never();
             r
         };
-        let job_runner = MockBackgroundJobRunner::new();
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_cancel_job().with(eq(id)).return_const(false);
+            j
+        };
 
-        let service = PollingServiceImpl::new(run_repo, job_runner);
+        let service = service(run_repo, job_runner);
 
-        let actual_result = service.get_run(id).await;
-        assert_eq!(expected_result, actual_result)
+        assert!(matches!(
+            service.cancel_run(id).await,
+            Err(ServiceError::Conflict(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn change_remaining_duration_of_running_run() {
+        let id = RunId::new_v4();
+
+        let run_repo = MockRunRepository::new();
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_set_remaining_duration()
+                .with(eq(id), eq(std::time::Duration::from_secs(15)))
+                .times(1)
+                .return_const(true);
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert_eq!(
+            Ok(()),
+            service
+                .update_run(
+                    id,
+                    UpdateRunRequestDto {
+                        remaining_seconds: 15
+                    }
+                )
+                .await
+        );
+    }
+
+    #[actix_rt::test]
+    async fn reject_remaining_duration_beyond_limit() {
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_set_remaining_duration().never();
+            j
+        };
+
+        let service = service(MockRunRepository::new(), job_runner);
+
+        assert!(matches!(
+            service
+                .update_run(
+                    RunId::new_v4(),
+                    UpdateRunRequestDto {
+                        remaining_seconds: u64::MAX
+                    }
+                )
+                .await,
+            Err(ServiceError::BadRequest(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn refuse_to_update_run_not_in_progress() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_by_id().with(eq(id)).return_const(Ok(Run {
+                requested_seconds: Some(30),
+                effective_seconds: Some(30),
+                ..run_with_status(id, RunStatus::Finished)
+            }));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_set_remaining_duration().return_const(false);
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert!(matches!(
+            service
+                .update_run(
+                    id,
+                    UpdateRunRequestDto {
+                        remaining_seconds: 15
+                    }
+                )
+                .await,
+            Err(ServiceError::Conflict(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn pause_and_resume_running_run() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_update_run_status()
+                .with(eq(id), eq(RunStatus::Paused))
+                .times(1)
+                .return_const(ServiceResult::Ok(()));
+            r.expect_update_run_status()
+                .with(eq(id), eq(RunStatus::InProgress))
+                .times(1)
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_pause_job()
+                .with(eq(id), eq(true))
+                .return_const(true);
+            j.expect_resume_job()
+                .with(eq(id))
+                .return_const(ResumeOutcome::Resumed);
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert_eq!(
+            Ok(()),
+            service
+                .pause_run(id, PauseRunRequestDto { lend_slot: true })
+                .await
+        );
+        assert_eq!(Ok(()), service.resume_run(id).await);
+    }
+
+    #[actix_rt::test]
+    async fn leave_run_paused_while_it_waits_for_its_lent_slot() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_update_run_status().never();
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_resume_job()
+                .with(eq(id))
+                .return_const(ResumeOutcome::WaitingForSlot);
+            j
+        };
+        let events = RunEventBus::default();
+        let mut published = events.subscribe();
+
+        let service = PollingServiceImpl {
+            events,
+            ..service(run_repo, job_runner)
+        };
+
+        assert_eq!(Ok(()), service.resume_run(id).await);
+        assert!(published.try_recv().is_err());
+    }
+
+    #[actix_rt::test]
+    async fn refuse_to_resume_run_not_paused() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_by_id().with(eq(id)).return_const(Ok(Run {
+                requested_seconds: Some(30),
+                ..run_with_status(id, RunStatus::InProgress)
+            }));
+            r.expect_update_run_status().never();
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_resume_job().return_const(ResumeOutcome::NotPaused);
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert!(matches!(
+            service.resume_run(id).await,
+            Err(ServiceError::Conflict(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn start_run_with_overridden_polling_settings() {
+        let id = RunId::new_v4();
+        let request = StartRunRequestDto {
+            concurrent_requests: Some(10),
+            polling_address: Some("127.0.0.2:0".into()),
+            ..start_run_request()
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(id);
+            r.expect_save_run()
+                .withf(|r| r.spec.concurrent_requests == Some(10))
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_job()
+                .withf(|job| job.concurrent_requests == 10 && job.target.url == "127.0.0.2:0")
+                .return_const(ServiceResult::Ok(Utc::now()));
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert!(service.start_run(request).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn start_run_with_callback_to_allowed_origin() {
+        let request = StartRunRequestDto {
+            callback_url: Some("http://127.0.0.1:9000/runs/finished".into()),
+            ..start_run_request()
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(RunId::new_v4());
+            r.expect_save_run()
+                .withf(|r| {
+                    r.spec.callback_url.as_deref() == Some("http://127.0.0.1:9000/runs/finished")
+                })
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_job()
+                .return_const(ServiceResult::Ok(Utc::now()));
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert!(service.start_run(request).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn start_run_polling_registered_target() {
+        let target = Target {
+            id: TargetId::new_v4(),
+            definition: TargetDefinition {
+                method: HttpMethod::Post,
+                ..TargetDefinition::from_url("http://127.0.0.3:80/poll".into())
+            },
+        };
+        let request = StartRunRequestDto {
+            target_id: Some(target.id),
+            ..start_run_request()
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            let target_id = target.id;
+            r.expect_generate_run_id().return_const(RunId::new_v4());
+            r.expect_save_run()
+                .withf(move |r| {
+                    r.spec.target_id == Some(target_id) && r.spec.polling_address.is_none()
+                })
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            let definition = target.definition.clone();
+            j.expect_try_push_job()
+                .withf(move |job| job.target == definition)
+                .return_const(ServiceResult::Ok(Utc::now()));
+            j
+        };
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_get_target_by_id()
+                .with(eq(target.id))
+                .return_const(Ok(target.clone()));
+            r
+        };
+
+        let service = PollingServiceImpl {
+            target_repo,
+            ..service(run_repo, job_runner)
+        };
+
+        assert!(service.start_run(request).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn reject_unknown_target() {
+        let request = StartRunRequestDto {
+            target_id: Some(TargetId::new_v4()),
+            ..start_run_request()
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(RunId::new_v4());
+            r.expect_save_run().never();
+            r
+        };
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_get_target_by_id()
+                .return_const(Err(ServiceError::NotFound));
+            r
+        };
+
+        let service = PollingServiceImpl {
+            target_repo,
+            ..service(run_repo, MockBackgroundJobRunner::new())
+        };
+
+        assert!(matches!(
+            service.start_run(request).await,
+            Err(ServiceError::BadRequest(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn reject_registered_target_off_allowed_polling_addresses() {
+        let target = Target {
+            id: TargetId::new_v4(),
+            definition: TargetDefinition::from_url("http://169.254.169.254/latest".into()),
+        };
+        let request = StartRunRequestDto {
+            target_id: Some(target.id),
+            ..start_run_request()
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(RunId::new_v4());
+            r.expect_save_run().never();
+            r
+        };
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_get_target_by_id().return_const(Ok(target.clone()));
+            r
+        };
+
+        let service = PollingServiceImpl {
+            target_repo,
+            ..service(run_repo, MockBackgroundJobRunner::new())
+        };
+
+        assert!(matches!(
+            service.start_run(request).await,
+            Err(ServiceError::BadRequest(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn reject_overrides_beyond_limits() {
+        let request = start_run_request();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(RunId::new_v4());
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_job().never();
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        for request in [
+            StartRunRequestDto {
+                seconds: u64::MAX,
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                max_queue_wait_seconds: Some(u64::MAX),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                concurrent_requests: Some(11),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                concurrent_requests: Some(0),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                polling_address: Some("10.0.0.1:80".into()),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                histogram_buckets: Some(vec![10, 10]),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                aggregators: vec![AggregatorKind::TopK { k: 0 }],
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                aggregators: vec![AggregatorKind::TopK { k: 3 }, AggregatorKind::TopK { k: 5 }],
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                capture: Some(CaptureMode::Fraction { fraction: 0.0 }),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                capture: Some(CaptureMode::Fraction { fraction: 1.5 }),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                callback_url: Some("ftp://127.0.0.1/callback".into()),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                callback_url: Some("http://169.254.169.254/latest/meta-data".into()),
+                ..request.clone()
+            },
+            StartRunRequestDto {
+                callback_url: Some("http://127.0.0.1:0/callback".into()),
+                ..request.clone()
+            },
+        ] {
+            assert!(matches!(
+                service.start_run(request).await,
+                Err(ServiceError::BadRequest(_))
+            ));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn rerun_with_stored_parameters() {
+        let (original_id, id) = (RunId::new_v4(), RunId::new_v4());
+        let spec = StartRunRequestDto {
+            priority: RunPriority::Low,
+            start_at: Some(Utc::now() - chrono::Duration::hours(1)),
+            ..start_run_request()
+        };
+        let expected_spec = StartRunRequestDto {
+            start_at: None,
+            concurrent_requests: Some(3),
+            polling_address: Some("127.0.0.1:0".into()),
+            histogram_buckets: Some(vec![0, 100]),
+            ..spec.clone()
+        };
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(id);
+            r.expect_get_run_spec()
+                .with(eq(original_id))
+                .return_const(Ok(Some(spec)));
+            r.expect_save_run()
+                .with(eq(NewRun {
+                    id,
+                    spec: expected_spec,
+                    status: RunStatus::InProgress,
+                    start_at: None,
+                    rerun_of: Some(original_id),
+                }))
+                .times(1)
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_try_push_job()
+                .withf(move |job| job.id == id && job.priority == RunPriority::Low)
+                .return_const(ServiceResult::Ok(Utc::now()));
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        assert_eq!(
+            Some(id),
+            service.rerun(original_id).await.ok().map(|r| r.id)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn refuse_to_rerun_run_without_stored_parameters() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_spec().return_const(Ok(None));
+            r.expect_save_run().never();
+            r
+        };
+        let job_runner = MockBackgroundJobRunner::new();
+
+        let service = service(run_repo, job_runner);
+
+        assert!(matches!(
+            service.rerun(id).await,
+            Err(ServiceError::Conflict(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn refuse_to_get_stats_of_unfinished_run() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_stats().with(eq(id)).return_const(Ok(None));
+            r.expect_get_run_by_id().with(eq(id)).return_const(Ok(Run {
+                requested_seconds: Some(30),
+                ..run_with_status(id, RunStatus::InProgress)
+            }));
+            r
+        };
+
+        let service = service(run_repo, MockBackgroundJobRunner::new());
+
+        assert!(matches!(
+            service.get_run_stats(id).await,
+            Err(ServiceError::Conflict(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn reject_export_of_empty_period() {
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_export_runs().never();
+            r
+        };
+
+        let service = service(run_repo, MockBackgroundJobRunner::new());
+
+        let now = Utc::now();
+        let filter = RunFilter {
+            from: Some(now),
+            to: Some(now),
+            ..RunFilter::default()
+        };
+        assert!(matches!(
+            service.export_runs(filter).await,
+            Err(ServiceError::BadRequest(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn reply_not_found_for_timeseries_of_unknown_run() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_samples()
+                .with(eq(id))
+                .return_const(Ok(Vec::new()));
+            r.expect_get_run_by_id()
+                .with(eq(id))
+                .return_const(Err(ServiceError::NotFound));
+            r
+        };
+
+        let service = service(run_repo, MockBackgroundJobRunner::new());
+
+        assert_eq!(
+            Err(ServiceError::NotFound),
+            service.get_run_timeseries(id).await
+        );
+    }
+
+    #[actix_rt::test]
+    async fn reply_not_found_for_responses_of_unknown_run() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_by_id()
+                .with(eq(id))
+                .return_const(Err(ServiceError::NotFound));
+            r.expect_get_run_responses().never();
+            r
+        };
+
+        let service = service(run_repo, MockBackgroundJobRunner::new());
+
+        assert!(matches!(
+            service.get_run_responses(id).await,
+            Err(ServiceError::NotFound)
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn wait_for_run_until_notified() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            let mut seq = mockall::Sequence::new();
+            r.expect_get_run_by_id()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_const(Ok(run_with_status(id, RunStatus::InProgress)));
+            r.expect_get_run_by_id()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_const(Ok(run_with_status(id, RunStatus::Finished)));
+            r
+        };
+        let finished_runs = FinishedRunNotifier::default();
+
+        let service = PollingServiceImpl {
+            finished_runs: finished_runs.clone(),
+            ..service(run_repo, MockBackgroundJobRunner::new())
+        };
+
+        let started = std::time::Instant::now();
+        let (run, ()) = tokio::join!(
+            service.wait_for_run(id, std::time::Duration::from_secs(10)),
+            async {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                finished_runs.notify(RunId::new_v4());
+                finished_runs.notify(id);
+            }
+        );
+
+        assert_eq!(RunStatus::Finished, run.unwrap().status);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[actix_rt::test]
+    async fn reply_with_unfinished_run_after_wait_times_out() {
+        let id = RunId::new_v4();
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_by_id()
+                .times(2)
+                .return_const(Ok(run_with_status(id, RunStatus::InProgress)));
+            r
+        };
+
+        let service = service(run_repo, MockBackgroundJobRunner::new());
+
+        let run = service
+            .wait_for_run(id, std::time::Duration::from_millis(50))
+            .await;
+        assert_eq!(RunStatus::InProgress, run.unwrap().status);
+
+        assert!(matches!(
+            service
+                .wait_for_run(id, std::time::Duration::from_secs(3600))
+                .await,
+            Err(ServiceError::BadRequest(_))
+        ));
+    }
+
+    #[actix_rt::test]
+    async fn get_run_correctly() {
+        let id = RunId::new_v4();
+        let expected_result = ServiceResult::Ok(Run {
+            successful_responses_count: 10,
+            sum: 150,
+            ..run_with_status(id, RunStatus::Finished)
+        });
+
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_generate_run_id().return_const(id);
+            r.expect_get_run_by_id()
+                .with(eq(id))
+                .return_const(expected_result.clone());
+            r
+        };
+        let job_runner = MockBackgroundJobRunner::new();
+
+        let service = service(run_repo, job_runner);
+
+        let actual_result = service.get_run(id).await;
+        assert_eq!(expected_result, actual_result)
+    }
+
+    #[actix_rt::test]
+    async fn get_queue_correctly() {
+        let expected_queue = QueueInfo::default();
+
+        let run_repo = MockRunRepository::new();
+        let job_runner = {
+            let mut j = MockBackgroundJobRunner::new();
+            j.expect_get_queue().return_const(expected_queue.clone());
+            j
+        };
+
+        let service = service(run_repo, job_runner);
+
+        let actual_result = service.get_queue().await;
+        assert_eq!(Ok(expected_queue), actual_result)
     }
 }
@@ -0,0 +1,168 @@
+use chrono::Utc;
+
+use crate::configuration::settings::{PollingSettings, WebhookSettings};
+use crate::polling::dto::{AggregatorKind, CaptureMode, RunId, RunJob, StartRunRequestDto};
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::targets::dto::TargetDefinition;
+use crate::targets::target_repository::TargetRepository;
+
+const MAX_HISTOGRAM_BUCKETS: usize = 100;
+const MAX_TOP_K: usize = 100;
+/// Longest run, keeping its end time representable
+pub const MAX_RUN_SECONDS: u64 = 7 * 24 * 60 * 60;
+const MAX_QUEUE_WAIT_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Builds the job to execute, resolving overrides of the polling settings.
+/// Fails with `BadRequest` if the run spec is invalid.
+pub async fn job_from_spec<T>(
+    id: RunId,
+    start_run_request_dto: &StartRunRequestDto,
+    settings: &PollingSettings,
+    webhook_settings: &WebhookSettings,
+    target_repo: &T,
+) -> ServiceResult<RunJob>
+where
+    T: TargetRepository,
+{
+    if start_run_request_dto.seconds > MAX_RUN_SECONDS {
+        return Err(ServiceError::BadRequest(format!(
+            "Runs may last at most {} seconds",
+            MAX_RUN_SECONDS
+        )));
+    }
+    if start_run_request_dto.max_queue_wait_seconds > Some(MAX_QUEUE_WAIT_SECONDS) {
+        return Err(ServiceError::BadRequest(format!(
+            "Runs may wait in the queue for at most {} seconds",
+            MAX_QUEUE_WAIT_SECONDS
+        )));
+    }
+    let concurrent_requests = match start_run_request_dto.concurrent_requests {
+        Some(0) => {
+            return Err(ServiceError::BadRequest(
+                "Concurrent requests must be positive".into(),
+            ))
+        }
+        Some(requested) if requested > settings.concurrent_requests_limit() => {
+            return Err(ServiceError::BadRequest(format!(
+                "At most {} concurrent requests per run are allowed",
+                settings.concurrent_requests_limit()
+            )))
+        }
+        Some(requested) => requested,
+        None => settings.concurrent_requests_per_run,
+    };
+    let target = match (
+        start_run_request_dto.target_id,
+        &start_run_request_dto.polling_address,
+    ) {
+        (Some(_), Some(_)) => {
+            return Err(ServiceError::BadRequest(
+                "Either a target or a polling address can be given".into(),
+            ))
+        }
+        (Some(target_id), None) => {
+            let definition = target_repo
+                .get_target_by_id(target_id)
+                .await
+                .map_err(|e| match e {
+                    ServiceError::NotFound => ServiceError::BadRequest("Unknown target".into()),
+                    e => e,
+                })?
+                .definition;
+            // targets saved before their origins were checked
+            match reqwest::Url::parse(&definition.url) {
+                Ok(url) if settings.is_target_url_allowed(&url) => definition,
+                _ => {
+                    return Err(ServiceError::BadRequest(
+                        "Target is not on an allowed polling address".into(),
+                    ))
+                }
+            }
+        }
+        (None, Some(address)) if !settings.is_polling_address_allowed(address) => {
+            return Err(ServiceError::BadRequest(
+                "Polling address is not allowed".into(),
+            ))
+        }
+        (None, Some(address)) => TargetDefinition::from_url(address.clone()),
+        (None, None) => TargetDefinition::from_url(settings.polling_address.clone()),
+    };
+    let histogram_buckets = match &start_run_request_dto.histogram_buckets {
+        Some(buckets) if buckets.len() > MAX_HISTOGRAM_BUCKETS => {
+            return Err(ServiceError::BadRequest(format!(
+                "At most {} histogram buckets are allowed",
+                MAX_HISTOGRAM_BUCKETS
+            )))
+        }
+        Some(buckets) if buckets.windows(2).any(|pair| pair[0] >= pair[1]) => {
+            return Err(ServiceError::BadRequest(
+                "Histogram buckets must be strictly increasing".into(),
+            ))
+        }
+        Some(buckets) => buckets.clone(),
+        None => settings.histogram_buckets(),
+    };
+    validate_aggregators(&start_run_request_dto.aggregators)?;
+    if let Some(callback_url) = &start_run_request_dto.callback_url {
+        let url = reqwest::Url::parse(callback_url)
+            .map_err(|e| ServiceError::BadRequest(format!("Invalid callback URL: {}", e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ServiceError::BadRequest(
+                "Callback URL must use http or https".into(),
+            ));
+        }
+        if !webhook_settings.is_callback_url_allowed(&url) {
+            return Err(ServiceError::BadRequest(
+                "Callback URL must be on one of the allowed callback origins".into(),
+            ));
+        }
+    }
+    if let Some(CaptureMode::Fraction { fraction }) = start_run_request_dto.capture {
+        if !(fraction > 0.0 && fraction <= 1.0) {
+            return Err(ServiceError::BadRequest(
+                "Captured fraction of responses must be above 0 and at most 1".into(),
+            ));
+        }
+    }
+
+    Ok(RunJob {
+        id,
+        duration: std::time::Duration::from_secs(start_run_request_dto.seconds),
+        priority: start_run_request_dto.priority,
+        max_queue_wait: start_run_request_dto
+            .max_queue_wait_seconds
+            .map(std::time::Duration::from_secs),
+        start_at: start_run_request_dto
+            .start_at
+            .filter(|start_at| *start_at > Utc::now()),
+        concurrent_requests,
+        target,
+        histogram_buckets,
+        aggregators: start_run_request_dto.aggregators.clone(),
+        capture: start_run_request_dto.capture,
+    })
+}
+
+fn validate_aggregators(aggregators: &[AggregatorKind]) -> ServiceResult<()> {
+    for (i, aggregator) in aggregators.iter().enumerate() {
+        if aggregators[..i]
+            .iter()
+            .any(|a| a.name() == aggregator.name())
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "Aggregator {} is chosen more than once",
+                aggregator.name()
+            )));
+        }
+        if let AggregatorKind::TopK { k } = aggregator {
+            if !(1..=MAX_TOP_K).contains(k) {
+                return Err(ServiceError::BadRequest(format!(
+                    "Top-k aggregator supports k from 1 to {}",
+                    MAX_TOP_K
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
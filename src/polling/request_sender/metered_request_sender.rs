@@ -0,0 +1,61 @@
+use crate::metrics::registry::Metrics;
+use crate::polling::dto::{PolledResponse, RunId};
+use crate::polling::request_sender::RequestSender;
+use crate::targets::dto::TargetDefinition;
+use async_trait::async_trait;
+use std::time::Instant;
+
+/// Counts outcomes and latencies of the requests sent by another sender
+#[derive(Clone)]
+pub struct MeteredRequestSender<S> {
+    sender: S,
+    metrics: Metrics,
+}
+
+impl<S> MeteredRequestSender<S> {
+    pub fn new(sender: S, metrics: Metrics) -> Self {
+        Self { sender, metrics }
+    }
+}
+
+#[async_trait]
+impl<S: RequestSender> RequestSender for MeteredRequestSender<S> {
+    async fn send_request(&self, target: &TargetDefinition, id: RunId) -> PolledResponse {
+        let started = Instant::now();
+        let response = self.sender.send_request(target, id).await;
+        self.metrics
+            .observe_upstream_request(&response, started.elapsed());
+        response
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::FaultyServerResponse;
+    use crate::polling::request_sender::MockRequestSender;
+
+    #[actix_rt::test]
+    async fn count_sent_requests() {
+        let mut sender = MockRequestSender::new();
+        sender
+            .expect_send_request()
+            .returning(|_, _| PolledResponse {
+                status: Some(200),
+                body: r#"{"value": 5}"#.into(),
+                result: FaultyServerResponse::Ok { value: 5 },
+            });
+        let metrics = Metrics::default();
+        let sender = MeteredRequestSender::new(sender, metrics.clone());
+
+        let target = TargetDefinition::from_url("127.0.0.1:0".into());
+        for _ in 0..2 {
+            sender.send_request(&target, RunId::new_v4()).await;
+        }
+
+        assert!(metrics
+            .encode()
+            .lines()
+            .any(|l| l == r#"poller_upstream_requests_total{outcome="ok",status="200"} 2"#));
+    }
+}
@@ -1,15 +1,18 @@
 #[cfg(test)]
 use mockall::mock;
 
-use crate::polling::dto::{FaultyServerResponse, RunId};
+use crate::polling::dto::{PolledResponse, RunId};
+use crate::targets::dto::TargetDefinition;
 use async_trait::async_trait;
 
+mod metered_request_sender;
 mod reqwest_request_sender;
+pub use metered_request_sender::MeteredRequestSender;
 pub use reqwest_request_sender::ReqwestRequestSender;
 
 #[async_trait]
 pub trait RequestSender: Clone + Send + Sync {
-    async fn send_request(&self, id: RunId) -> FaultyServerResponse;
+    async fn send_request(&self, target: &TargetDefinition, id: RunId) -> PolledResponse;
 }
 
 #[cfg(test)]
@@ -24,6 +27,6 @@ mock! {
 
     #[async_trait]
     impl RequestSender for RequestSender {
-        async fn send_request(&self, id: RunId) -> FaultyServerResponse;
+        async fn send_request(&self, target: &TargetDefinition, id: RunId) -> PolledResponse;
     }
 }
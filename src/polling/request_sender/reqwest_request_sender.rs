@@ -1,41 +1,84 @@
-use crate::polling::dto::{FaultyServerResponse, RunId};
+use crate::polling::dto::{FaultyServerResponse, PolledResponse, RunId};
 use crate::polling::request_sender::RequestSender;
+use crate::targets::dto::{TargetDefinition, RUN_ID_HEADER};
 use async_trait::async_trait;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::sdk::propagation::TraceContextPropagator;
 use reqwest::Client;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 #[derive(Clone)]
 pub struct ReqwestRequestSender {
     client: Client,
-    polling_address: String,
 }
 
 impl ReqwestRequestSender {
-    pub fn new(client: Client, polling_address: String) -> Self {
-        Self {
-            client,
-            polling_address,
-        }
+    pub fn new(client: Client) -> Self {
+        Self { client }
     }
 }
 
 #[async_trait]
 impl RequestSender for ReqwestRequestSender {
-    async fn send_request(&self, id: RunId) -> FaultyServerResponse {
-        self.client
-            .get(&self.polling_address)
-            .header("X-Run-Id", id.to_string())
-            .send()
-            .await
-            .expect("Failed to execute request to Faulty Server")
-            .json::<FaultyServerResponse>()
-            .await
-            .expect("Failed to parse json from Faulty Server")
+    async fn send_request(&self, target: &TargetDefinition, id: RunId) -> PolledResponse {
+        let mut request = self.client.request(target.method.into(), &target.url);
+        for (name, value) in &target.headers {
+            request = request.header(name, value);
+        }
+        if let Some(timeout_ms) = target.timeout_ms {
+            request = request.timeout(Duration::from_millis(timeout_ms));
+        }
+        // the polled server may continue the trace of the run with `traceparent`
+        let mut trace_headers = HashMap::new();
+        TraceContextPropagator::new()
+            .inject_context(&tracing::Span::current().context(), &mut trace_headers);
+        for (name, value) in &trace_headers {
+            request = request.header(name, value);
+        }
+
+        let response = request.header(RUN_ID_HEADER, id.to_string()).send().await;
+        // timeouts and unexpected bodies are counted as failed requests
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::debug!(run_id = %id, error = %e, "Request to target failed");
+                return PolledResponse::failed(None, e.to_string());
+            }
+        };
+        let status = response.status().as_u16();
+        match response.bytes().await {
+            Ok(body) => {
+                tracing::trace!(run_id = %id, status, "Target responded");
+                PolledResponse {
+                    status: Some(status),
+                    result: target.response_schema.extract(status, &body),
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                }
+            }
+            Err(e) => {
+                tracing::debug!(run_id = %id, status, error = %e, "Response of target was not read");
+                PolledResponse::failed(Some(status), e.to_string())
+            }
+        }
+    }
+}
+
+impl PolledResponse {
+    fn failed(status: Option<u16>, error: String) -> Self {
+        Self {
+            status,
+            body: String::new(),
+            result: FaultyServerResponse::Err { error },
+        }
     }
 }
 
 #[cfg(test)]
 mod should {
     use super::*;
+    use crate::targets::dto::HttpMethod;
     use httpmock::{Method, MockServer};
 
     async fn mock(mock_server: &MockServer, status: u16, response_json: &FaultyServerResponse) {
@@ -52,58 +95,131 @@ mod should {
     #[actix_rt::test]
     async fn handle_200_ok_responses_correctly() {
         let mock_server = MockServer::start_async().await;
-        let sender =
-            ReqwestRequestSender::new(Client::new(), format!("http://{}", mock_server.address()));
+        let sender = ReqwestRequestSender::new(Client::new());
+        let target = TargetDefinition::from_url(format!("http://{}", mock_server.address()));
 
         let expected_response = FaultyServerResponse::Ok { value: 50 };
         mock(&mock_server, 200, &expected_response).await;
 
-        let actual_response = sender.send_request(RunId::new_v4()).await;
+        let actual_response = sender.send_request(&target, RunId::new_v4()).await.result;
         assert_eq!(expected_response, actual_response)
     }
 
     #[actix_rt::test]
     async fn handle_500_internal_server_error_responses_correctly() {
         let mock_server = MockServer::start_async().await;
-        let sender =
-            ReqwestRequestSender::new(Client::new(), format!("http://{}", mock_server.address()));
+        let sender = ReqwestRequestSender::new(Client::new());
+        let target = TargetDefinition::from_url(format!("http://{}", mock_server.address()));
 
         let expected_response = FaultyServerResponse::Err {
             error: "Internal server error".into(),
         };
         mock(&mock_server, 500, &expected_response).await;
 
-        let actual_response = sender.send_request(RunId::new_v4()).await;
+        let actual_response = sender.send_request(&target, RunId::new_v4()).await.result;
         assert_eq!(expected_response, actual_response);
     }
 
     #[actix_rt::test]
     async fn handle_504_timed_out_responses_correctly() {
         let mock_server = MockServer::start_async().await;
-        let sender =
-            ReqwestRequestSender::new(Client::new(), format!("http://{}", mock_server.address()));
+        let sender = ReqwestRequestSender::new(Client::new());
+        let target = TargetDefinition::from_url(format!("http://{}", mock_server.address()));
 
         let expected_response = FaultyServerResponse::Err {
             error: "Timed out".into(),
         };
         mock(&mock_server, 504, &expected_response).await;
 
-        let actual_response = sender.send_request(RunId::new_v4()).await;
+        let actual_response = sender.send_request(&target, RunId::new_v4()).await.result;
         assert_eq!(expected_response, actual_response);
     }
 
     #[actix_rt::test]
     async fn handle_429_too_many_requests_responses_correctly() {
         let mock_server = MockServer::start_async().await;
-        let sender =
-            ReqwestRequestSender::new(Client::new(), format!("http://{}", mock_server.address()));
+        let sender = ReqwestRequestSender::new(Client::new());
+        let target = TargetDefinition::from_url(format!("http://{}", mock_server.address()));
 
         let expected_response = FaultyServerResponse::Err {
             error: "Too many concurrent requests".into(),
         };
         mock(&mock_server, 429, &expected_response).await;
 
-        let actual_response = sender.send_request(RunId::new_v4()).await;
+        let actual_response = sender.send_request(&target, RunId::new_v4()).await.result;
         assert_eq!(expected_response, actual_response);
     }
+
+    #[actix_rt::test]
+    async fn send_requests_as_defined_by_target() {
+        let mock_server = MockServer::start_async().await;
+        let sender = ReqwestRequestSender::new(Client::new());
+        let id = RunId::new_v4();
+        let target = TargetDefinition {
+            method: HttpMethod::Post,
+            headers: vec![("Authorization".to_string(), "Bearer token".to_string())]
+                .into_iter()
+                .collect(),
+            ..TargetDefinition::from_url(format!("http://{}/poll", mock_server.address()))
+        };
+
+        let expected_response = FaultyServerResponse::Ok { value: 7 };
+        let target_mock = mock_server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/poll")
+                    .header("Authorization", "Bearer token")
+                    .header(RUN_ID_HEADER, &id.to_string());
+                then.status(200)
+                    .header("Content-Type", "application/json")
+                    .json_body_obj(&expected_response);
+            })
+            .await;
+
+        let actual_response = sender.send_request(&target, id).await.result;
+        assert_eq!(expected_response, actual_response);
+        target_mock.assert_async().await;
+    }
+
+    #[actix_rt::test]
+    async fn propagate_trace_context_of_current_span() {
+        use opentelemetry::trace::TracerProvider;
+        use tracing::Instrument;
+        use tracing_subscriber::layer::SubscriberExt;
+
+        // tracers only hold a weak reference to their provider
+        let provider = opentelemetry::sdk::trace::TracerProvider::builder().build();
+        let tracer = provider.get_tracer("test", None);
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mock_server = MockServer::start_async().await;
+        let sender = ReqwestRequestSender::new(Client::new());
+        let target = TargetDefinition::from_url(format!("http://{}", mock_server.address()));
+
+        let target_mock = mock_server
+            .mock_async(|when, then| {
+                when.method(Method::GET).header_exists("traceparent");
+                then.status(200)
+                    .header("Content-Type", "application/json")
+                    .json_body_obj(&FaultyServerResponse::Ok { value: 1 });
+            })
+            .await;
+
+        sender
+            .send_request(&target, RunId::new_v4())
+            .instrument(tracing::info_span!("run"))
+            .await;
+        target_mock.assert_async().await;
+    }
+
+    #[actix_rt::test]
+    async fn report_unreachable_target_as_failed_request() {
+        let sender = ReqwestRequestSender::new(Client::new());
+        let target = TargetDefinition::from_url("http://127.0.0.1:1".into());
+
+        let actual_response = sender.send_request(&target, RunId::new_v4()).await.result;
+        assert!(matches!(actual_response, FaultyServerResponse::Err { .. }));
+    }
 }
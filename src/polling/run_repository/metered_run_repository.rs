@@ -0,0 +1,146 @@
+use std::future::Future;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+
+use crate::metrics::registry::Metrics;
+use crate::polling::dto::{
+    CapturedResponse, ExportedRun, NewRun, Run, RunFilter, RunId, RunSample, RunStats, RunStatus,
+    StartRunRequestDto,
+};
+use crate::polling::errors::ServiceResult;
+use crate::polling::run_repository::RunRepository;
+
+/// Measures how long the queries of another run repository take
+#[derive(Clone)]
+pub struct MeteredRunRepository<R> {
+    run_repo: R,
+    metrics: Metrics,
+}
+
+impl<R> MeteredRunRepository<R> {
+    pub fn new(run_repo: R, metrics: Metrics) -> Self {
+        Self { run_repo, metrics }
+    }
+
+    async fn timed<T>(&self, query: &str, future: impl Future<Output = T>) -> T {
+        let started = Instant::now();
+        let output = future.await;
+        self.metrics
+            .observe_repository_query(query, started.elapsed());
+        output
+    }
+}
+
+#[async_trait]
+impl<R: RunRepository> RunRepository for MeteredRunRepository<R> {
+    async fn generate_run_id(&self) -> RunId {
+        self.run_repo.generate_run_id().await
+    }
+
+    async fn save_run(&self, run: &NewRun) -> ServiceResult<()> {
+        self.timed("save_run", self.run_repo.save_run(run)).await
+    }
+
+    async fn update_run(&self, run: &Run) -> ServiceResult<()> {
+        self.timed("update_run", self.run_repo.update_run(run))
+            .await
+    }
+
+    async fn update_run_status(&self, run_id: RunId, status: RunStatus) -> ServiceResult<()> {
+        self.timed(
+            "update_run_status",
+            self.run_repo.update_run_status(run_id, status),
+        )
+        .await
+    }
+
+    async fn get_run_by_id(&self, run_id: RunId) -> ServiceResult<Run> {
+        self.timed("get_run_by_id", self.run_repo.get_run_by_id(run_id))
+            .await
+    }
+
+    async fn get_run_spec(&self, run_id: RunId) -> ServiceResult<Option<StartRunRequestDto>> {
+        self.timed("get_run_spec", self.run_repo.get_run_spec(run_id))
+            .await
+    }
+
+    async fn finish_run(&self, run: &Run, stats: &RunStats) -> ServiceResult<()> {
+        self.timed("finish_run", self.run_repo.finish_run(run, stats))
+            .await
+    }
+
+    async fn get_run_stats(&self, run_id: RunId) -> ServiceResult<Option<RunStats>> {
+        self.timed("get_run_stats", self.run_repo.get_run_stats(run_id))
+            .await
+    }
+
+    async fn save_run_sample(&self, run_id: RunId, sample: &RunSample) -> ServiceResult<()> {
+        self.timed(
+            "save_run_sample",
+            self.run_repo.save_run_sample(run_id, sample),
+        )
+        .await
+    }
+
+    async fn get_run_samples(&self, run_id: RunId) -> ServiceResult<Vec<RunSample>> {
+        self.timed("get_run_samples", self.run_repo.get_run_samples(run_id))
+            .await
+    }
+
+    async fn save_run_responses(
+        &self,
+        run_id: RunId,
+        responses: &[CapturedResponse],
+    ) -> ServiceResult<()> {
+        self.timed(
+            "save_run_responses",
+            self.run_repo.save_run_responses(run_id, responses),
+        )
+        .await
+    }
+
+    // like the export, pages are read while the response is streamed
+    fn get_run_responses(
+        &self,
+        run_id: RunId,
+    ) -> BoxStream<'static, ServiceResult<CapturedResponse>> {
+        self.run_repo.get_run_responses(run_id)
+    }
+
+    // pages of the export are read while the response is streamed, they are not timed
+    fn export_runs(&self, filter: RunFilter) -> BoxStream<'static, ServiceResult<ExportedRun>> {
+        self.run_repo.export_runs(filter)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::errors::ServiceError;
+    use crate::polling::run_repository::MockRunRepository;
+    use mockall::predicate::*;
+
+    #[actix_rt::test]
+    async fn time_queries_of_inner_repository() {
+        let run_id = RunId::new_v4();
+        let mut run_repo = MockRunRepository::new();
+        run_repo
+            .expect_get_run_by_id()
+            .with(eq(run_id))
+            .return_const(Err(ServiceError::NotFound));
+        let metrics = Metrics::default();
+        let run_repo = MeteredRunRepository::new(run_repo, metrics.clone());
+
+        assert_eq!(
+            Err(ServiceError::NotFound),
+            run_repo.get_run_by_id(run_id).await
+        );
+        assert!(metrics
+            .encode()
+            .lines()
+            .any(|l| l
+                == r#"poller_repository_query_duration_seconds_count{query="get_run_by_id"} 1"#));
+    }
+}
@@ -1,19 +1,50 @@
 use async_trait::async_trait;
+use futures::stream::BoxStream;
 #[cfg(test)]
 use mockall::mock;
 
-use crate::polling::dto::{NewRun, Run, RunId};
+use crate::polling::dto::{
+    CapturedResponse, ExportedRun, NewRun, Run, RunFilter, RunId, RunSample, RunStats, RunStatus,
+    StartRunRequestDto,
+};
 use crate::polling::errors::ServiceResult;
 
+mod metered_run_repository;
 mod postgres_run_repository;
+pub use metered_run_repository::MeteredRunRepository;
 pub use postgres_run_repository::PostgresRunRepository;
 
 #[async_trait]
 pub trait RunRepository: Clone + Send + Sync {
     async fn generate_run_id(&self) -> RunId;
     async fn save_run(&self, run: &NewRun) -> ServiceResult<()>;
+    /// `NotFound` if there is no such run
     async fn update_run(&self, run: &Run) -> ServiceResult<()>;
+    /// Changes status of a run that is neither finished nor cancelled yet
+    async fn update_run_status(&self, run_id: RunId, status: RunStatus) -> ServiceResult<()>;
     async fn get_run_by_id(&self, run_id: RunId) -> ServiceResult<Run>;
+    /// Request the run was started with, `None` for runs saved before it was kept
+    async fn get_run_spec(&self, run_id: RunId) -> ServiceResult<Option<StartRunRequestDto>>;
+    /// Updates the finished run and saves its statistics in one transaction
+    async fn finish_run(&self, run: &Run, stats: &RunStats) -> ServiceResult<()>;
+    /// `None` until the run finishes
+    async fn get_run_stats(&self, run_id: RunId) -> ServiceResult<Option<RunStats>>;
+    async fn save_run_sample(&self, run_id: RunId, sample: &RunSample) -> ServiceResult<()>;
+    /// Samples of a run, oldest first
+    async fn get_run_samples(&self, run_id: RunId) -> ServiceResult<Vec<RunSample>>;
+    async fn save_run_responses(
+        &self,
+        run_id: RunId,
+        responses: &[CapturedResponse],
+    ) -> ServiceResult<()>;
+    /// Captured responses of a run, in the order they were saved
+    /// Streams captured responses of the run in the order of capture
+    fn get_run_responses(
+        &self,
+        run_id: RunId,
+    ) -> BoxStream<'static, ServiceResult<CapturedResponse>>;
+    /// Streams runs matching the filter, oldest first, without reading them all at once
+    fn export_runs(&self, filter: RunFilter) -> BoxStream<'static, ServiceResult<ExportedRun>>;
 }
 
 #[cfg(test)]
@@ -29,6 +60,19 @@ mock! {
         async fn generate_run_id(&self) -> RunId;
         async fn save_run(&self, run: &NewRun) -> ServiceResult<()>;
         async fn update_run(&self, run: &Run) -> ServiceResult<()>;
+        async fn update_run_status(&self, run_id: RunId, status: RunStatus) -> ServiceResult<()>;
         async fn get_run_by_id(&self, run_id: RunId) -> ServiceResult<Run>;
+        async fn get_run_spec(&self, run_id: RunId) -> ServiceResult<Option<StartRunRequestDto>>;
+        async fn finish_run(&self, run: &Run, stats: &RunStats) -> ServiceResult<()>;
+        async fn get_run_stats(&self, run_id: RunId) -> ServiceResult<Option<RunStats>>;
+        async fn save_run_sample(&self, run_id: RunId, sample: &RunSample) -> ServiceResult<()>;
+        async fn get_run_samples(&self, run_id: RunId) -> ServiceResult<Vec<RunSample>>;
+        async fn save_run_responses(
+            &self,
+            run_id: RunId,
+            responses: &[CapturedResponse],
+        ) -> ServiceResult<()>;
+        fn get_run_responses(&self, run_id: RunId) -> BoxStream<'static, ServiceResult<CapturedResponse>>;
+        fn export_runs(&self, filter: RunFilter) -> BoxStream<'static, ServiceResult<ExportedRun>>;
     }
 }
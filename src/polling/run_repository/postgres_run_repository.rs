@@ -1,10 +1,19 @@
 use std::convert::TryInto;
 
 use async_trait::async_trait;
-use sqlx::PgPool;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use sqlx::postgres::PgListener;
+use sqlx::types::Json;
+use sqlx::{PgPool, Postgres, Transaction};
 
-use crate::polling::dto::{NewRun, Run, RunId};
-use crate::polling::errors::ServiceResult;
+use crate::polling::dto::{
+    CapturedResponse, ExportedRun, NewRun, Run, RunFilter, RunId, RunSample, RunStats, RunStatus,
+    StartRunRequestDto,
+};
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::polling::finished_run_notifier::FinishedRunNotifier;
 use crate::polling::run_repository::RunRepository;
 
 #[derive(Clone, Debug)]
@@ -12,10 +21,271 @@ pub struct PostgresRunRepository {
     db_pool: PgPool,
 }
 
+/// Runs exported by a single query
+const EXPORT_PAGE_SIZE: usize = 1000;
+/// Captured responses read by a single query
+const RESPONSES_PAGE_SIZE: usize = 1000;
+/// Notified with ids of runs reaching a terminal status
+const RUN_FINISHED_CHANNEL: &str = "run_finished";
+const LISTEN_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
 impl PostgresRunRepository {
     pub fn new(db_pool: PgPool) -> Self {
         Self { db_pool }
     }
+
+    /// Passes runs reaching a terminal status, on this or any other replica, to the notifier
+    pub async fn forward_finished_runs(self, notifier: FinishedRunNotifier) {
+        loop {
+            if let Err(e) = self.listen_finished_runs(&notifier).await {
+                log::error!("Failed to listen for finished runs: {}", e);
+            }
+            tokio::time::sleep(LISTEN_RETRY_INTERVAL).await;
+        }
+    }
+
+    async fn listen_finished_runs(&self, notifier: &FinishedRunNotifier) -> ServiceResult<()> {
+        let mut listener = PgListener::connect_with(&self.db_pool).await?;
+        listener.listen(RUN_FINISHED_CHANNEL).await?;
+        loop {
+            let notification = listener.recv().await?;
+            match notification.payload().parse() {
+                Ok(run_id) => notifier.notify(run_id),
+                Err(_) => log::warn!("Unexpected finished run: {}", notification.payload()),
+            }
+        }
+    }
+
+    /// Raises the notification once the transaction commits
+    async fn notify_finished(
+        tx: &mut Transaction<'static, Postgres>,
+        run_id: RunId,
+    ) -> ServiceResult<()> {
+        sqlx::query!(
+            "select from pg_notify($1, $2)",
+            RUN_FINISHED_CHANNEL,
+            run_id.to_string(),
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Adds the delivery of a run that reached a terminal status to the webhook outbox,
+    /// in the transaction changing the status
+    async fn enqueue_callback(
+        tx: &mut Transaction<'static, Postgres>,
+        run_id: RunId,
+        callback_url: Option<String>,
+    ) -> ServiceResult<()> {
+        let callback_url = match callback_url {
+            Some(callback_url) => callback_url,
+            None => return Ok(()),
+        };
+        sqlx::query!(
+            r#"
+            insert into webhook_delivery (run_id,
+                                          webhook_delivery_url,
+                                          webhook_delivery_next_attempt_datetime)
+            values ($1, $2, now())
+            on conflict (run_id) do nothing
+            "#,
+            run_id,
+            callback_url,
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Updates the run, enqueueing its callback if its status became terminal.
+    /// `NotFound` if there is no such run.
+    async fn update_run_in(
+        tx: &mut Transaction<'static, Postgres>,
+        run: &Run,
+    ) -> ServiceResult<()> {
+        let row = sqlx::query!(
+            r#"
+            update run set status_id = $1,
+                           run_successful_responses = $2,
+                           run_value_sum = cast($3::text as numeric),
+                           run_sum_overflowed = $4,
+                           run_effective_seconds = $5,
+                           run_aggregates = $6
+            where run_id = $7
+            returning run_callback_url
+            "#,
+            run.status as i16,
+            run.successful_responses_count as i64,
+            run.sum.to_string(),
+            run.sum_overflowed,
+            run.effective_seconds.map(|s| s as i64),
+            run.aggregates,
+            run.id,
+        )
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+        if run.status.is_terminal() {
+            Self::enqueue_callback(tx, run.id, row.run_callback_url).await?;
+            Self::notify_finished(tx, run.id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn insert_run_stats(
+        tx: &mut Transaction<'static, Postgres>,
+        run_id: RunId,
+        stats: &RunStats,
+    ) -> ServiceResult<()> {
+        let histogram = serde_json::to_value(&stats.histogram)
+            .map_err(|_| ServiceError::InternalServerError)?;
+        sqlx::query!(
+            r#"
+            insert into run_stats (run_id,
+                                   run_stats_count,
+                                   run_stats_min,
+                                   run_stats_max,
+                                   run_stats_mean,
+                                   run_stats_variance,
+                                   run_stats_histogram)
+            values ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            run_id,
+            stats.count as i64,
+            stats.min,
+            stats.max,
+            stats.mean,
+            stats.variance,
+            histogram,
+        )
+        .execute(tx)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Captured responses of the run, in the order of capture, following the given one
+    async fn fetch_responses_page(
+        db_pool: &PgPool,
+        run_id: RunId,
+        after: Option<i64>,
+    ) -> ServiceResult<Vec<(i64, CapturedResponse)>> {
+        let rows = sqlx::query!(
+            r#"
+            select r.run_response_id,
+                   r.run_response_datetime,
+                   r.run_response_latency_us,
+                   r.run_response_slot,
+                   r.run_response_status,
+                   r.run_response_body,
+                   r.run_response_error
+            from run_response r
+            where r.run_id = $1
+              and ($2::bigint is null or r.run_response_id > $2)
+            order by r.run_response_id
+            limit $3;
+            "#,
+            run_id,
+            after,
+            RESPONSES_PAGE_SIZE as i64,
+        )
+        .fetch_all(db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.run_response_id,
+                    CapturedResponse {
+                        at: row.run_response_datetime,
+                        latency_us: row.run_response_latency_us as u64,
+                        slot: row.run_response_slot as usize,
+                        status: row.run_response_status.map(|status| status as u16),
+                        body: row.run_response_body,
+                        error: row.run_response_error,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Runs matching the filter, in the order of creation, following the given one
+    async fn fetch_export_page(
+        db_pool: &PgPool,
+        filter: &RunFilter,
+        after: Option<(DateTime<Utc>, RunId)>,
+    ) -> ServiceResult<Vec<ExportedRun>> {
+        let (after_created_at, after_id) = after.unzip();
+        let rows = sqlx::query!(
+            r#"
+            select r.run_id,
+                   r.run_insertion_datetime at time zone current_setting('TimeZone') as "created_at!",
+                   r.status_id,
+                   r.run_successful_responses,
+                   r.run_value_sum::text as "run_value_sum!",
+                   r.run_sum_overflowed,
+                   r.run_requested_seconds,
+                   r.run_effective_seconds,
+                   r.run_rerun_of,
+                   r.run_concurrent_requests,
+                   r.run_polling_address,
+                   r.run_target_id,
+                   r.run_aggregates
+            from run r
+            where ($1::smallint is null or r.status_id = $1)
+              and ($2::uuid is null or r.run_target_id = $2)
+              and ($3::timestamptz is null
+                or r.run_insertion_datetime >= $3 at time zone current_setting('TimeZone'))
+              and ($4::timestamptz is null
+                or r.run_insertion_datetime < $4 at time zone current_setting('TimeZone'))
+              and ($5::timestamptz is null
+                or (r.run_insertion_datetime, r.run_id)
+                       > ($5 at time zone current_setting('TimeZone'), $6::uuid))
+            order by r.run_insertion_datetime, r.run_id
+            limit $7;
+            "#,
+            filter.status.map(|status| status as i16),
+            filter.target_id,
+            filter.from,
+            filter.to,
+            after_created_at,
+            after_id,
+            EXPORT_PAGE_SIZE as i64,
+        )
+        .fetch_all(db_pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ExportedRun {
+                    created_at: row.created_at,
+                    run: Run {
+                        id: row.run_id,
+                        status: row.status_id.try_into()?,
+                        successful_responses_count: row.run_successful_responses as u64,
+                        sum: row
+                            .run_value_sum
+                            .parse()
+                            .expect("Failed to parse sum of values"),
+                        sum_overflowed: row.run_sum_overflowed,
+                        requested_seconds: row.run_requested_seconds.map(|s| s as u64),
+                        effective_seconds: row.run_effective_seconds.map(|s| s as u64),
+                        rerun_of: row.run_rerun_of,
+                        concurrent_requests: row.run_concurrent_requests.map(|c| c as usize),
+                        polling_address: row.run_polling_address,
+                        target_id: row.run_target_id,
+                        aggregates: row.run_aggregates,
+                    },
+                })
+            })
+            .collect()
+    }
 }
 
 #[async_trait]
@@ -25,12 +295,33 @@ impl RunRepository for PostgresRunRepository {
     }
 
     async fn save_run(&self, run: &NewRun) -> ServiceResult<()> {
+        let spec =
+            serde_json::to_value(&run.spec).map_err(|_| ServiceError::InternalServerError)?;
+
         sqlx::query!(
             r#"
-            insert into run (run_id)
-            values ($1)
+            insert into run (run_id,
+                             status_id,
+                             run_start_datetime,
+                             run_requested_seconds,
+                             run_spec,
+                             run_rerun_of,
+                             run_concurrent_requests,
+                             run_polling_address,
+                             run_target_id,
+                             run_callback_url)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
             "#,
-            run.id
+            run.id,
+            run.status as i16,
+            run.start_at,
+            run.spec.seconds as i64,
+            spec,
+            run.rerun_of,
+            run.spec.concurrent_requests.map(|c| c as i32),
+            run.spec.polling_address,
+            run.spec.target_id,
+            run.spec.callback_url,
         )
         .execute(&self.db_pool)
         .await?;
@@ -39,27 +330,36 @@ impl RunRepository for PostgresRunRepository {
     }
 
     async fn update_run(&self, run: &Run) -> ServiceResult<()> {
-        let query_result = sqlx::query!(
+        let mut tx = self.db_pool.begin().await?;
+        Self::update_run_in(&mut tx, run).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn update_run_status(&self, run_id: RunId, status: RunStatus) -> ServiceResult<()> {
+        let mut tx = self.db_pool.begin().await?;
+        let row = sqlx::query!(
             r#"
-            update run set status_id = $1,
-                           run_successful_responses = $2,
-                           run_value_sum = $3
-            where run_id = $4
+            update run set status_id = $1
+            where run_id = $2
+              and status_id in ($3, $4, $5)
+            returning run_callback_url
             "#,
-            run.status as i16,
-            run.successful_responses_count as i64,
-            run.sum as i64,
-            run.id,
+            status as i16,
+            run_id,
+            RunStatus::Scheduled as i16,
+            RunStatus::InProgress as i16,
+            RunStatus::Paused as i16,
         )
-        .execute(&self.db_pool)
+        .fetch_optional(&mut tx)
         .await?;
 
-        assert_eq!(
-            1,
-            query_result.rows_affected(),
-            "DAO method 'finish_run' updated 0 rows, tried to finish run with id: {}",
-            run.id
-        );
+        if let Some(row) = row.filter(|_| status.is_terminal()) {
+            Self::enqueue_callback(&mut tx, run_id, row.run_callback_url).await?;
+            Self::notify_finished(&mut tx, run_id).await?;
+        }
+        tx.commit().await?;
 
         Ok(())
     }
@@ -69,7 +369,15 @@ impl RunRepository for PostgresRunRepository {
             r#"
             select r.status_id,
                    r.run_successful_responses,
-                   r.run_value_sum
+                   r.run_value_sum::text as "run_value_sum!",
+                   r.run_sum_overflowed,
+                   r.run_requested_seconds,
+                   r.run_effective_seconds,
+                   r.run_rerun_of,
+                   r.run_concurrent_requests,
+                   r.run_polling_address,
+                   r.run_target_id,
+                   r.run_aggregates
             from run r
             where r.run_id = $1;
             "#,
@@ -82,7 +390,225 @@ impl RunRepository for PostgresRunRepository {
             id: run_id,
             status: row.status_id.try_into()?,
             successful_responses_count: row.run_successful_responses as u64,
-            sum: row.run_value_sum as u64,
+            sum: row
+                .run_value_sum
+                .parse()
+                .expect("Failed to parse sum of values"),
+            sum_overflowed: row.run_sum_overflowed,
+            requested_seconds: row.run_requested_seconds.map(|s| s as u64),
+            effective_seconds: row.run_effective_seconds.map(|s| s as u64),
+            rerun_of: row.run_rerun_of,
+            concurrent_requests: row.run_concurrent_requests.map(|c| c as usize),
+            polling_address: row.run_polling_address,
+            target_id: row.run_target_id,
+            aggregates: row.run_aggregates,
+        })
+    }
+
+    async fn get_run_spec(&self, run_id: RunId) -> ServiceResult<Option<StartRunRequestDto>> {
+        let row = sqlx::query!(
+            r#"
+            select r.run_spec
+            from run r
+            where r.run_id = $1;
+            "#,
+            run_id
+        )
+        .fetch_one(&self.db_pool)
+        .await?;
+
+        row.run_spec
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    async fn finish_run(&self, run: &Run, stats: &RunStats) -> ServiceResult<()> {
+        let mut tx = self.db_pool.begin().await?;
+        Self::update_run_in(&mut tx, run).await?;
+        Self::insert_run_stats(&mut tx, run.id, stats).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_run_stats(&self, run_id: RunId) -> ServiceResult<Option<RunStats>> {
+        let row = sqlx::query!(
+            r#"
+            select s.run_stats_count,
+                   s.run_stats_min,
+                   s.run_stats_max,
+                   s.run_stats_mean,
+                   s.run_stats_variance,
+                   s.run_stats_histogram
+            from run_stats s
+            where s.run_id = $1;
+            "#,
+            run_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?;
+
+        row.map(|row| {
+            Ok(RunStats {
+                count: row.run_stats_count as u64,
+                min: row.run_stats_min,
+                max: row.run_stats_max,
+                mean: row.run_stats_mean,
+                variance: row.run_stats_variance,
+                histogram: serde_json::from_value(row.run_stats_histogram)
+                    .map_err(|_| ServiceError::InternalServerError)?,
+            })
+        })
+        .transpose()
+    }
+
+    async fn save_run_sample(&self, run_id: RunId, sample: &RunSample) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            insert into run_sample (run_id,
+                                    run_sample_datetime,
+                                    run_sample_attempts,
+                                    run_sample_successes,
+                                    run_sample_errors,
+                                    run_sample_value_sum)
+            values ($1, $2, $3, $4, $5, cast($6::text as numeric))
+            "#,
+            run_id,
+            sample.at,
+            sample.attempts as i64,
+            sample.successes as i64,
+            sample.errors as i64,
+            sample.sum.to_string(),
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_run_samples(&self, run_id: RunId) -> ServiceResult<Vec<RunSample>> {
+        let rows = sqlx::query!(
+            r#"
+            select s.run_sample_datetime,
+                   s.run_sample_attempts,
+                   s.run_sample_successes,
+                   s.run_sample_errors,
+                   s.run_sample_value_sum::text as "run_sample_value_sum!"
+            from run_sample s
+            where s.run_id = $1
+            order by s.run_sample_datetime;
+            "#,
+            run_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| RunSample {
+                at: row.run_sample_datetime,
+                attempts: row.run_sample_attempts as u64,
+                successes: row.run_sample_successes as u64,
+                errors: row.run_sample_errors as u64,
+                sum: row
+                    .run_sample_value_sum
+                    .parse()
+                    .expect("Failed to parse sum of values"),
+            })
+            .collect())
+    }
+
+    async fn save_run_responses(
+        &self,
+        run_id: RunId,
+        responses: &[CapturedResponse],
+    ) -> ServiceResult<()> {
+        let at: Vec<_> = responses.iter().map(|r| r.at).collect();
+        let latency_us: Vec<_> = responses.iter().map(|r| r.latency_us as i64).collect();
+        let slot: Vec<_> = responses.iter().map(|r| r.slot as i32).collect();
+        let status: Vec<_> = responses
+            .iter()
+            .map(|r| r.status.map(|status| status as i16))
+            .collect();
+        let body: Vec<_> = responses.iter().map(|r| r.body.clone().map(Json)).collect();
+        let error: Vec<_> = responses.iter().map(|r| r.error.clone()).collect();
+
+        // one statement for the whole batch
+        sqlx::query!(
+            r#"
+            insert into run_response (run_id,
+                                      run_response_datetime,
+                                      run_response_latency_us,
+                                      run_response_slot,
+                                      run_response_status,
+                                      run_response_body,
+                                      run_response_error)
+            select $1, *
+            from unnest($2::timestamptz[], $3::bigint[], $4::integer[], $5::smallint[],
+                        $6::jsonb[], $7::text[])
+            "#,
+            run_id,
+            &at,
+            &latency_us,
+            &slot,
+            &status as _,
+            &body as _,
+            &error as _,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn get_run_responses(
+        &self,
+        run_id: RunId,
+    ) -> BoxStream<'static, ServiceResult<CapturedResponse>> {
+        let db_pool = self.db_pool.clone();
+        // one page is held at a time, the next one continues after its last response
+        stream::unfold(Some(None), move |after| {
+            let db_pool = db_pool.clone();
+            async move {
+                let after = after?;
+                match Self::fetch_responses_page(&db_pool, run_id, after).await {
+                    Ok(responses) => {
+                        let next = responses
+                            .last()
+                            .filter(|_| responses.len() == RESPONSES_PAGE_SIZE)
+                            .map(|(id, _)| Some(*id));
+                        let responses = responses.into_iter().map(|(_, response)| Ok(response));
+                        Some((responses.collect::<Vec<_>>(), next))
+                    }
+                    Err(e) => Some((vec![Err(e)], None)),
+                }
+            }
+        })
+        .flat_map(stream::iter)
+        .boxed()
+    }
+
+    fn export_runs(&self, filter: RunFilter) -> BoxStream<'static, ServiceResult<ExportedRun>> {
+        let db_pool = self.db_pool.clone();
+        // one page is held at a time, the next one continues after its last run
+        stream::unfold(Some(None), move |after| {
+            let (db_pool, filter) = (db_pool.clone(), filter.clone());
+            async move {
+                let after = after?;
+                match Self::fetch_export_page(&db_pool, &filter, after).await {
+                    Ok(runs) => {
+                        let next = runs
+                            .last()
+                            .filter(|_| runs.len() == EXPORT_PAGE_SIZE)
+                            .map(|last| Some((last.created_at, last.run.id)));
+                        Some((runs.into_iter().map(Ok).collect::<Vec<_>>(), next))
+                    }
+                    Err(e) => Some((vec![Err(e)], None)),
+                }
+            }
         })
+        .flat_map(stream::iter)
+        .boxed()
     }
 }
@@ -0,0 +1,133 @@
+use actix_web::{guard, web, HttpResponse, Responder};
+
+use crate::polling::errors::ServiceResult;
+use crate::scheduling::dto::{CreateScheduleRequestDto, ScheduleId};
+use crate::scheduling::scheduling_service::SchedulingService;
+
+async fn create_schedule<T: SchedulingService>(
+    service: web::Data<T>,
+    request_payload: web::Json<CreateScheduleRequestDto>,
+) -> ServiceResult<impl Responder> {
+    service
+        .create_schedule(request_payload.into_inner())
+        .await
+        .map(web::Json)
+}
+
+async fn get_schedule<T: SchedulingService>(
+    service: web::Data<T>,
+    id: web::Path<ScheduleId>,
+) -> ServiceResult<impl Responder> {
+    service.get_schedule(id.into_inner()).await.map(web::Json)
+}
+
+async fn delete_schedule<T: SchedulingService>(
+    service: web::Data<T>,
+    id: web::Path<ScheduleId>,
+) -> ServiceResult<impl Responder> {
+    service
+        .delete_schedule(id.into_inner())
+        .await
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+pub fn configure<T: 'static + SchedulingService>(
+    service: web::Data<T>,
+    cfg: &mut web::ServiceConfig,
+) {
+    cfg.app_data(service);
+    cfg.route(
+        "/schedules",
+        web::post()
+            .guard(guard::Header("Content-Type", "application/json"))
+            .to(create_schedule::<T>),
+    );
+    cfg.route("/schedules/{id}", web::get().to(get_schedule::<T>));
+    cfg.route("/schedules/{id}", web::delete().to(delete_schedule::<T>));
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::{RunPriority, StartRunRequestDto};
+    use crate::polling::errors::ServiceError;
+    use crate::scheduling::dto::CreateScheduleResponseDto;
+    use crate::scheduling::scheduling_service::MockSchedulingService;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use mockall::predicate::*;
+
+    #[actix_rt::test]
+    async fn create_new_schedule() {
+        let request_payload = CreateScheduleRequestDto {
+            cron: Some("0 * * * *".into()),
+            interval_seconds: None,
+            run: StartRunRequestDto {
+                seconds: 30,
+                priority: RunPriority::Normal,
+                max_queue_wait_seconds: None,
+                start_at: None,
+                concurrent_requests: None,
+                polling_address: None,
+                target_id: None,
+                histogram_buckets: None,
+                aggregators: Vec::new(),
+                capture: None,
+                callback_url: None,
+            },
+        };
+        let expected_response = CreateScheduleResponseDto {
+            id: ScheduleId::new_v4(),
+            next_fire_at: chrono::Utc::now(),
+        };
+
+        let scheduling_service = {
+            let mut ss = MockSchedulingService::new();
+            ss.expect_create_schedule()
+                .with(eq(request_payload.clone()))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ss)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(scheduling_service, cfg)))
+                .await;
+
+        let request = test::TestRequest::post()
+            .uri("/schedules")
+            .set_json(&request_payload)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: CreateScheduleResponseDto = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn reply_not_found_when_deleting_unknown_schedule() {
+        let schedule_id = ScheduleId::new_v4();
+
+        let scheduling_service = {
+            let mut ss = MockSchedulingService::new();
+            ss.expect_delete_schedule()
+                .with(eq(schedule_id))
+                .return_const(Err(ServiceError::NotFound));
+            web::Data::new(ss)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(scheduling_service, cfg)))
+                .await;
+
+        let request = test::TestRequest::delete()
+            .uri(&format!("/schedules/{}", schedule_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+}
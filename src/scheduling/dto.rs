@@ -0,0 +1,138 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, Utc};
+use uuid::Uuid;
+
+use crate::polling::dto::{RunId, StartRunRequestDto};
+use crate::polling::errors::{ServiceError, ServiceResult};
+
+pub type ScheduleId = Uuid;
+
+/// Longest interval between firings, a leap year
+pub const MAX_INTERVAL_SECONDS: u64 = 366 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CreateScheduleRequestDto {
+    /// Cron expression, with or without the leading seconds field
+    #[serde(default)]
+    pub cron: Option<String>,
+    #[serde(default)]
+    pub interval_seconds: Option<u64>,
+    pub run: StartRunRequestDto,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct CreateScheduleResponseDto {
+    pub id: ScheduleId,
+    pub next_fire_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Schedule {
+    pub id: ScheduleId,
+    pub cron: Option<String>,
+    pub interval_seconds: Option<u64>,
+    pub run: StartRunRequestDto,
+    pub next_fire_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ScheduleFiring {
+    pub fired_at: DateTime<Utc>,
+    /// Not set if the run could not be started, e.g. because the queue was full
+    pub run_id: Option<RunId>,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ScheduleInfo {
+    #[serde(flatten)]
+    pub schedule: Schedule,
+    pub runs: Vec<RunId>,
+    pub missed_firings: Vec<DateTime<Utc>>,
+}
+
+pub enum ScheduleTrigger {
+    Cron(Box<cron::Schedule>),
+    Interval(Duration),
+}
+
+impl ScheduleTrigger {
+    pub fn new(cron: Option<&str>, interval_seconds: Option<u64>) -> ServiceResult<Self> {
+        match (cron, interval_seconds) {
+            (Some(cron), None) => Self::parse_cron(cron),
+            (None, Some(seconds)) if seconds > 0 && seconds <= MAX_INTERVAL_SECONDS => {
+                Ok(Self::Interval(Duration::seconds(seconds as i64)))
+            }
+            (None, Some(_)) => Err(ServiceError::BadRequest(format!(
+                "Schedule interval must be positive and at most {} seconds",
+                MAX_INTERVAL_SECONDS
+            ))),
+            _ => Err(ServiceError::BadRequest(
+                "Schedule must have either a cron expression or an interval".into(),
+            )),
+        }
+    }
+
+    fn parse_cron(cron: &str) -> ServiceResult<Self> {
+        let cron = match cron.split_whitespace().count() {
+            5 => format!("0 {}", cron),
+            _ => cron.to_string(),
+        };
+        cron::Schedule::from_str(&cron)
+            .map(|schedule| Self::Cron(Box::new(schedule)))
+            .map_err(|e| ServiceError::BadRequest(format!("Invalid cron expression: {}", e)))
+    }
+
+    /// Firing times strictly after `after` and no later than `now`, the ones `next_fire_at` skips
+    pub fn skipped_firings<'a>(
+        &'a self,
+        after: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> Box<dyn Iterator<Item = DateTime<Utc>> + 'a> {
+        match self {
+            Self::Cron(schedule) => Box::new(
+                schedule
+                    .after(&after)
+                    .take_while(move |fire_at| *fire_at <= now),
+            ),
+            Self::Interval(interval) => {
+                let interval = *interval;
+                Box::new(
+                    std::iter::successors(Some(after + interval), move |fire_at| {
+                        Some(*fire_at + interval)
+                    })
+                    .take_while(move |fire_at| *fire_at <= now),
+                )
+            }
+        }
+    }
+
+    /// First firing time strictly after `after`. Firings already in the past relative to `now`
+    /// are skipped, so a long outage results in a single firing and `skipped_firings`.
+    pub fn next_fire_at(
+        &self,
+        after: DateTime<Utc>,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<DateTime<Utc>> {
+        match self {
+            Self::Cron(schedule) => schedule
+                .after(&after.max(now))
+                .next()
+                .ok_or_else(|| ServiceError::BadRequest("Cron expression never fires".into())),
+            Self::Interval(interval) => {
+                let mut next = after + *interval;
+                if next <= now {
+                    let skipped = (now - next).num_seconds() / interval.num_seconds() + 1;
+                    next = next + Duration::seconds(skipped * interval.num_seconds());
+                }
+                Ok(next)
+            }
+        }
+    }
+}
+
+impl Schedule {
+    pub fn trigger(&self) -> ServiceResult<ScheduleTrigger> {
+        ScheduleTrigger::new(self.cron.as_deref(), self.interval_seconds)
+    }
+}
@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod dto;
+pub mod schedule_repository;
+pub mod scheduler;
+pub mod scheduling_service;
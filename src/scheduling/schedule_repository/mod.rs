@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+#[cfg(test)]
+use mockall::mock;
+
+use crate::polling::errors::ServiceResult;
+use crate::scheduling::dto::{Schedule, ScheduleFiring, ScheduleId};
+
+mod postgres_schedule_repository;
+pub use postgres_schedule_repository::PostgresScheduleRepository;
+
+#[async_trait]
+pub trait ScheduleRepository: Clone + Send + Sync {
+    async fn generate_schedule_id(&self) -> ScheduleId;
+    async fn save_schedule(&self, schedule: &Schedule) -> ServiceResult<()>;
+    async fn get_schedule_by_id(&self, schedule_id: ScheduleId) -> ServiceResult<Schedule>;
+    async fn delete_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<()>;
+    async fn get_due_schedules(&self, now: DateTime<Utc>) -> ServiceResult<Vec<Schedule>>;
+    /// Moves the schedule to its next firing time, returns false if another poller got there first
+    async fn claim_firing(
+        &self,
+        schedule_id: ScheduleId,
+        fire_at: DateTime<Utc>,
+        next_fire_at: DateTime<Utc>,
+    ) -> ServiceResult<bool>;
+    async fn save_firing(
+        &self,
+        schedule_id: ScheduleId,
+        firing: &ScheduleFiring,
+    ) -> ServiceResult<()>;
+    /// Saves firings that started no run, all at once
+    async fn save_missed_firings(
+        &self,
+        schedule_id: ScheduleId,
+        fired_at: &[DateTime<Utc>],
+    ) -> ServiceResult<()>;
+    async fn get_firings(&self, schedule_id: ScheduleId) -> ServiceResult<Vec<ScheduleFiring>>;
+}
+
+#[cfg(test)]
+mock! {
+    pub ScheduleRepository {}
+
+    impl Clone for ScheduleRepository {
+        fn clone(&self) -> Self;
+    }
+
+    #[async_trait]
+    impl ScheduleRepository for ScheduleRepository {
+        async fn generate_schedule_id(&self) -> ScheduleId;
+        async fn save_schedule(&self, schedule: &Schedule) -> ServiceResult<()>;
+        async fn get_schedule_by_id(&self, schedule_id: ScheduleId) -> ServiceResult<Schedule>;
+        async fn delete_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<()>;
+        async fn get_due_schedules(&self, now: DateTime<Utc>) -> ServiceResult<Vec<Schedule>>;
+        async fn claim_firing(
+            &self,
+            schedule_id: ScheduleId,
+            fire_at: DateTime<Utc>,
+            next_fire_at: DateTime<Utc>,
+        ) -> ServiceResult<bool>;
+        async fn save_firing(
+            &self,
+            schedule_id: ScheduleId,
+            firing: &ScheduleFiring,
+        ) -> ServiceResult<()>;
+        async fn save_missed_firings(
+            &self,
+            schedule_id: ScheduleId,
+            fired_at: &[DateTime<Utc>],
+        ) -> ServiceResult<()>;
+        async fn get_firings(&self, schedule_id: ScheduleId) -> ServiceResult<Vec<ScheduleFiring>>;
+    }
+}
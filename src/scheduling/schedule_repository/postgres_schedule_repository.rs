@@ -0,0 +1,208 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::scheduling::dto::{Schedule, ScheduleFiring, ScheduleId};
+use crate::scheduling::schedule_repository::ScheduleRepository;
+
+#[derive(Clone, Debug)]
+pub struct PostgresScheduleRepository {
+    db_pool: PgPool,
+}
+
+impl PostgresScheduleRepository {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+}
+
+#[async_trait]
+impl ScheduleRepository for PostgresScheduleRepository {
+    async fn generate_schedule_id(&self) -> ScheduleId {
+        ScheduleId::new_v4()
+    }
+
+    async fn save_schedule(&self, schedule: &Schedule) -> ServiceResult<()> {
+        let run_spec =
+            serde_json::to_value(&schedule.run).map_err(|_| ServiceError::InternalServerError)?;
+
+        sqlx::query!(
+            r#"
+            insert into schedule (schedule_id,
+                                  schedule_cron,
+                                  schedule_interval_seconds,
+                                  schedule_run_spec,
+                                  schedule_next_fire_datetime)
+            values ($1, $2, $3, $4, $5)
+            "#,
+            schedule.id,
+            schedule.cron,
+            schedule.interval_seconds.map(|s| s as i64),
+            run_spec,
+            schedule.next_fire_at,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_schedule_by_id(&self, schedule_id: ScheduleId) -> ServiceResult<Schedule> {
+        let row = sqlx::query!(
+            r#"
+            select s.schedule_cron,
+                   s.schedule_interval_seconds,
+                   s.schedule_run_spec,
+                   s.schedule_next_fire_datetime
+            from schedule s
+            where s.schedule_id = $1;
+            "#,
+            schedule_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+        Ok(Schedule {
+            id: schedule_id,
+            cron: row.schedule_cron,
+            interval_seconds: row.schedule_interval_seconds.map(|s| s as u64),
+            run: serde_json::from_value(row.schedule_run_spec)
+                .map_err(|_| ServiceError::InternalServerError)?,
+            next_fire_at: row.schedule_next_fire_datetime,
+        })
+    }
+
+    async fn delete_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<()> {
+        let query_result = sqlx::query!(
+            r#"
+            delete from schedule
+            where schedule_id = $1
+            "#,
+            schedule_id
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        match query_result.rows_affected() {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_due_schedules(&self, now: DateTime<Utc>) -> ServiceResult<Vec<Schedule>> {
+        let rows = sqlx::query!(
+            r#"
+            select s.schedule_id,
+                   s.schedule_cron,
+                   s.schedule_interval_seconds,
+                   s.schedule_run_spec,
+                   s.schedule_next_fire_datetime
+            from schedule s
+            where s.schedule_next_fire_datetime <= $1
+            order by s.schedule_next_fire_datetime;
+            "#,
+            now
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Schedule {
+                    id: row.schedule_id,
+                    cron: row.schedule_cron,
+                    interval_seconds: row.schedule_interval_seconds.map(|s| s as u64),
+                    run: serde_json::from_value(row.schedule_run_spec)
+                        .map_err(|_| ServiceError::InternalServerError)?,
+                    next_fire_at: row.schedule_next_fire_datetime,
+                })
+            })
+            .collect()
+    }
+
+    async fn claim_firing(
+        &self,
+        schedule_id: ScheduleId,
+        fire_at: DateTime<Utc>,
+        next_fire_at: DateTime<Utc>,
+    ) -> ServiceResult<bool> {
+        let query_result = sqlx::query!(
+            r#"
+            update schedule set schedule_next_fire_datetime = $1
+            where schedule_id = $2
+              and schedule_next_fire_datetime = $3
+            "#,
+            next_fire_at,
+            schedule_id,
+            fire_at,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(query_result.rows_affected() == 1)
+    }
+
+    async fn save_firing(
+        &self,
+        schedule_id: ScheduleId,
+        firing: &ScheduleFiring,
+    ) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            insert into schedule_firing (schedule_id, firing_datetime, run_id)
+            values ($1, $2, $3)
+            "#,
+            schedule_id,
+            firing.fired_at,
+            firing.run_id,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn save_missed_firings(
+        &self,
+        schedule_id: ScheduleId,
+        fired_at: &[DateTime<Utc>],
+    ) -> ServiceResult<()> {
+        sqlx::query!(
+            r#"
+            insert into schedule_firing (schedule_id, firing_datetime)
+            select $1, * from unnest($2::timestamptz[])
+            "#,
+            schedule_id,
+            fired_at,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn get_firings(&self, schedule_id: ScheduleId) -> ServiceResult<Vec<ScheduleFiring>> {
+        let rows = sqlx::query!(
+            r#"
+            select f.firing_datetime,
+                   f.run_id
+            from schedule_firing f
+            where f.schedule_id = $1
+            order by f.firing_datetime;
+            "#,
+            schedule_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| ScheduleFiring {
+                fired_at: row.firing_datetime,
+                run_id: row.run_id,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+
+use crate::configuration::settings::SchedulingSettings;
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::polling::polling_service::PollingService;
+use crate::scheduling::dto::{Schedule, ScheduleFiring, ScheduleTrigger};
+use crate::scheduling::schedule_repository::ScheduleRepository;
+
+/// Firings skipped during an outage that are recorded as missed, the older ones are only logged
+const MAX_RECORDED_SKIPPED_FIRINGS: usize = 1000;
+
+/// Periodically starts runs of due schedules through the polling service.
+///
+/// A schedule that was due several times while no poller was running starts a single run;
+/// its other firings are recorded as missed. A firing is also missed when the queue is full,
+/// other failures to start its run are only logged.
+pub struct Scheduler<R, P> {
+    schedule_repo: R,
+    polling_service: P,
+    settings: SchedulingSettings,
+}
+
+impl<R, P> Scheduler<R, P>
+where
+    R: ScheduleRepository,
+    P: PollingService,
+{
+    pub fn new(schedule_repo: R, polling_service: P, settings: SchedulingSettings) -> Self {
+        Self {
+            schedule_repo,
+            polling_service,
+            settings,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            self.settings.check_interval_seconds,
+        ));
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.fire_due_schedules(Utc::now()).await {
+                log::error!("Failed to fire due schedules: {}", e);
+            }
+        }
+    }
+
+    pub async fn fire_due_schedules(&self, now: DateTime<Utc>) -> ServiceResult<()> {
+        for schedule in self.schedule_repo.get_due_schedules(now).await? {
+            let id = schedule.id;
+            if let Err(e) = self.fire(schedule, now).await {
+                log::error!("Failed to fire schedule {}: {}", id, e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn fire(&self, schedule: Schedule, now: DateTime<Utc>) -> ServiceResult<()> {
+        let trigger = schedule.trigger()?;
+        let next_fire_at = trigger.next_fire_at(schedule.next_fire_at, now)?;
+
+        let claimed = self
+            .schedule_repo
+            .claim_firing(schedule.id, schedule.next_fire_at, next_fire_at)
+            .await?;
+        if !claimed {
+            return Ok(());
+        }
+
+        self.record_skipped_firings(&schedule, &trigger, now)
+            .await?;
+
+        let run_id = match self.polling_service.start_run(schedule.run).await {
+            Ok(response) => Some(response.id),
+            Err(e @ ServiceError::TooManyRequests { .. }) => {
+                log::warn!("Schedule {} missed its firing: {}", schedule.id, e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        self.schedule_repo
+            .save_firing(
+                schedule.id,
+                &ScheduleFiring {
+                    fired_at: schedule.next_fire_at,
+                    run_id,
+                },
+            )
+            .await
+    }
+
+    async fn record_skipped_firings(
+        &self,
+        schedule: &Schedule,
+        trigger: &ScheduleTrigger,
+        now: DateTime<Utc>,
+    ) -> ServiceResult<()> {
+        // the latest ones are kept, a short interval may have fired countless times
+        let mut skipped = VecDeque::with_capacity(MAX_RECORDED_SKIPPED_FIRINGS);
+        let mut count = 0u64;
+        for fired_at in trigger.skipped_firings(schedule.next_fire_at, now) {
+            if skipped.len() == MAX_RECORDED_SKIPPED_FIRINGS {
+                skipped.pop_front();
+            }
+            skipped.push_back(fired_at);
+            count += 1;
+        }
+        if count == 0 {
+            return Ok(());
+        }
+        log::warn!(
+            "Schedule {} skipped {} firings while no poller was running",
+            schedule.id,
+            count
+        );
+
+        self.schedule_repo
+            .save_missed_firings(schedule.id, skipped.make_contiguous())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::{RunId, RunPriority, StartRunRequestDto, StartRunResponseDto};
+    use crate::polling::polling_service::MockPollingService;
+    use crate::scheduling::dto::ScheduleId;
+    use crate::scheduling::schedule_repository::MockScheduleRepository;
+    use mockall::predicate::eq;
+
+    fn due_schedule(now: DateTime<Utc>) -> Schedule {
+        Schedule {
+            id: ScheduleId::new_v4(),
+            cron: None,
+            interval_seconds: Some(60),
+            run: StartRunRequestDto {
+                seconds: 30,
+                priority: RunPriority::Normal,
+                max_queue_wait_seconds: None,
+                start_at: None,
+                concurrent_requests: None,
+                polling_address: None,
+                target_id: None,
+                histogram_buckets: None,
+                aggregators: Vec::new(),
+                capture: None,
+                callback_url: None,
+            },
+            next_fire_at: now - chrono::Duration::seconds(1),
+        }
+    }
+
+    fn settings() -> SchedulingSettings {
+        SchedulingSettings {
+            check_interval_seconds: 1,
+        }
+    }
+
+    fn schedule_repo(
+        schedule: &Schedule,
+        expected_run_id: Option<RunId>,
+    ) -> MockScheduleRepository {
+        let mut r = MockScheduleRepository::new();
+        r.expect_get_due_schedules()
+            .return_const(Ok(vec![schedule.clone()]));
+        r.expect_claim_firing()
+            .with(
+                eq(schedule.id),
+                eq(schedule.next_fire_at),
+                eq(schedule.next_fire_at + chrono::Duration::seconds(60)),
+            )
+            .return_const(Ok(true));
+        r.expect_save_firing()
+            .with(
+                eq(schedule.id),
+                eq(ScheduleFiring {
+                    fired_at: schedule.next_fire_at,
+                    run_id: expected_run_id,
+                }),
+            )
+            .times(1)
+            .return_const(Ok(()));
+        r
+    }
+
+    #[actix_rt::test]
+    async fn start_run_of_due_schedule_and_link_it() {
+        let now = Utc::now();
+        let schedule = due_schedule(now);
+        let run_id = RunId::new_v4();
+
+        let schedule_repo = schedule_repo(&schedule, Some(run_id));
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_start_run()
+                .with(eq(schedule.run.clone()))
+                .return_const(Ok(StartRunResponseDto {
+                    id: run_id,
+                    estimated_start_at: now,
+                }));
+            ps
+        };
+
+        let scheduler = Scheduler::new(schedule_repo, polling_service, settings());
+
+        assert_eq!(Ok(()), scheduler.fire_due_schedules(now).await);
+    }
+
+    #[actix_rt::test]
+    async fn record_missed_firing_when_queue_is_full() {
+        let now = Utc::now();
+        let schedule = due_schedule(now);
+
+        let schedule_repo = schedule_repo(&schedule, None);
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_start_run()
+                .return_const(Err(ServiceError::TooManyRequests { retry_at: None }));
+            ps
+        };
+
+        let scheduler = Scheduler::new(schedule_repo, polling_service, settings());
+
+        assert_eq!(Ok(()), scheduler.fire_due_schedules(now).await);
+    }
+
+    #[actix_rt::test]
+    async fn not_record_firing_when_run_fails_to_start() {
+        let now = Utc::now();
+        let schedule = due_schedule(now);
+
+        let schedule_repo = {
+            let mut r = MockScheduleRepository::new();
+            r.expect_get_due_schedules()
+                .return_const(Ok(vec![schedule.clone()]));
+            r.expect_claim_firing().return_const(Ok(true));
+            r.expect_save_firing().never();
+            r
+        };
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_start_run()
+                .return_const(Err(ServiceError::BadRequest("Unknown target".into())));
+            ps
+        };
+
+        let scheduler = Scheduler::new(schedule_repo, polling_service, settings());
+
+        assert_eq!(Ok(()), scheduler.fire_due_schedules(now).await);
+    }
+
+    fn run_started(now: DateTime<Utc>) -> MockPollingService {
+        let mut ps = MockPollingService::new();
+        ps.expect_start_run()
+            .times(1)
+            .return_const(Ok(StartRunResponseDto {
+                id: RunId::new_v4(),
+                estimated_start_at: now,
+            }));
+        ps
+    }
+
+    #[actix_rt::test]
+    async fn record_firings_skipped_while_down_as_missed() {
+        let now = Utc::now();
+        let schedule = Schedule {
+            next_fire_at: now - chrono::Duration::seconds(150),
+            ..due_schedule(now)
+        };
+        let (id, fire_at) = (schedule.id, schedule.next_fire_at);
+        let minutes_later = |minutes| fire_at + chrono::Duration::minutes(minutes);
+
+        let schedule_repo = {
+            let mut r = MockScheduleRepository::new();
+            r.expect_get_due_schedules()
+                .return_const(Ok(vec![schedule.clone()]));
+            r.expect_claim_firing()
+                .with(
+                    eq(schedule.id),
+                    eq(schedule.next_fire_at),
+                    eq(minutes_later(3)),
+                )
+                .return_const(Ok(true));
+            let skipped = vec![minutes_later(1), minutes_later(2)];
+            r.expect_save_missed_firings()
+                .withf(move |s, fired_at| *s == id && fired_at == skipped.as_slice())
+                .times(1)
+                .return_const(Ok(()));
+            r.expect_save_firing()
+                .withf(move |_, firing| firing.fired_at == fire_at)
+                .times(1)
+                .return_const(Ok(()));
+            r
+        };
+
+        let scheduler = Scheduler::new(schedule_repo, run_started(now), settings());
+
+        assert_eq!(Ok(()), scheduler.fire_due_schedules(now).await);
+    }
+
+    #[actix_rt::test]
+    async fn record_only_latest_firings_skipped_while_down() {
+        let now = Utc::now();
+        let schedule = Schedule {
+            interval_seconds: Some(1),
+            next_fire_at: now - chrono::Duration::seconds(5000),
+            ..due_schedule(now)
+        };
+
+        let schedule_repo = {
+            let mut r = MockScheduleRepository::new();
+            r.expect_get_due_schedules()
+                .return_const(Ok(vec![schedule.clone()]));
+            r.expect_claim_firing().return_const(Ok(true));
+            r.expect_save_missed_firings()
+                .withf(move |_, fired_at| {
+                    fired_at.len() == MAX_RECORDED_SKIPPED_FIRINGS && fired_at.last() == Some(&now)
+                })
+                .times(1)
+                .return_const(Ok(()));
+            r.expect_save_firing().return_const(Ok(()));
+            r
+        };
+
+        let scheduler = Scheduler::new(schedule_repo, run_started(now), settings());
+
+        assert_eq!(Ok(()), scheduler.fire_due_schedules(now).await);
+    }
+
+    #[actix_rt::test]
+    async fn skip_schedule_claimed_by_another_poller() {
+        let now = Utc::now();
+        let schedule = due_schedule(now);
+
+        let schedule_repo = {
+            let mut r = MockScheduleRepository::new();
+            r.expect_get_due_schedules()
+                .return_const(Ok(vec![schedule.clone()]));
+            r.expect_claim_firing().return_const(Ok(false));
+            r
+        };
+        let polling_service = {
+            let mut ps = MockPollingService::new();
+            ps.expect_start_run().never();
+            ps
+        };
+
+        let scheduler = Scheduler::new(schedule_repo, polling_service, settings());
+
+        assert_eq!(Ok(()), scheduler.fire_due_schedules(now).await);
+    }
+}
@@ -0,0 +1,21 @@
+use async_trait::async_trait;
+
+pub use scheduling_service_impl::SchedulingServiceImpl;
+
+use crate::polling::errors::ServiceResult;
+use crate::scheduling::dto::{
+    CreateScheduleRequestDto, CreateScheduleResponseDto, ScheduleId, ScheduleInfo,
+};
+
+mod scheduling_service_impl;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait(? Send)]
+pub trait SchedulingService {
+    async fn create_schedule(
+        &self,
+        create_schedule_request_dto: CreateScheduleRequestDto,
+    ) -> ServiceResult<CreateScheduleResponseDto>;
+    async fn get_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<ScheduleInfo>;
+    async fn delete_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<()>;
+}
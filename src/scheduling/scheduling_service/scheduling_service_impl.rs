@@ -0,0 +1,299 @@
+use async_trait::async_trait;
+use chrono::Utc;
+
+use crate::configuration::settings::{PollingSettings, WebhookSettings};
+use crate::polling::dto::RunId;
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::polling::polling_service::job_from_spec;
+use crate::scheduling::dto::{
+    CreateScheduleRequestDto, CreateScheduleResponseDto, Schedule, ScheduleId, ScheduleInfo,
+    ScheduleTrigger,
+};
+use crate::scheduling::schedule_repository::ScheduleRepository;
+use crate::scheduling::scheduling_service::SchedulingService;
+use crate::targets::target_repository::TargetRepository;
+
+#[derive(Clone, Debug)]
+pub struct SchedulingServiceImpl<R, T> {
+    schedule_repo: R,
+    target_repo: T,
+    polling_settings: PollingSettings,
+    webhook_settings: WebhookSettings,
+}
+
+#[async_trait(? Send)]
+impl<R, T> SchedulingService for SchedulingServiceImpl<R, T>
+where
+    R: ScheduleRepository,
+    T: TargetRepository,
+{
+    async fn create_schedule(
+        &self,
+        create_schedule_request_dto: CreateScheduleRequestDto,
+    ) -> ServiceResult<CreateScheduleResponseDto> {
+        if create_schedule_request_dto.run.start_at.is_some() {
+            return Err(ServiceError::BadRequest(
+                "Scheduled runs cannot have a deferred start".into(),
+            ));
+        }
+        // fired runs are checked again when started, as settings and targets may change
+        job_from_spec(
+            RunId::nil(),
+            &create_schedule_request_dto.run,
+            &self.polling_settings,
+            &self.webhook_settings,
+            &self.target_repo,
+        )
+        .await?;
+
+        let now = Utc::now();
+        let trigger = ScheduleTrigger::new(
+            create_schedule_request_dto.cron.as_deref(),
+            create_schedule_request_dto.interval_seconds,
+        )?;
+        let next_fire_at = trigger.next_fire_at(now, now)?;
+
+        let id = self.schedule_repo.generate_schedule_id().await;
+        self.schedule_repo
+            .save_schedule(&Schedule {
+                id,
+                cron: create_schedule_request_dto.cron,
+                interval_seconds: create_schedule_request_dto.interval_seconds,
+                run: create_schedule_request_dto.run,
+                next_fire_at,
+            })
+            .await?;
+
+        Ok(CreateScheduleResponseDto { id, next_fire_at })
+    }
+
+    async fn get_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<ScheduleInfo> {
+        let schedule = self.schedule_repo.get_schedule_by_id(schedule_id).await?;
+        let firings = self.schedule_repo.get_firings(schedule_id).await?;
+
+        let runs = firings.iter().filter_map(|f| f.run_id).collect();
+        let missed_firings = firings
+            .iter()
+            .filter(|f| f.run_id.is_none())
+            .map(|f| f.fired_at)
+            .collect();
+
+        Ok(ScheduleInfo {
+            schedule,
+            runs,
+            missed_firings,
+        })
+    }
+
+    async fn delete_schedule(&self, schedule_id: ScheduleId) -> ServiceResult<()> {
+        self.schedule_repo.delete_schedule(schedule_id).await
+    }
+}
+
+impl<R, T> SchedulingServiceImpl<R, T>
+where
+    R: ScheduleRepository,
+    T: TargetRepository,
+{
+    pub fn new(
+        schedule_repo: R,
+        target_repo: T,
+        polling_settings: PollingSettings,
+        webhook_settings: WebhookSettings,
+    ) -> Self {
+        Self {
+            schedule_repo,
+            target_repo,
+            polling_settings,
+            webhook_settings,
+        }
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::configuration::settings::PriorityLimits;
+    use crate::polling::dto::{RunPriority, StartRunRequestDto};
+    use crate::scheduling::dto::{ScheduleFiring, MAX_INTERVAL_SECONDS};
+    use crate::scheduling::schedule_repository::MockScheduleRepository;
+    use crate::targets::target_repository::MockTargetRepository;
+    use mockall::predicate::eq;
+
+    fn service(
+        schedule_repo: MockScheduleRepository,
+    ) -> SchedulingServiceImpl<MockScheduleRepository, MockTargetRepository> {
+        SchedulingServiceImpl::new(
+            schedule_repo,
+            MockTargetRepository::new(),
+            PollingSettings {
+                polling_address: "127.0.0.1:0".into(),
+                max_concurrent_runs: 1,
+                max_pending_runs: 1,
+                concurrent_requests_per_run: 3,
+                max_pending_runs_per_priority: PriorityLimits::default(),
+                priority_aging_seconds: 0,
+                max_queue_wait_seconds: None,
+                max_scheduled_runs: None,
+                max_concurrent_requests_per_run: Some(10),
+                allowed_polling_addresses: Vec::new(),
+                histogram_buckets: Vec::new(),
+                sample_interval_seconds: None,
+            },
+            WebhookSettings {
+                secret: "secret".into(),
+                check_interval_seconds: 1,
+                max_attempts: 3,
+                retry_base_seconds: 5,
+                allowed_callback_origins: Vec::new(),
+            },
+        )
+    }
+
+    fn run_spec() -> StartRunRequestDto {
+        StartRunRequestDto {
+            seconds: 30,
+            priority: RunPriority::Normal,
+            max_queue_wait_seconds: None,
+            start_at: None,
+            concurrent_requests: None,
+            polling_address: None,
+            target_id: None,
+            histogram_buckets: None,
+            aggregators: Vec::new(),
+            capture: None,
+            callback_url: None,
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_interval_schedule_correctly() {
+        let id = ScheduleId::new_v4();
+        let request = CreateScheduleRequestDto {
+            cron: None,
+            interval_seconds: Some(3600),
+            run: run_spec(),
+        };
+
+        let schedule_repo = {
+            let mut r = MockScheduleRepository::new();
+            r.expect_generate_schedule_id().return_const(id);
+            r.expect_save_schedule()
+                .withf(move |s| {
+                    s.id == id
+                        && s.interval_seconds == Some(3600)
+                        && s.next_fire_at > Utc::now() + chrono::Duration::seconds(3590)
+                })
+                .return_const(ServiceResult::Ok(()));
+            r
+        };
+
+        let service = service(schedule_repo);
+
+        let actual_result = service.create_schedule(request).await;
+        assert_eq!(Some(id), actual_result.map(|r| r.id).ok());
+    }
+
+    #[actix_rt::test]
+    async fn reject_invalid_cron_expression() {
+        let request = CreateScheduleRequestDto {
+            cron: Some("every hour".into()),
+            interval_seconds: None,
+            run: run_spec(),
+        };
+
+        let service = service(MockScheduleRepository::new());
+
+        let actual_result = service.create_schedule(request).await;
+        assert!(matches!(actual_result, Err(ServiceError::BadRequest(_))));
+    }
+
+    #[actix_rt::test]
+    async fn reject_interval_out_of_range() {
+        let service = service(MockScheduleRepository::new());
+
+        for interval_seconds in [0, MAX_INTERVAL_SECONDS + 1, u64::MAX].iter() {
+            let request = CreateScheduleRequestDto {
+                cron: None,
+                interval_seconds: Some(*interval_seconds),
+                run: run_spec(),
+            };
+
+            let actual_result = service.create_schedule(request).await;
+            assert!(matches!(actual_result, Err(ServiceError::BadRequest(_))));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn reject_invalid_run_spec() {
+        let service = service(MockScheduleRepository::new());
+
+        for run in IntoIterator::into_iter([
+            StartRunRequestDto {
+                concurrent_requests: Some(0),
+                ..run_spec()
+            },
+            StartRunRequestDto {
+                polling_address: Some("http://169.254.169.254/latest".into()),
+                ..run_spec()
+            },
+            StartRunRequestDto {
+                callback_url: Some("http://127.0.0.1:9000/done".into()),
+                ..run_spec()
+            },
+        ]) {
+            let request = CreateScheduleRequestDto {
+                cron: None,
+                interval_seconds: Some(3600),
+                run,
+            };
+
+            let actual_result = service.create_schedule(request).await;
+            assert!(matches!(actual_result, Err(ServiceError::BadRequest(_))));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn get_schedule_with_runs_and_missed_firings() {
+        let id = ScheduleId::new_v4();
+        let run_id = RunId::new_v4();
+        let now = Utc::now();
+        let schedule = Schedule {
+            id,
+            cron: Some("0 * * * *".into()),
+            interval_seconds: None,
+            run: run_spec(),
+            next_fire_at: now,
+        };
+
+        let schedule_repo = {
+            let mut r = MockScheduleRepository::new();
+            r.expect_get_schedule_by_id()
+                .with(eq(id))
+                .return_const(Ok(schedule.clone()));
+            r.expect_get_firings().with(eq(id)).return_const(Ok(vec![
+                ScheduleFiring {
+                    fired_at: now,
+                    run_id: Some(run_id),
+                },
+                ScheduleFiring {
+                    fired_at: now,
+                    run_id: None,
+                },
+            ]));
+            r
+        };
+
+        let service = service(schedule_repo);
+
+        let actual_result = service.get_schedule(id).await;
+        assert_eq!(
+            Ok(ScheduleInfo {
+                schedule,
+                runs: vec![run_id],
+                missed_firings: vec![now],
+            }),
+            actual_result
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use actix_web::{guard, web, HttpRequest, HttpResponse, Responder};
+
+use crate::configuration::settings::AdminSettings;
+use crate::polling::errors::ServiceResult;
+use crate::targets::dto::{TargetDefinition, TargetId};
+use crate::targets::target_service::TargetService;
+
+async fn create_target<T: TargetService>(
+    request: HttpRequest,
+    admin: web::Data<AdminSettings>,
+    service: web::Data<T>,
+    request_payload: web::Json<TargetDefinition>,
+) -> ServiceResult<impl Responder> {
+    admin.authorize(&request)?;
+    service
+        .create_target(request_payload.into_inner())
+        .await
+        .map(web::Json)
+}
+
+async fn get_targets<T: TargetService>(service: web::Data<T>) -> ServiceResult<impl Responder> {
+    service.get_targets().await.map(web::Json)
+}
+
+async fn get_target<T: TargetService>(
+    service: web::Data<T>,
+    id: web::Path<TargetId>,
+) -> ServiceResult<impl Responder> {
+    service.get_target(id.into_inner()).await.map(web::Json)
+}
+
+async fn update_target<T: TargetService>(
+    request: HttpRequest,
+    admin: web::Data<AdminSettings>,
+    service: web::Data<T>,
+    id: web::Path<TargetId>,
+    request_payload: web::Json<TargetDefinition>,
+) -> ServiceResult<impl Responder> {
+    admin.authorize(&request)?;
+    service
+        .update_target(id.into_inner(), request_payload.into_inner())
+        .await
+        .map(web::Json)
+}
+
+async fn delete_target<T: TargetService>(
+    request: HttpRequest,
+    admin: web::Data<AdminSettings>,
+    service: web::Data<T>,
+    id: web::Path<TargetId>,
+) -> ServiceResult<impl Responder> {
+    admin.authorize(&request)?;
+    service
+        .delete_target(id.into_inner())
+        .await
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+/// Targets are readable by anyone, changing them takes the admin token
+pub fn configure<T: 'static + TargetService>(
+    service: web::Data<T>,
+    admin: web::Data<AdminSettings>,
+    cfg: &mut web::ServiceConfig,
+) {
+    cfg.app_data(service);
+    cfg.app_data(admin);
+    cfg.route(
+        "/targets",
+        web::post()
+            .guard(guard::Header("Content-Type", "application/json"))
+            .to(create_target::<T>),
+    );
+    cfg.route("/targets", web::get().to(get_targets::<T>));
+    cfg.route("/targets/{id}", web::get().to(get_target::<T>));
+    cfg.route(
+        "/targets/{id}",
+        web::put()
+            .guard(guard::Header("Content-Type", "application/json"))
+            .to(update_target::<T>),
+    );
+    cfg.route("/targets/{id}", web::delete().to(delete_target::<T>));
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::targets::dto::Target;
+    use crate::targets::target_service::MockTargetService;
+    use actix_web::http::StatusCode;
+    use actix_web::{test, App};
+    use mockall::predicate::*;
+
+    fn admin() -> web::Data<AdminSettings> {
+        web::Data::new(AdminSettings {
+            token: Some("admin token".into()),
+        })
+    }
+
+    const BEARER: (&str, &str) = ("Authorization", "Bearer admin token");
+
+    #[actix_rt::test]
+    async fn create_new_target() {
+        let request_payload = TargetDefinition::from_url("http://127.0.0.1:8080".into());
+        let expected_response = Target {
+            id: TargetId::new_v4(),
+            definition: request_payload.clone(),
+        };
+
+        let target_service = {
+            let mut ts = MockTargetService::new();
+            ts.expect_create_target()
+                .with(eq(request_payload.clone()))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ts)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(target_service, admin(), cfg)))
+                .await;
+
+        let request = test::TestRequest::post()
+            .uri("/targets")
+            .insert_header(BEARER)
+            .set_json(&request_payload)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: Target = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+
+    #[actix_rt::test]
+    async fn update_target() {
+        let target_id = TargetId::new_v4();
+        let request_payload = TargetDefinition::from_url("http://127.0.0.1:8080".into());
+
+        let target_service = {
+            let mut ts = MockTargetService::new();
+            ts.expect_update_target()
+                .with(eq(target_id), eq(request_payload.clone()))
+                .times(1)
+                .return_const(Ok(Target {
+                    id: target_id,
+                    definition: request_payload.clone(),
+                }));
+            web::Data::new(ts)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(target_service, admin(), cfg)))
+                .await;
+
+        let request = test::TestRequest::put()
+            .uri(&format!("/targets/{}", target_id))
+            .insert_header(BEARER)
+            .set_json(&request_payload)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn delete_target() {
+        let target_id = TargetId::new_v4();
+
+        let target_service = {
+            let mut ts = MockTargetService::new();
+            ts.expect_delete_target()
+                .with(eq(target_id))
+                .times(1)
+                .return_const(Ok(()));
+            web::Data::new(ts)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(target_service, admin(), cfg)))
+                .await;
+
+        let request = test::TestRequest::delete()
+            .uri(&format!("/targets/{}", target_id))
+            .insert_header(BEARER)
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[actix_rt::test]
+    async fn refuse_changes_of_targets_without_admin_token() {
+        let target_id = TargetId::new_v4();
+        let request_payload = TargetDefinition::from_url("http://127.0.0.1:8080".into());
+
+        let target_service = {
+            let mut ts = MockTargetService::new();
+            ts.expect_create_target().never();
+            ts.expect_update_target().never();
+            ts.expect_delete_target().never();
+            web::Data::new(ts)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(target_service, admin(), cfg)))
+                .await;
+
+        let requests = [
+            test::TestRequest::post()
+                .uri("/targets")
+                .set_json(&request_payload),
+            test::TestRequest::put()
+                .uri(&format!("/targets/{}", target_id))
+                .insert_header(("Authorization", "Bearer other token"))
+                .set_json(&request_payload),
+            test::TestRequest::delete().uri(&format!("/targets/{}", target_id)),
+        ];
+        for request in IntoIterator::into_iter(requests) {
+            let response = test::call_service(&app, request.to_request()).await;
+            assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+        }
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+pub type TargetId = Uuid;
+
+/// Header set on every polling request, so the upstream can tell runs apart.
+pub const RUN_ID_HEADER: &str = "X-Run-Id";
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum HttpMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+impl HttpMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Get => "GET",
+            Self::Post => "POST",
+            Self::Put => "PUT",
+            Self::Patch => "PATCH",
+            Self::Delete => "DELETE",
+        }
+    }
+}
+
+impl std::str::FromStr for HttpMethod {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "GET" => Ok(Self::Get),
+            "POST" => Ok(Self::Post),
+            "PUT" => Ok(Self::Put),
+            "PATCH" => Ok(Self::Patch),
+            "DELETE" => Ok(Self::Delete),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<HttpMethod> for reqwest::Method {
+    fn from(method: HttpMethod) -> Self {
+        match method {
+            HttpMethod::Get => Self::GET,
+            HttpMethod::Post => Self::POST,
+            HttpMethod::Put => Self::PUT,
+            HttpMethod::Patch => Self::PATCH,
+            HttpMethod::Delete => Self::DELETE,
+        }
+    }
+}
+
+/// Format of the responses a target replies with.
+#[derive(Debug, Clone, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseSchema {
+    /// `{"value": <i64>}` on success, `{"error": <string>}` otherwise
+    #[default]
+    FaultyServer,
+    /// Arbitrary JSON, the value is located by a JSON pointer (RFC 6901), e.g. `/data/count`
+    Json {
+        value_pointer: String,
+        /// Status codes of successful responses, any 2xx if empty
+        #[serde(default)]
+        success_statuses: Vec<u16>,
+        /// A response having a non-null value here is failed
+        #[serde(default)]
+        error_pointer: Option<String>,
+    },
+}
+
+/// How to send polling requests to an upstream.
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct TargetDefinition {
+    pub url: String,
+    #[serde(default)]
+    pub method: HttpMethod,
+    /// Sent with every request, besides the run id header
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub response_schema: ResponseSchema,
+}
+
+impl TargetDefinition {
+    /// Plain `GET` requests to the address, as used by runs not naming a target.
+    pub fn from_url(url: String) -> Self {
+        Self {
+            url,
+            method: HttpMethod::default(),
+            headers: BTreeMap::new(),
+            timeout_ms: None,
+            response_schema: ResponseSchema::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Target {
+    pub id: TargetId,
+    #[serde(flatten)]
+    pub definition: TargetDefinition,
+}
@@ -0,0 +1,165 @@
+use serde_json::Value;
+
+use crate::polling::dto::FaultyServerResponse;
+use crate::targets::dto::ResponseSchema;
+
+impl ResponseSchema {
+    /// Classifies a target's response and pulls the polled value out of it.
+    pub fn extract(&self, status: u16, body: &[u8]) -> FaultyServerResponse {
+        match self {
+            Self::FaultyServer => {
+                serde_json::from_slice(body).unwrap_or_else(|e| FaultyServerResponse::Err {
+                    error: format!("Unexpected response: {}", e),
+                })
+            }
+            Self::Json {
+                value_pointer,
+                success_statuses,
+                error_pointer,
+            } => {
+                let successful = if success_statuses.is_empty() {
+                    (200..300).contains(&status)
+                } else {
+                    success_statuses.contains(&status)
+                };
+                if !successful {
+                    return FaultyServerResponse::Err {
+                        error: format!("Unsuccessful status {}", status),
+                    };
+                }
+
+                let json = match serde_json::from_slice::<Value>(body) {
+                    Ok(json) => json,
+                    Err(e) => {
+                        return FaultyServerResponse::Err {
+                            error: format!("Response is not JSON: {}", e),
+                        }
+                    }
+                };
+
+                match error_pointer.as_deref().and_then(|p| json.pointer(p)) {
+                    None | Some(Value::Null) => {}
+                    Some(Value::String(error)) => {
+                        return FaultyServerResponse::Err {
+                            error: error.clone(),
+                        }
+                    }
+                    Some(error) => {
+                        return FaultyServerResponse::Err {
+                            error: error.to_string(),
+                        }
+                    }
+                }
+
+                match json.pointer(value_pointer).map(to_value) {
+                    Some(Some(value)) => FaultyServerResponse::Ok { value },
+                    Some(None) => FaultyServerResponse::Err {
+                        error: format!("Value at {} is not a valid number", value_pointer),
+                    },
+                    None => FaultyServerResponse::Err {
+                        error: format!("No value at {}", value_pointer),
+                    },
+                }
+            }
+        }
+    }
+}
+
+/// Accepts numbers and numeric strings, as some services quote their numbers.
+fn to_value(json: &Value) -> Option<i64> {
+    match json {
+        Value::Number(number) => number.as_i64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Checks a pointer has the RFC 6901 syntax, `serde_json` silently finds nothing otherwise.
+pub fn is_valid_pointer(pointer: &str) -> bool {
+    pointer.is_empty() || pointer.starts_with('/')
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    fn json_schema(success_statuses: Vec<u16>, error_pointer: Option<&str>) -> ResponseSchema {
+        ResponseSchema::Json {
+            value_pointer: "/data/count".into(),
+            success_statuses,
+            error_pointer: error_pointer.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn extract_faulty_server_responses_regardless_of_status() {
+        assert_eq!(
+            FaultyServerResponse::Ok { value: 5 },
+            ResponseSchema::FaultyServer.extract(200, br#"{"value": 5}"#)
+        );
+        assert_eq!(
+            FaultyServerResponse::Err {
+                error: "Timed out".into()
+            },
+            ResponseSchema::FaultyServer.extract(504, br#"{"error": "Timed out"}"#)
+        );
+        assert!(matches!(
+            ResponseSchema::FaultyServer.extract(200, b"<html>"),
+            FaultyServerResponse::Err { .. }
+        ));
+    }
+
+    #[test]
+    fn extract_value_by_pointer() {
+        let schema = json_schema(Vec::new(), None);
+
+        assert_eq!(
+            FaultyServerResponse::Ok { value: 42 },
+            schema.extract(200, br#"{"data": {"count": 42}}"#)
+        );
+        assert_eq!(
+            FaultyServerResponse::Ok { value: 42 },
+            schema.extract(201, br#"{"data": {"count": "42"}}"#)
+        );
+        assert_eq!(
+            FaultyServerResponse::Ok {
+                value: -5_000_000_000
+            },
+            schema.extract(200, br#"{"data": {"count": -5000000000}}"#)
+        );
+        for body in [
+            br#"{"data": {}}"#.as_ref(),
+            br#"{"data": {"count": 9223372036854775808}}"#,
+            br#"{"data": {"count": 1.5}}"#,
+            br#"{"data": {"count": [1]}}"#,
+            b"not json",
+        ] {
+            assert!(matches!(
+                schema.extract(200, body),
+                FaultyServerResponse::Err { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn classify_responses_by_status_and_error_field() {
+        let schema = json_schema(vec![200, 404], Some("/error"));
+
+        assert_eq!(
+            FaultyServerResponse::Ok { value: 1 },
+            schema.extract(404, br#"{"data": {"count": 1}, "error": null}"#)
+        );
+        assert_eq!(
+            FaultyServerResponse::Err {
+                error: "Unsuccessful status 201".into()
+            },
+            schema.extract(201, br#"{"data": {"count": 1}}"#)
+        );
+        assert_eq!(
+            FaultyServerResponse::Err {
+                error: "Overloaded".into()
+            },
+            schema.extract(200, br#"{"data": {"count": 1}, "error": "Overloaded"}"#)
+        );
+    }
+}
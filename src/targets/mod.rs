@@ -0,0 +1,5 @@
+pub mod controller;
+pub mod dto;
+mod extraction;
+pub mod target_repository;
+pub mod target_service;
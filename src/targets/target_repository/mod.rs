@@ -0,0 +1,38 @@
+use async_trait::async_trait;
+#[cfg(test)]
+use mockall::mock;
+
+use crate::polling::errors::ServiceResult;
+use crate::targets::dto::{Target, TargetId};
+
+mod postgres_target_repository;
+pub use postgres_target_repository::PostgresTargetRepository;
+
+#[async_trait]
+pub trait TargetRepository: Clone + Send + Sync {
+    async fn generate_target_id(&self) -> TargetId;
+    async fn save_target(&self, target: &Target) -> ServiceResult<()>;
+    async fn update_target(&self, target: &Target) -> ServiceResult<()>;
+    async fn get_target_by_id(&self, target_id: TargetId) -> ServiceResult<Target>;
+    async fn get_targets(&self) -> ServiceResult<Vec<Target>>;
+    async fn delete_target(&self, target_id: TargetId) -> ServiceResult<()>;
+}
+
+#[cfg(test)]
+mock! {
+    pub TargetRepository {}
+
+    impl Clone for TargetRepository {
+        fn clone(&self) -> Self;
+    }
+
+    #[async_trait]
+    impl TargetRepository for TargetRepository {
+        async fn generate_target_id(&self) -> TargetId;
+        async fn save_target(&self, target: &Target) -> ServiceResult<()>;
+        async fn update_target(&self, target: &Target) -> ServiceResult<()>;
+        async fn get_target_by_id(&self, target_id: TargetId) -> ServiceResult<Target>;
+        async fn get_targets(&self) -> ServiceResult<Vec<Target>>;
+        async fn delete_target(&self, target_id: TargetId) -> ServiceResult<()>;
+    }
+}
@@ -0,0 +1,185 @@
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::targets::dto::{Target, TargetDefinition, TargetId};
+use crate::targets::target_repository::TargetRepository;
+
+#[derive(Clone, Debug)]
+pub struct PostgresTargetRepository {
+    db_pool: PgPool,
+}
+
+impl PostgresTargetRepository {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+}
+
+#[async_trait]
+impl TargetRepository for PostgresTargetRepository {
+    async fn generate_target_id(&self) -> TargetId {
+        TargetId::new_v4()
+    }
+
+    async fn save_target(&self, target: &Target) -> ServiceResult<()> {
+        let definition = &target.definition;
+        let (headers, response_schema) = to_json(definition)?;
+
+        sqlx::query!(
+            r#"
+            insert into target (target_id,
+                                target_url,
+                                target_method,
+                                target_headers,
+                                target_timeout_ms,
+                                target_response_schema)
+            values ($1, $2, $3, $4, $5, $6)
+            "#,
+            target.id,
+            definition.url,
+            definition.method.as_str(),
+            headers,
+            definition.timeout_ms.map(|t| t as i64),
+            response_schema,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn update_target(&self, target: &Target) -> ServiceResult<()> {
+        let definition = &target.definition;
+        let (headers, response_schema) = to_json(definition)?;
+
+        let query_result = sqlx::query!(
+            r#"
+            update target set target_url = $1,
+                              target_method = $2,
+                              target_headers = $3,
+                              target_timeout_ms = $4,
+                              target_response_schema = $5
+            where target_id = $6
+            "#,
+            definition.url,
+            definition.method.as_str(),
+            headers,
+            definition.timeout_ms.map(|t| t as i64),
+            response_schema,
+            target.id,
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        match query_result.rows_affected() {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+    async fn get_target_by_id(&self, target_id: TargetId) -> ServiceResult<Target> {
+        let row = sqlx::query!(
+            r#"
+            select t.target_url,
+                   t.target_method,
+                   t.target_headers,
+                   t.target_timeout_ms,
+                   t.target_response_schema
+            from target t
+            where t.target_id = $1;
+            "#,
+            target_id
+        )
+        .fetch_optional(&self.db_pool)
+        .await?
+        .ok_or(ServiceError::NotFound)?;
+
+        Ok(Target {
+            id: target_id,
+            definition: from_row(
+                row.target_url,
+                &row.target_method,
+                row.target_headers,
+                row.target_timeout_ms,
+                row.target_response_schema,
+            )?,
+        })
+    }
+
+    async fn get_targets(&self) -> ServiceResult<Vec<Target>> {
+        let rows = sqlx::query!(
+            r#"
+            select t.target_id,
+                   t.target_url,
+                   t.target_method,
+                   t.target_headers,
+                   t.target_timeout_ms,
+                   t.target_response_schema
+            from target t
+            order by t.target_insertion_datetime;
+            "#
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(Target {
+                    id: row.target_id,
+                    definition: from_row(
+                        row.target_url,
+                        &row.target_method,
+                        row.target_headers,
+                        row.target_timeout_ms,
+                        row.target_response_schema,
+                    )?,
+                })
+            })
+            .collect()
+    }
+
+    async fn delete_target(&self, target_id: TargetId) -> ServiceResult<()> {
+        let query_result = sqlx::query!(
+            r#"
+            delete from target
+            where target_id = $1
+            "#,
+            target_id
+        )
+        .execute(&self.db_pool)
+        .await?;
+
+        match query_result.rows_affected() {
+            0 => Err(ServiceError::NotFound),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn to_json(definition: &TargetDefinition) -> ServiceResult<(serde_json::Value, serde_json::Value)> {
+    let headers =
+        serde_json::to_value(&definition.headers).map_err(|_| ServiceError::InternalServerError)?;
+    let response_schema = serde_json::to_value(&definition.response_schema)
+        .map_err(|_| ServiceError::InternalServerError)?;
+    Ok((headers, response_schema))
+}
+
+fn from_row(
+    url: String,
+    method: &str,
+    headers: serde_json::Value,
+    timeout_ms: Option<i64>,
+    response_schema: serde_json::Value,
+) -> ServiceResult<TargetDefinition> {
+    Ok(TargetDefinition {
+        url,
+        method: method
+            .parse()
+            .map_err(|_| ServiceError::InternalServerError)?,
+        headers: serde_json::from_value(headers).map_err(|_| ServiceError::InternalServerError)?,
+        timeout_ms: timeout_ms.map(|t| t as u64),
+        response_schema: serde_json::from_value(response_schema)
+            .map_err(|_| ServiceError::InternalServerError)?,
+    })
+}
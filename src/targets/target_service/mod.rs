@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+
+pub use target_service_impl::TargetServiceImpl;
+
+use crate::polling::errors::ServiceResult;
+use crate::targets::dto::{Target, TargetDefinition, TargetId};
+
+mod target_service_impl;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait(? Send)]
+pub trait TargetService {
+    async fn create_target(&self, definition: TargetDefinition) -> ServiceResult<Target>;
+    async fn get_target(&self, target_id: TargetId) -> ServiceResult<Target>;
+    async fn get_targets(&self) -> ServiceResult<Vec<Target>>;
+    async fn update_target(
+        &self,
+        target_id: TargetId,
+        definition: TargetDefinition,
+    ) -> ServiceResult<Target>;
+    async fn delete_target(&self, target_id: TargetId) -> ServiceResult<()>;
+}
@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use reqwest::header::{HeaderName, HeaderValue};
+
+use crate::configuration::settings::PollingSettings;
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::targets::dto::{ResponseSchema, Target, TargetDefinition, TargetId, RUN_ID_HEADER};
+use crate::targets::extraction::is_valid_pointer;
+use crate::targets::target_repository::TargetRepository;
+use crate::targets::target_service::TargetService;
+
+#[derive(Clone, Debug)]
+pub struct TargetServiceImpl<R> {
+    target_repo: R,
+    settings: PollingSettings,
+}
+
+#[async_trait(? Send)]
+impl<R> TargetService for TargetServiceImpl<R>
+where
+    R: TargetRepository,
+{
+    async fn create_target(&self, definition: TargetDefinition) -> ServiceResult<Target> {
+        validate(&definition, &self.settings)?;
+
+        let target = Target {
+            id: self.target_repo.generate_target_id().await,
+            definition,
+        };
+        self.target_repo.save_target(&target).await?;
+
+        Ok(target)
+    }
+
+    async fn get_target(&self, target_id: TargetId) -> ServiceResult<Target> {
+        self.target_repo.get_target_by_id(target_id).await
+    }
+
+    async fn get_targets(&self) -> ServiceResult<Vec<Target>> {
+        self.target_repo.get_targets().await
+    }
+
+    async fn update_target(
+        &self,
+        target_id: TargetId,
+        definition: TargetDefinition,
+    ) -> ServiceResult<Target> {
+        validate(&definition, &self.settings)?;
+
+        let target = Target {
+            id: target_id,
+            definition,
+        };
+        self.target_repo.update_target(&target).await?;
+
+        Ok(target)
+    }
+
+    async fn delete_target(&self, target_id: TargetId) -> ServiceResult<()> {
+        self.target_repo.delete_target(target_id).await
+    }
+}
+
+impl<R> TargetServiceImpl<R>
+where
+    R: TargetRepository,
+{
+    pub fn new(target_repo: R, settings: PollingSettings) -> Self {
+        Self {
+            target_repo,
+            settings,
+        }
+    }
+}
+
+/// Rejects definitions requests could not be built from, or that poll addresses runs may not.
+fn validate(definition: &TargetDefinition, settings: &PollingSettings) -> ServiceResult<()> {
+    let url = reqwest::Url::parse(&definition.url)
+        .map_err(|e| ServiceError::BadRequest(format!("Invalid target URL: {}", e)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(ServiceError::BadRequest(
+            "Target URL must use http or https".into(),
+        ));
+    }
+    if !settings.is_target_url_allowed(&url) {
+        return Err(ServiceError::BadRequest(
+            "Target URL must be on one of the allowed polling addresses".into(),
+        ));
+    }
+
+    for (name, value) in &definition.headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| ServiceError::BadRequest(format!("Invalid header name: {}", name)))?;
+        if name == RUN_ID_HEADER {
+            return Err(ServiceError::BadRequest(format!(
+                "{} header is always set by the poller",
+                RUN_ID_HEADER
+            )));
+        }
+        HeaderValue::from_str(value)
+            .map_err(|_| ServiceError::BadRequest(format!("Invalid value of header {}", name)))?;
+    }
+
+    if definition.timeout_ms == Some(0) {
+        return Err(ServiceError::BadRequest("Timeout must be positive".into()));
+    }
+
+    if let ResponseSchema::Json {
+        value_pointer,
+        success_statuses,
+        error_pointer,
+    } = &definition.response_schema
+    {
+        for pointer in std::iter::once(value_pointer).chain(error_pointer) {
+            if !is_valid_pointer(pointer) {
+                return Err(ServiceError::BadRequest(format!(
+                    "Invalid JSON pointer: {}",
+                    pointer
+                )));
+            }
+        }
+        if let Some(status) = success_statuses
+            .iter()
+            .find(|status| !(100..600).contains(*status))
+        {
+            return Err(ServiceError::BadRequest(format!(
+                "Invalid status code: {}",
+                status
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::configuration::settings::PriorityLimits;
+    use crate::targets::dto::HttpMethod;
+    use crate::targets::target_repository::MockTargetRepository;
+    use mockall::predicate::eq;
+
+    fn settings() -> PollingSettings {
+        PollingSettings {
+            polling_address: "http://127.0.0.1:8080".into(),
+            max_concurrent_runs: 1,
+            max_pending_runs: 1,
+            concurrent_requests_per_run: 1,
+            max_pending_runs_per_priority: PriorityLimits::default(),
+            priority_aging_seconds: 0,
+            max_queue_wait_seconds: None,
+            max_scheduled_runs: None,
+            max_concurrent_requests_per_run: None,
+            allowed_polling_addresses: vec!["https://upstream.example.com/api".into()],
+            histogram_buckets: Vec::new(),
+            sample_interval_seconds: None,
+        }
+    }
+
+    fn definition() -> TargetDefinition {
+        TargetDefinition {
+            method: HttpMethod::Post,
+            timeout_ms: Some(500),
+            ..TargetDefinition::from_url("http://127.0.0.1:8080/poll".into())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn create_valid_target() {
+        let id = TargetId::new_v4();
+        let expected_target = Target {
+            id,
+            definition: definition(),
+        };
+
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_generate_target_id().return_const(id);
+            r.expect_save_target()
+                .with(eq(expected_target.clone()))
+                .times(1)
+                .return_const(Ok(()));
+            r
+        };
+
+        let service = TargetServiceImpl::new(target_repo, settings());
+
+        assert_eq!(
+            Ok(expected_target),
+            service.create_target(definition()).await
+        );
+    }
+
+    #[actix_rt::test]
+    async fn accept_targets_on_origins_of_allowed_polling_addresses() {
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_generate_target_id()
+                .return_const(TargetId::new_v4());
+            r.expect_save_target().times(1).return_const(Ok(()));
+            r
+        };
+
+        let service = TargetServiceImpl::new(target_repo, settings());
+
+        let definition = TargetDefinition::from_url("https://upstream.example.com/v2/poll".into());
+        assert!(service.create_target(definition).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn reject_invalid_targets() {
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_generate_target_id().never();
+            r.expect_save_target().never();
+            r
+        };
+
+        let service = TargetServiceImpl::new(target_repo, settings());
+
+        let invalid = vec![
+            TargetDefinition::from_url("not a url".into()),
+            TargetDefinition::from_url("ftp://127.0.0.1/poll".into()),
+            TargetDefinition::from_url("http://169.254.169.254/latest/meta-data".into()),
+            TargetDefinition::from_url("http://127.0.0.1:8081/poll".into()),
+            TargetDefinition::from_url("http://upstream.example.com/api".into()),
+            TargetDefinition {
+                headers: vec![("x-run-id".to_string(), "1".to_string())]
+                    .into_iter()
+                    .collect(),
+                ..definition()
+            },
+            TargetDefinition {
+                headers: vec![("Bad Header".to_string(), "1".to_string())]
+                    .into_iter()
+                    .collect(),
+                ..definition()
+            },
+            TargetDefinition {
+                timeout_ms: Some(0),
+                ..definition()
+            },
+            TargetDefinition {
+                response_schema: ResponseSchema::Json {
+                    value_pointer: "data.count".into(),
+                    success_statuses: Vec::new(),
+                    error_pointer: None,
+                },
+                ..definition()
+            },
+            TargetDefinition {
+                response_schema: ResponseSchema::Json {
+                    value_pointer: "/data/count".into(),
+                    success_statuses: vec![200, 600],
+                    error_pointer: None,
+                },
+                ..definition()
+            },
+        ];
+        for definition in invalid {
+            assert!(matches!(
+                service.create_target(definition).await,
+                Err(ServiceError::BadRequest(_))
+            ));
+        }
+    }
+
+    #[actix_rt::test]
+    async fn reply_not_found_when_updating_unknown_target() {
+        let target_repo = {
+            let mut r = MockTargetRepository::new();
+            r.expect_update_target()
+                .return_const(Err(ServiceError::NotFound));
+            r
+        };
+
+        let service = TargetServiceImpl::new(target_repo, settings());
+
+        assert_eq!(
+            Err(ServiceError::NotFound),
+            service
+                .update_target(TargetId::new_v4(), definition())
+                .await
+        );
+    }
+}
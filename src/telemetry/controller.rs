@@ -0,0 +1,154 @@
+use actix_web::{web, HttpRequest, HttpResponse, Responder};
+
+use crate::configuration::settings::AdminSettings;
+use crate::polling::errors::ServiceResult;
+use crate::telemetry::dto::LogFilterDto;
+use crate::telemetry::log_filter::LogFilter;
+
+async fn get_log_filter(
+    request: HttpRequest,
+    admin: web::Data<AdminSettings>,
+    log_filter: web::Data<LogFilter>,
+) -> ServiceResult<impl Responder> {
+    admin.authorize(&request)?;
+    log_filter
+        .current()
+        .map(|filter| web::Json(LogFilterDto { filter }))
+}
+
+async fn set_log_filter(
+    request: HttpRequest,
+    admin: web::Data<AdminSettings>,
+    log_filter: web::Data<LogFilter>,
+    log_filter_dto: web::Json<LogFilterDto>,
+) -> ServiceResult<impl Responder> {
+    admin.authorize(&request)?;
+    log_filter
+        .set(&log_filter_dto.filter)
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+async fn reset_log_filter(
+    request: HttpRequest,
+    admin: web::Data<AdminSettings>,
+    log_filter: web::Data<LogFilter>,
+) -> ServiceResult<impl Responder> {
+    admin.authorize(&request)?;
+    log_filter
+        .reset()
+        .map(|_| HttpResponse::NoContent().finish())
+}
+
+pub fn configure(
+    log_filter: web::Data<LogFilter>,
+    admin: web::Data<AdminSettings>,
+    cfg: &mut web::ServiceConfig,
+) {
+    cfg.app_data(log_filter);
+    cfg.app_data(admin);
+    cfg.service(
+        web::resource("/admin/log-filter")
+            .route(web::get().to(get_log_filter))
+            .route(web::put().to(set_log_filter))
+            .route(web::delete().to(reset_log_filter)),
+    );
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use actix_web::{test, App};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::{reload, EnvFilter};
+
+    fn log_filter() -> web::Data<LogFilter> {
+        let (filter, handle) = reload::Layer::new(EnvFilter::new("info"));
+        Box::leak(Box::new(tracing_subscriber::registry().with(filter)));
+        web::Data::new(LogFilter::new(handle, "info".into()))
+    }
+
+    fn admin(token: Option<&str>) -> web::Data<AdminSettings> {
+        web::Data::new(AdminSettings {
+            token: token.map(str::to_string),
+        })
+    }
+
+    const BEARER: (&str, &str) = ("Authorization", "Bearer admin token");
+
+    #[actix_rt::test]
+    async fn change_log_filter() {
+        let log_filter = log_filter();
+
+        let app = test::init_service(
+            App::new()
+                .configure(|cfg| configure(log_filter.clone(), admin(Some("admin token")), cfg)),
+        )
+        .await;
+
+        let request = test::TestRequest::put()
+            .uri("/admin/log-filter")
+            .insert_header(BEARER)
+            .set_json(&LogFilterDto {
+                filter: "debug".into(),
+            })
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(204, response.status().as_u16());
+
+        let request = test::TestRequest::get()
+            .uri("/admin/log-filter")
+            .insert_header(BEARER)
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        let actual_response: LogFilterDto = test::read_body_json(response).await;
+        assert_eq!("debug", actual_response.filter);
+
+        let request = test::TestRequest::put()
+            .uri("/admin/log-filter")
+            .insert_header(BEARER)
+            .set_json(&LogFilterDto {
+                filter: "[run{".into(),
+            })
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(400, response.status().as_u16());
+
+        let request = test::TestRequest::delete()
+            .uri("/admin/log-filter")
+            .insert_header(BEARER)
+            .to_request();
+        let response = test::call_service(&app, request).await;
+        assert_eq!(204, response.status().as_u16());
+        assert_eq!("info", log_filter.current().unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn refuse_requests_without_admin_token() {
+        for (token, authorization) in [
+            (Some("admin token"), None),
+            (Some("admin token"), Some("Bearer other token")),
+            (Some("admin token"), Some("admin token")),
+            (None, Some("Bearer admin token")),
+        ] {
+            let log_filter = log_filter();
+            let app = test::init_service(
+                App::new().configure(|cfg| configure(log_filter.clone(), admin(token), cfg)),
+            )
+            .await;
+
+            let mut request = test::TestRequest::put().uri("/admin/log-filter");
+            if let Some(authorization) = authorization {
+                request = request.insert_header(("Authorization", authorization));
+            }
+            let request = request
+                .set_json(&LogFilterDto {
+                    filter: "trace".into(),
+                })
+                .to_request();
+            let response = test::call_service(&app, request).await;
+
+            assert_eq!(401, response.status().as_u16());
+            assert_eq!("info", log_filter.current().unwrap());
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct LogFilterDto {
+    /// Directives in `RUST_LOG` syntax, e.g. `info,faulty_server_poller=debug`
+    pub filter: String,
+}
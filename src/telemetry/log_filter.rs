@@ -0,0 +1,83 @@
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+use crate::polling::errors::{ServiceError, ServiceResult};
+
+/// Filter of the logs and spans of the service, in `RUST_LOG` syntax, changeable at runtime.
+#[derive(Clone)]
+pub struct LogFilter {
+    handle: reload::Handle<EnvFilter, Registry>,
+    /// Filter the service started with, restored by `reset`
+    initial: String,
+}
+
+impl LogFilter {
+    pub fn new(handle: reload::Handle<EnvFilter, Registry>, initial: String) -> Self {
+        Self { handle, initial }
+    }
+
+    pub fn current(&self) -> ServiceResult<String> {
+        self.handle
+            .with_current(|filter| filter.to_string())
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    /// Directives may select spans by their fields, e.g. `[{run_id=<id>}]=trace`.
+    /// Spans opened before the change keep being filtered as they were.
+    pub fn set(&self, directives: &str) -> ServiceResult<()> {
+        let filter = EnvFilter::try_new(directives)
+            .map_err(|e| ServiceError::BadRequest(format!("Invalid log filter: {}", e)))?;
+        self.handle
+            .reload(filter)
+            .map_err(|_| ServiceError::InternalServerError)
+    }
+
+    pub fn reset(&self) -> ServiceResult<()> {
+        self.set(&self.initial)
+    }
+}
+
+impl std::fmt::Debug for LogFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LogFilter")
+            .field("initial", &self.initial)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    /// Filter of a subscriber that is not installed, so that tests do not change each other's logs
+    fn log_filter(initial: &str) -> LogFilter {
+        let (filter, handle) = reload::Layer::new(EnvFilter::new(initial));
+        // the handle only holds a weak reference to the filter, so the subscriber is leaked
+        Box::leak(Box::new(tracing_subscriber::registry().with(filter)));
+        LogFilter::new(handle, initial.to_string())
+    }
+
+    #[test]
+    fn change_and_reset_filter() {
+        let log_filter = log_filter("info");
+
+        log_filter.set("warn,faulty_server_poller=debug").unwrap();
+        assert_eq!(
+            "faulty_server_poller=debug,warn",
+            log_filter.current().unwrap()
+        );
+
+        log_filter.reset().unwrap();
+        assert_eq!("info", log_filter.current().unwrap());
+    }
+
+    #[test]
+    fn reject_invalid_filter() {
+        let log_filter = log_filter("info");
+
+        let result = log_filter.set("faulty_server_poller=loud");
+
+        assert!(matches!(result, Err(ServiceError::BadRequest(_))));
+        assert_eq!("info", log_filter.current().unwrap());
+    }
+}
@@ -0,0 +1,149 @@
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::{HeaderName, HeaderValue};
+use actix_web::Error;
+use futures::future::{self, LocalBoxFuture};
+use tracing::Instrument;
+use uuid::Uuid;
+
+/// Header identifying a request in the logs, taken from the client or generated
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+/// Longer ids given by clients are replaced, so that they cannot flood the logs
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Runs each request in a span carrying its id, logs it once served and echoes the id
+/// in `X-Request-Id`.
+pub struct RequestTracing;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestTracing
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RequestTracingMiddleware<S>;
+    type InitError = ();
+    type Future = future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        future::ok(RequestTracingMiddleware { service })
+    }
+}
+
+pub struct RequestTracingMiddleware<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for RequestTracingMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let started = Instant::now();
+        let request_id = req
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|id| id.to_str().ok())
+            .filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH)
+            .map(str::to_string)
+            .unwrap_or_else(|| Uuid::new_v4().to_string());
+        let method = req.method().to_string();
+        let path = req.path().to_string();
+        let remote_addr = req
+            .connection_info()
+            .realip_remote_addr()
+            .unwrap_or("-")
+            .to_string();
+
+        let span = tracing::info_span!(
+            "http_request",
+            request_id = %request_id,
+            method = %method,
+            path = %path
+        );
+        let response = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let mut response = response.await?;
+                tracing::info!(
+                    request_id = %request_id,
+                    method = %method,
+                    path = %path,
+                    status = response.status().as_u16(),
+                    latency_ms = started.elapsed().as_secs_f64() * 1000.0,
+                    remote_addr = %remote_addr,
+                    "Request served"
+                );
+                if let Ok(value) = HeaderValue::from_str(&request_id) {
+                    response
+                        .headers_mut()
+                        .insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+                }
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use actix_web::{test, web, App, HttpResponse};
+
+    async fn ok() -> HttpResponse {
+        HttpResponse::Ok().finish()
+    }
+
+    #[actix_rt::test]
+    async fn generate_request_id() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestTracing)
+                .route("/health_check", web::get().to(ok)),
+        )
+        .await;
+
+        let request = test::TestRequest::get().uri("/health_check").to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        let request_id = response.headers().get(REQUEST_ID_HEADER).unwrap();
+        assert!(Uuid::parse_str(request_id.to_str().unwrap()).is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn echo_request_id_of_client() {
+        let app = test::init_service(
+            App::new()
+                .wrap(RequestTracing)
+                .route("/health_check", web::get().to(ok)),
+        )
+        .await;
+
+        let request = test::TestRequest::get()
+            .uri("/health_check")
+            .insert_header((REQUEST_ID_HEADER, "client-request-1"))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert_eq!(
+            "client-request-1",
+            response.headers().get(REQUEST_ID_HEADER).unwrap()
+        );
+    }
+}
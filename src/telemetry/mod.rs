@@ -0,0 +1,93 @@
+pub mod controller;
+pub mod dto;
+pub mod log_filter;
+pub mod middleware;
+
+use std::fs::OpenOptions;
+
+use opentelemetry::sdk::export::trace::stdout;
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::KeyValue;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter};
+
+use crate::configuration::settings::{LogFormat, LoggingSettings, TelemetrySettings};
+use crate::telemetry::log_filter::LogFilter;
+
+const SERVICE_NAME: &str = "faulty-server-poller";
+/// Level of logs and spans when `RUST_LOG` is not set, sqlx logs every query at info
+const DEFAULT_FILTER: &str = "info,sqlx=warn";
+
+/// Writes logs, including the ones of the `log` crate, to stdout and exports spans as configured.
+/// Must be called from within the runtime, batch exports of OTLP spans are started on it.
+/// The returned filter of logs and spans may be changed while the service runs.
+pub fn init_telemetry(telemetry: &TelemetrySettings, logging: &LoggingSettings) -> LogFilter {
+    let filter =
+        EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_FILTER));
+    let initial_filter = filter.to_string();
+    let (filter, filter_handle) = reload::Layer::new(filter);
+    let tracer = build_tracer(telemetry).expect("Failed to build span exporter");
+
+    let (json, pretty) = match logging.format {
+        LogFormat::Json => (
+            Some(
+                fmt::layer()
+                    .json()
+                    .with_timer(fmt::time::ChronoUtc::rfc3339())
+                    .flatten_event(true)
+                    .with_current_span(true)
+                    .with_span_list(true),
+            ),
+            None,
+        ),
+        LogFormat::Pretty => (None, Some(fmt::layer())),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(json)
+        .with(pretty)
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)))
+        .init();
+
+    LogFilter::new(filter_handle, initial_filter)
+}
+
+/// Exports spans that have not been exported yet
+pub fn shutdown_telemetry() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+fn build_tracer(settings: &TelemetrySettings) -> anyhow::Result<Option<trace::Tracer>> {
+    let config = trace::config().with_resource(Resource::new(vec![KeyValue::new(
+        "service.name",
+        SERVICE_NAME,
+    )]));
+
+    let tracer = match settings {
+        TelemetrySettings::None => None,
+        TelemetrySettings::Stdout => Some(
+            stdout::new_pipeline()
+                .with_trace_config(config)
+                .install_simple(),
+        ),
+        TelemetrySettings::File { path } => {
+            let file = OpenOptions::new().create(true).append(true).open(path)?;
+            Some(
+                stdout::new_pipeline()
+                    .with_writer(file)
+                    .with_trace_config(config)
+                    .install_simple(),
+            )
+        }
+        TelemetrySettings::Otlp { endpoint } => Some(
+            opentelemetry_otlp::new_pipeline()
+                .with_endpoint(endpoint)
+                .with_trace_config(config)
+                .with_tonic()
+                .install_batch(opentelemetry::runtime::TokioCurrentThread)?,
+        ),
+    };
+    Ok(tracer)
+}
@@ -0,0 +1,74 @@
+use actix_web::{web, Responder};
+
+use crate::polling::dto::RunId;
+use crate::polling::errors::ServiceResult;
+use crate::webhooks::delivery_service::DeliveryService;
+
+async fn get_run_deliveries<T: DeliveryService>(
+    service: web::Data<T>,
+    id: web::Path<RunId>,
+) -> ServiceResult<impl Responder> {
+    service
+        .get_run_deliveries(id.into_inner())
+        .await
+        .map(web::Json)
+}
+
+pub fn configure<T: 'static + DeliveryService>(
+    service: web::Data<T>,
+    cfg: &mut web::ServiceConfig,
+) {
+    cfg.app_data(service);
+    cfg.route(
+        "/runs/{id}/deliveries",
+        web::get().to(get_run_deliveries::<T>),
+    );
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::webhooks::delivery_service::MockDeliveryService;
+    use crate::webhooks::dto::{Delivery, DeliveryAttempt, DeliveryStatus};
+    use actix_web::{test, App};
+    use mockall::predicate::*;
+
+    #[actix_rt::test]
+    async fn get_run_deliveries() {
+        let run_id = RunId::new_v4();
+        let now = chrono::Utc::now();
+        let expected_response = vec![Delivery {
+            id: 1,
+            url: "http://127.0.0.1:9000/callback".into(),
+            status: DeliveryStatus::Pending,
+            next_attempt_at: Some(now + chrono::Duration::seconds(5)),
+            attempts: vec![DeliveryAttempt {
+                attempted_at: now,
+                status: Some(503),
+                error: None,
+            }],
+        }];
+
+        let delivery_service = {
+            let mut ds = MockDeliveryService::new();
+            ds.expect_get_run_deliveries()
+                .with(eq(run_id))
+                .return_const(Ok(expected_response.clone()));
+            web::Data::new(ds)
+        };
+
+        let app =
+            test::init_service(App::new().configure(|cfg| configure(delivery_service, cfg))).await;
+
+        let request = test::TestRequest::get()
+            .uri(&format!("/runs/{}/deliveries", run_id))
+            .to_request();
+
+        let response = test::call_service(&app, request).await;
+
+        assert!(response.status().is_success());
+
+        let actual_response: Vec<Delivery> = test::read_body_json(response).await;
+        assert_eq!(expected_response, actual_response);
+    }
+}
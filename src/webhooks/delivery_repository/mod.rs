@@ -0,0 +1,60 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+#[cfg(test)]
+use mockall::mock;
+
+use crate::polling::dto::RunId;
+use crate::polling::errors::ServiceResult;
+use crate::webhooks::dto::{Delivery, DeliveryAttempt, DeliveryId, DeliveryStatus, DueDelivery};
+
+mod postgres_delivery_repository;
+pub use postgres_delivery_repository::PostgresDeliveryRepository;
+
+/// Outbox of webhook deliveries, filled when runs reach a terminal status.
+#[async_trait]
+pub trait DeliveryRepository: Clone + Send + Sync {
+    /// Pending deliveries due by `now`, postponed to `lease_until`
+    /// so that they are not claimed again while being attempted
+    async fn claim_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: usize,
+    ) -> ServiceResult<Vec<DueDelivery>>;
+    /// Records the attempt, `next_attempt_at` is only set for deliveries still pending
+    async fn save_attempt(
+        &self,
+        delivery_id: DeliveryId,
+        attempt: &DeliveryAttempt,
+        status: DeliveryStatus,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()>;
+    async fn get_deliveries(&self, run_id: RunId) -> ServiceResult<Vec<Delivery>>;
+}
+
+#[cfg(test)]
+mock! {
+    pub DeliveryRepository {}
+
+    impl Clone for DeliveryRepository {
+        fn clone(&self) -> Self;
+    }
+
+    #[async_trait]
+    impl DeliveryRepository for DeliveryRepository {
+        async fn claim_due_deliveries(
+            &self,
+            now: DateTime<Utc>,
+            lease_until: DateTime<Utc>,
+            limit: usize,
+        ) -> ServiceResult<Vec<DueDelivery>>;
+        async fn save_attempt(
+            &self,
+            delivery_id: DeliveryId,
+            attempt: &DeliveryAttempt,
+            status: DeliveryStatus,
+            next_attempt_at: Option<DateTime<Utc>>,
+        ) -> ServiceResult<()>;
+        async fn get_deliveries(&self, run_id: RunId) -> ServiceResult<Vec<Delivery>>;
+    }
+}
@@ -0,0 +1,164 @@
+use std::convert::TryInto;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::polling::dto::RunId;
+use crate::polling::errors::ServiceResult;
+use crate::webhooks::delivery_repository::DeliveryRepository;
+use crate::webhooks::dto::{Delivery, DeliveryAttempt, DeliveryId, DeliveryStatus, DueDelivery};
+
+#[derive(Clone, Debug)]
+pub struct PostgresDeliveryRepository {
+    db_pool: PgPool,
+}
+
+impl PostgresDeliveryRepository {
+    pub fn new(db_pool: PgPool) -> Self {
+        Self { db_pool }
+    }
+}
+
+#[async_trait]
+impl DeliveryRepository for PostgresDeliveryRepository {
+    async fn claim_due_deliveries(
+        &self,
+        now: DateTime<Utc>,
+        lease_until: DateTime<Utc>,
+        limit: usize,
+    ) -> ServiceResult<Vec<DueDelivery>> {
+        let rows = sqlx::query!(
+            r#"
+            update webhook_delivery d
+            set webhook_delivery_next_attempt_datetime = $1
+            where d.webhook_delivery_id in (select due.webhook_delivery_id
+                                            from webhook_delivery due
+                                            where due.status_id = $2
+                                              and due.webhook_delivery_next_attempt_datetime <= $3
+                                            order by due.webhook_delivery_next_attempt_datetime
+                                            limit $4
+                                            for update skip locked)
+            returning d.webhook_delivery_id,
+                      d.run_id,
+                      d.webhook_delivery_url,
+                      d.webhook_delivery_attempts
+            "#,
+            lease_until,
+            DeliveryStatus::Pending as i16,
+            now,
+            limit as i64,
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| DueDelivery {
+                id: row.webhook_delivery_id,
+                run_id: row.run_id,
+                url: row.webhook_delivery_url,
+                attempts: row.webhook_delivery_attempts as u32,
+            })
+            .collect())
+    }
+
+    async fn save_attempt(
+        &self,
+        delivery_id: DeliveryId,
+        attempt: &DeliveryAttempt,
+        status: DeliveryStatus,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> ServiceResult<()> {
+        let mut tx = self.db_pool.begin().await?;
+        sqlx::query!(
+            r#"
+            insert into webhook_delivery_attempt (webhook_delivery_id,
+                                                  webhook_delivery_attempt_datetime,
+                                                  webhook_delivery_attempt_status,
+                                                  webhook_delivery_attempt_error)
+            values ($1, $2, $3, $4)
+            "#,
+            delivery_id,
+            attempt.attempted_at,
+            attempt.status.map(|status| status as i16),
+            attempt.error,
+        )
+        .execute(&mut tx)
+        .await?;
+        sqlx::query!(
+            r#"
+            update webhook_delivery
+            set status_id = $1,
+                webhook_delivery_attempts = webhook_delivery_attempts + 1,
+                webhook_delivery_next_attempt_datetime = $2
+            where webhook_delivery_id = $3
+            "#,
+            status as i16,
+            next_attempt_at,
+            delivery_id,
+        )
+        .execute(&mut tx)
+        .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn get_deliveries(&self, run_id: RunId) -> ServiceResult<Vec<Delivery>> {
+        let deliveries = sqlx::query!(
+            r#"
+            select d.webhook_delivery_id,
+                   d.webhook_delivery_url,
+                   d.status_id,
+                   d.webhook_delivery_next_attempt_datetime
+            from webhook_delivery d
+            where d.run_id = $1
+            order by d.webhook_delivery_id;
+            "#,
+            run_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+        let attempts = sqlx::query!(
+            r#"
+            select a.webhook_delivery_id,
+                   a.webhook_delivery_attempt_datetime,
+                   a.webhook_delivery_attempt_status,
+                   a.webhook_delivery_attempt_error
+            from webhook_delivery_attempt a
+                     join webhook_delivery d using (webhook_delivery_id)
+            where d.run_id = $1
+            order by a.webhook_delivery_attempt_datetime;
+            "#,
+            run_id
+        )
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        deliveries
+            .into_iter()
+            .map(|row| {
+                let id = row.webhook_delivery_id;
+                let status: DeliveryStatus = row.status_id.try_into()?;
+                Ok(Delivery {
+                    id,
+                    url: row.webhook_delivery_url,
+                    next_attempt_at: row
+                        .webhook_delivery_next_attempt_datetime
+                        .filter(|_| status == DeliveryStatus::Pending),
+                    status,
+                    attempts: attempts
+                        .iter()
+                        .filter(|a| a.webhook_delivery_id == id)
+                        .map(|a| DeliveryAttempt {
+                            attempted_at: a.webhook_delivery_attempt_datetime,
+                            status: a.webhook_delivery_attempt_status.map(|s| s as u16),
+                            error: a.webhook_delivery_attempt_error.clone(),
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
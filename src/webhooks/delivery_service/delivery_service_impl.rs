@@ -0,0 +1,76 @@
+use async_trait::async_trait;
+
+use crate::polling::dto::RunId;
+use crate::polling::errors::ServiceResult;
+use crate::polling::run_repository::RunRepository;
+use crate::webhooks::delivery_repository::DeliveryRepository;
+use crate::webhooks::delivery_service::DeliveryService;
+use crate::webhooks::dto::Delivery;
+
+#[derive(Clone, Debug)]
+pub struct DeliveryServiceImpl<D, R> {
+    delivery_repo: D,
+    run_repo: R,
+}
+
+impl<D, R> DeliveryServiceImpl<D, R> {
+    pub fn new(delivery_repo: D, run_repo: R) -> Self {
+        Self {
+            delivery_repo,
+            run_repo,
+        }
+    }
+}
+
+#[async_trait(? Send)]
+impl<D, R> DeliveryService for DeliveryServiceImpl<D, R>
+where
+    D: DeliveryRepository,
+    R: RunRepository,
+{
+    async fn get_run_deliveries(&self, run_id: RunId) -> ServiceResult<Vec<Delivery>> {
+        let deliveries = self.delivery_repo.get_deliveries(run_id).await?;
+        if deliveries.is_empty() {
+            // tells unknown runs from the ones without a callback or not finished yet
+            self.run_repo.get_run_by_id(run_id).await?;
+        }
+
+        Ok(deliveries)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::errors::ServiceError;
+    use crate::polling::run_repository::MockRunRepository;
+    use crate::webhooks::delivery_repository::MockDeliveryRepository;
+    use mockall::predicate::eq;
+
+    #[actix_rt::test]
+    async fn reply_not_found_for_deliveries_of_unknown_run() {
+        let id = RunId::new_v4();
+
+        let delivery_repo = {
+            let mut d = MockDeliveryRepository::new();
+            d.expect_get_deliveries()
+                .with(eq(id))
+                .return_const(Ok(Vec::new()));
+            d
+        };
+        let run_repo = {
+            let mut r = MockRunRepository::new();
+            r.expect_get_run_by_id()
+                .with(eq(id))
+                .return_const(Err(ServiceError::NotFound));
+            r
+        };
+
+        let service = DeliveryServiceImpl::new(delivery_repo, run_repo);
+
+        assert_eq!(
+            Err(ServiceError::NotFound),
+            service.get_run_deliveries(id).await
+        );
+    }
+}
@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+
+pub use delivery_service_impl::DeliveryServiceImpl;
+
+use crate::polling::dto::RunId;
+use crate::polling::errors::ServiceResult;
+use crate::webhooks::dto::Delivery;
+
+mod delivery_service_impl;
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait(? Send)]
+pub trait DeliveryService {
+    /// Webhook deliveries of a run with their attempts, empty if it has no callback
+    async fn get_run_deliveries(&self, run_id: RunId) -> ServiceResult<Vec<Delivery>>;
+}
@@ -0,0 +1,361 @@
+use chrono::{DateTime, Utc};
+
+use crate::configuration::settings::WebhookSettings;
+use crate::polling::errors::{ServiceError, ServiceResult};
+use crate::polling::run_repository::RunRepository;
+use crate::webhooks::delivery_repository::DeliveryRepository;
+use crate::webhooks::dto::{DeliveryAttempt, DeliveryStatus, DueDelivery};
+use crate::webhooks::webhook_sender::WebhookSender;
+
+/// Most deliveries attempted per check, each claimed right before its attempt
+const DELIVERY_BATCH_SIZE: usize = 50;
+/// How long a claimed delivery is kept from other dispatchers, several times
+/// [`DELIVERY_TIMEOUT`](crate::webhooks::webhook_sender::DELIVERY_TIMEOUT)
+/// so that the run lookup and the save of the attempt fit in as well
+const DELIVERY_LEASE_SECONDS: i64 = 60;
+
+/// Periodically POSTs finished runs to their callbacks from the delivery outbox, retrying with backoff.
+pub struct WebhookDispatcher<D, R, S> {
+    delivery_repo: D,
+    run_repo: R,
+    sender: S,
+    settings: WebhookSettings,
+}
+
+impl<D, R, S> WebhookDispatcher<D, R, S>
+where
+    D: DeliveryRepository,
+    R: RunRepository,
+    S: WebhookSender,
+{
+    pub fn new(delivery_repo: D, run_repo: R, sender: S, settings: WebhookSettings) -> Self {
+        Self {
+            delivery_repo,
+            run_repo,
+            sender,
+            settings,
+        }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(
+            self.settings.check_interval_seconds,
+        ));
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = self.deliver_due(Utc::now()).await {
+                log::error!("Failed to deliver due webhooks: {}", e);
+            }
+        }
+    }
+
+    /// Delivers what is due at `now` one by one, so that a lease only has to outlast one attempt
+    pub async fn deliver_due(&self, now: DateTime<Utc>) -> ServiceResult<()> {
+        for _ in 0..DELIVERY_BATCH_SIZE {
+            let lease_until = Utc::now() + chrono::Duration::seconds(DELIVERY_LEASE_SECONDS);
+            let delivery = match self
+                .delivery_repo
+                .claim_due_deliveries(now, lease_until, 1)
+                .await?
+                .pop()
+            {
+                Some(delivery) => delivery,
+                None => break,
+            };
+
+            let id = delivery.id;
+            if let Err(e) = self.deliver(delivery).await {
+                log::error!("Failed to deliver webhook {}: {}", id, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Attempts the delivery and saves the attempt. An attempt whose payload could not be built
+    /// counts as failed, so that it is retried with backoff and given up like any other.
+    async fn deliver(&self, delivery: DueDelivery) -> ServiceResult<()> {
+        let attempt = match self.payload(&delivery).await {
+            Ok(payload) => self.sender.send(&delivery.url, &payload).await,
+            Err(e) => DeliveryAttempt {
+                attempted_at: Utc::now(),
+                status: None,
+                error: Some(format!("Failed to build payload: {}", e)),
+            },
+        };
+        let attempts = delivery.attempts + 1;
+        let (status, next_attempt_at) = if attempt.is_successful() {
+            (DeliveryStatus::Delivered, None)
+        } else if attempts >= self.settings.max_attempts {
+            log::warn!(
+                "Gave up delivering webhook {} of run {} after {} attempts",
+                delivery.id,
+                delivery.run_id,
+                attempts
+            );
+            (DeliveryStatus::Failed, None)
+        } else {
+            let delay = chrono::Duration::from_std(self.settings.retry_delay(attempts))
+                .expect("Retry delay is at most an hour");
+            (DeliveryStatus::Pending, Some(attempt.attempted_at + delay))
+        };
+
+        self.delivery_repo
+            .save_attempt(delivery.id, &attempt, status, next_attempt_at)
+            .await
+    }
+
+    async fn payload(&self, delivery: &DueDelivery) -> ServiceResult<Vec<u8>> {
+        let run = self.run_repo.get_run_by_id(delivery.run_id).await?;
+        serde_json::to_vec(&run).map_err(|_| ServiceError::InternalServerError)
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use crate::polling::dto::{Run, RunId, RunStatus};
+    use crate::polling::run_repository::MockRunRepository;
+    use crate::webhooks::delivery_repository::MockDeliveryRepository;
+    use crate::webhooks::webhook_sender::{MockWebhookSender, DELIVERY_TIMEOUT};
+    use mockall::predicate::eq;
+    use mockall::Sequence;
+
+    fn settings() -> WebhookSettings {
+        WebhookSettings {
+            secret: "secret".into(),
+            check_interval_seconds: 1,
+            max_attempts: 3,
+            retry_base_seconds: 5,
+            allowed_callback_origins: Vec::new(),
+        }
+    }
+
+    fn due_delivery(attempts: u32) -> DueDelivery {
+        DueDelivery {
+            id: 7,
+            run_id: RunId::new_v4(),
+            url: "http://127.0.0.1:9000/callback".into(),
+            attempts,
+        }
+    }
+
+    fn run_repo(run_id: RunId) -> MockRunRepository {
+        let mut r = MockRunRepository::new();
+        r.expect_get_run_by_id()
+            .with(eq(run_id))
+            .return_const(Ok(Run {
+                id: run_id,
+                status: RunStatus::Finished,
+                successful_responses_count: 10,
+                sum: 150,
+                sum_overflowed: false,
+                requested_seconds: Some(30),
+                effective_seconds: Some(30),
+                rerun_of: None,
+                concurrent_requests: None,
+                polling_address: None,
+                target_id: None,
+                aggregates: None,
+            }));
+        r
+    }
+
+    fn sender(status: u16, attempted_at: DateTime<Utc>) -> MockWebhookSender {
+        let mut s = MockWebhookSender::new();
+        s.expect_send()
+            .withf(|url, payload| {
+                url == "http://127.0.0.1:9000/callback"
+                    && serde_json::from_slice::<serde_json::Value>(payload).unwrap()["sum"] == 150
+            })
+            .times(1)
+            .return_const(DeliveryAttempt {
+                attempted_at,
+                status: Some(status),
+                error: None,
+            });
+        s
+    }
+
+    fn delivery_repo(
+        delivery: &DueDelivery,
+        now: DateTime<Utc>,
+        status: DeliveryStatus,
+        next_attempt_at: Option<DateTime<Utc>>,
+    ) -> MockDeliveryRepository {
+        let mut d = MockDeliveryRepository::new();
+        let mut seq = Sequence::new();
+        d.expect_claim_due_deliveries()
+            .withf(move |n, lease_until, limit| {
+                *n == now
+                    && *lease_until >= now + chrono::Duration::seconds(DELIVERY_LEASE_SECONDS)
+                    && *limit == 1
+            })
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(Ok(vec![delivery.clone()]));
+        d.expect_save_attempt()
+            .withf(move |id, _, s, next| *id == 7 && *s == status && *next == next_attempt_at)
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(Ok(()));
+        d.expect_claim_due_deliveries()
+            .times(1)
+            .in_sequence(&mut seq)
+            .return_const(Ok(Vec::new()));
+        d
+    }
+
+    #[test]
+    fn lease_delivery_for_longer_than_its_attempt() {
+        let attempt = chrono::Duration::from_std(DELIVERY_TIMEOUT).unwrap();
+
+        assert!(chrono::Duration::seconds(DELIVERY_LEASE_SECONDS) >= attempt * 3);
+    }
+
+    #[actix_rt::test]
+    async fn claim_next_delivery_once_previous_one_is_saved() {
+        let now = Utc::now();
+        let first = due_delivery(0);
+        let second = DueDelivery {
+            id: 8,
+            ..first.clone()
+        };
+
+        let delivery_repo = {
+            let mut d = MockDeliveryRepository::new();
+            let mut seq = Sequence::new();
+            for delivery in [first.clone(), second] {
+                let id = delivery.id;
+                d.expect_claim_due_deliveries()
+                    .withf(move |n, _, limit| *n == now && *limit == 1)
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_const(Ok(vec![delivery]));
+                d.expect_save_attempt()
+                    .withf(move |saved, _, _, _| *saved == id)
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_const(Ok(()));
+            }
+            d.expect_claim_due_deliveries()
+                .times(1)
+                .in_sequence(&mut seq)
+                .return_const(Ok(Vec::new()));
+            d
+        };
+        let sender = {
+            let mut s = MockWebhookSender::new();
+            s.expect_send().times(2).return_const(DeliveryAttempt {
+                attempted_at: now,
+                status: Some(200),
+                error: None,
+            });
+            s
+        };
+
+        let dispatcher =
+            WebhookDispatcher::new(delivery_repo, run_repo(first.run_id), sender, settings());
+
+        dispatcher.deliver_due(now).await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn mark_accepted_delivery_delivered() {
+        let now = Utc::now();
+        let delivery = due_delivery(0);
+
+        let dispatcher = WebhookDispatcher::new(
+            delivery_repo(&delivery, now, DeliveryStatus::Delivered, None),
+            run_repo(delivery.run_id),
+            sender(200, now),
+            settings(),
+        );
+
+        dispatcher.deliver_due(now).await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn retry_failed_delivery_with_backoff() {
+        let now = Utc::now();
+        let delivery = due_delivery(1);
+
+        let dispatcher = WebhookDispatcher::new(
+            delivery_repo(
+                &delivery,
+                now,
+                DeliveryStatus::Pending,
+                Some(now + chrono::Duration::seconds(10)),
+            ),
+            run_repo(delivery.run_id),
+            sender(500, now),
+            settings(),
+        );
+
+        dispatcher.deliver_due(now).await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn record_failed_attempt_when_run_cannot_be_read() {
+        let now = Utc::now();
+
+        for (delivery, expected_status) in [
+            (due_delivery(1), DeliveryStatus::Pending),
+            (due_delivery(2), DeliveryStatus::Failed),
+        ] {
+            let delivery_repo = {
+                let mut d = MockDeliveryRepository::new();
+                let mut seq = Sequence::new();
+                d.expect_claim_due_deliveries()
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_const(Ok(vec![delivery.clone()]));
+                d.expect_save_attempt()
+                    .withf(move |_, attempt, status, next| {
+                        attempt.status.is_none()
+                            && attempt.error.is_some()
+                            && *status == expected_status
+                            && next.is_some() == (expected_status == DeliveryStatus::Pending)
+                    })
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_const(Ok(()));
+                d.expect_claim_due_deliveries()
+                    .times(1)
+                    .in_sequence(&mut seq)
+                    .return_const(Ok(Vec::new()));
+                d
+            };
+            let run_repo = {
+                let mut r = MockRunRepository::new();
+                r.expect_get_run_by_id()
+                    .return_const(Err(ServiceError::InternalServerError));
+                r
+            };
+            let sender = {
+                let mut s = MockWebhookSender::new();
+                s.expect_send().never();
+                s
+            };
+
+            let dispatcher = WebhookDispatcher::new(delivery_repo, run_repo, sender, settings());
+
+            dispatcher.deliver_due(now).await.unwrap();
+        }
+    }
+
+    #[actix_rt::test]
+    async fn give_up_delivery_after_last_attempt() {
+        let now = Utc::now();
+        let delivery = due_delivery(2);
+
+        let dispatcher = WebhookDispatcher::new(
+            delivery_repo(&delivery, now, DeliveryStatus::Failed, None),
+            run_repo(delivery.run_id),
+            sender(500, now),
+            settings(),
+        );
+
+        dispatcher.deliver_due(now).await.unwrap();
+    }
+}
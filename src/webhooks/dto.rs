@@ -0,0 +1,65 @@
+use chrono::{DateTime, Utc};
+
+use crate::polling::dto::RunId;
+use crate::polling::errors::ServiceError;
+
+pub type DeliveryId = i64;
+
+/// Header carrying `sha256=<hex HMAC-SHA256 of the body>`, keyed by the webhook secret
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+#[derive(Debug, Copy, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum DeliveryStatus {
+    Pending = 0,
+    Delivered = 1,
+    /// Given up after the last attempt failed
+    Failed = 2,
+}
+
+impl std::convert::TryFrom<i16> for DeliveryStatus {
+    type Error = ServiceError;
+
+    fn try_from(value: i16) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Self::Pending),
+            1 => Ok(Self::Delivered),
+            2 => Ok(Self::Failed),
+            _ => Err(ServiceError::InternalServerError),
+        }
+    }
+}
+
+/// Delivery claimed from the outbox for its next attempt
+#[derive(Debug, Clone, PartialEq)]
+pub struct DueDelivery {
+    pub id: DeliveryId,
+    pub run_id: RunId,
+    pub url: String,
+    /// Attempts made before this one
+    pub attempts: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct DeliveryAttempt {
+    pub attempted_at: DateTime<Utc>,
+    /// Status the callback replied with, if it replied
+    pub status: Option<u16>,
+    pub error: Option<String>,
+}
+
+impl DeliveryAttempt {
+    pub fn is_successful(&self) -> bool {
+        self.error.is_none() && matches!(self.status, Some(200..=299))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Delivery {
+    pub id: DeliveryId,
+    pub url: String,
+    pub status: DeliveryStatus,
+    /// Set while the delivery is pending
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    /// Oldest first
+    pub attempts: Vec<DeliveryAttempt>,
+}
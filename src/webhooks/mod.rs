@@ -0,0 +1,6 @@
+pub mod controller;
+pub mod delivery_repository;
+pub mod delivery_service;
+pub mod dispatcher;
+pub mod dto;
+pub mod webhook_sender;
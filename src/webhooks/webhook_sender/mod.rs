@@ -0,0 +1,40 @@
+use async_trait::async_trait;
+use hmac::{Hmac, Mac, NewMac};
+use sha2::Sha256;
+
+use crate::webhooks::dto::DeliveryAttempt;
+
+mod reqwest_webhook_sender;
+pub use reqwest_webhook_sender::ReqwestWebhookSender;
+
+/// Longest a single delivery attempt may take
+pub const DELIVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+#[cfg_attr(test, mockall::automock)]
+#[async_trait]
+pub trait WebhookSender: Send + Sync {
+    /// POSTs the signed JSON payload to the callback
+    async fn send(&self, url: &str, payload: &[u8]) -> DeliveryAttempt;
+}
+
+/// Hex encoded HMAC-SHA256 of the payload
+pub fn sign(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_varkey(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(payload);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+
+    #[test]
+    fn sign_payloads_with_hmac_sha256() {
+        // test case 2 of RFC 4231
+        assert_eq!(
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            sign("Jefe", b"what do ya want for nothing?")
+        );
+    }
+}
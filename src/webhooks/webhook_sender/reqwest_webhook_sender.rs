@@ -0,0 +1,139 @@
+use async_trait::async_trait;
+use chrono::Utc;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::redirect::Policy;
+use reqwest::Client;
+
+use crate::webhooks::dto::{DeliveryAttempt, SIGNATURE_HEADER};
+use crate::webhooks::webhook_sender::{sign, WebhookSender, DELIVERY_TIMEOUT};
+
+#[derive(Clone)]
+pub struct ReqwestWebhookSender {
+    client: Client,
+    secret: String,
+}
+
+impl ReqwestWebhookSender {
+    /// Redirects are not followed, they could lead callbacks past the allowed addresses
+    pub fn new(secret: String) -> Self {
+        let client = Client::builder()
+            .redirect(Policy::none())
+            .build()
+            .expect("Failed to build webhook client");
+        Self { client, secret }
+    }
+}
+
+#[async_trait]
+impl WebhookSender for ReqwestWebhookSender {
+    async fn send(&self, url: &str, payload: &[u8]) -> DeliveryAttempt {
+        let attempted_at = Utc::now();
+        let response = self
+            .client
+            .post(url)
+            .timeout(DELIVERY_TIMEOUT)
+            .header(CONTENT_TYPE, "application/json")
+            .header(
+                SIGNATURE_HEADER,
+                format!("sha256={}", sign(&self.secret, payload)),
+            )
+            .body(payload.to_vec())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) => DeliveryAttempt {
+                attempted_at,
+                status: Some(response.status().as_u16()),
+                error: None,
+            },
+            Err(e) => DeliveryAttempt {
+                attempted_at,
+                status: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod should {
+    use super::*;
+    use httpmock::{Method, MockServer};
+
+    #[actix_rt::test]
+    async fn post_signed_payload() {
+        let payload = br#"{"id":"42"}"#;
+        let server = MockServer::start_async().await;
+        let callback = server
+            .mock_async(|when, then| {
+                when.method(Method::POST)
+                    .path("/callback")
+                    .header("Content-Type", "application/json")
+                    .header(
+                        SIGNATURE_HEADER,
+                        &format!("sha256={}", sign("secret", payload)),
+                    )
+                    .body(r#"{"id":"42"}"#);
+                then.status(204);
+            })
+            .await;
+
+        let sender = ReqwestWebhookSender::new("secret".into());
+        let attempt = sender.send(&server.url("/callback"), payload).await;
+
+        callback.assert_async().await;
+        assert!(attempt.is_successful());
+        assert_eq!((Some(204), None), (attempt.status, attempt.error));
+    }
+
+    #[actix_rt::test]
+    async fn report_unsuccessful_status() {
+        let server = MockServer::start_async().await;
+        server
+            .mock_async(|when, then| {
+                when.method(Method::POST);
+                then.status(503);
+            })
+            .await;
+
+        let sender = ReqwestWebhookSender::new("secret".into());
+        let attempt = sender.send(&server.url("/callback"), b"{}").await;
+
+        assert_eq!(Some(503), attempt.status);
+        assert!(!attempt.is_successful());
+    }
+
+    #[actix_rt::test]
+    async fn not_follow_redirects() {
+        let server = MockServer::start_async().await;
+        let redirected = server
+            .mock_async(|when, then| {
+                when.path("/internal");
+                then.status(204);
+            })
+            .await;
+        server
+            .mock_async(|when, then| {
+                when.path("/callback");
+                then.status(307).header("Location", "/internal");
+            })
+            .await;
+
+        let sender = ReqwestWebhookSender::new("secret".into());
+        let attempt = sender.send(&server.url("/callback"), b"{}").await;
+
+        redirected.assert_hits_async(0).await;
+        assert_eq!(Some(307), attempt.status);
+        assert!(!attempt.is_successful());
+    }
+
+    #[actix_rt::test]
+    async fn report_unreachable_callback() {
+        let sender = ReqwestWebhookSender::new("secret".into());
+        let attempt = sender.send("http://127.0.0.1:1/callback", b"{}").await;
+
+        assert_eq!(None, attempt.status);
+        assert!(attempt.error.is_some());
+    }
+}